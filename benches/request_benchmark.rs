@@ -0,0 +1,56 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// See LICENSE-APACHE.md and LICENSE-MIT.md in the repository root for full license information.
+
+#![allow(missing_docs)]
+
+//! # Request Parsing Benchmark
+//!
+//! This benchmark measures how quickly `Request::from_reader` parses a representative
+//! browser request from an in-memory buffer, so parser regressions can be caught without
+//! involving real sockets.
+//!
+//! ## How it works
+//!
+//! - A request line followed by fifteen typical headers is built once.
+//! - Each iteration parses the request from a byte slice.
+//! - Criterion reports the throughput in bytes per second.
+//!
+//! Run it with `cargo bench --bench request_benchmark`.
+
+use criterion::{
+    black_box, criterion_group, criterion_main, Criterion, Throughput,
+};
+use http_handle::request::Request;
+
+/// A representative request: request line plus fifteen headers.
+const REQUEST: &[u8] = b"GET /assets/css/styles.css?v=20240101 HTTP/1.1\r\n\
+Host: www.example.com\r\n\
+User-Agent: Mozilla/5.0 (X11; Linux x86_64; rv:128.0) Gecko/20100101 Firefox/128.0\r\n\
+Accept: text/css,*/*;q=0.1\r\n\
+Accept-Language: en-GB,en;q=0.7,fr;q=0.3\r\n\
+Accept-Encoding: gzip, deflate, br, zstd\r\n\
+Referer: https://www.example.com/blog/2024/01/01/hello-world.html\r\n\
+Connection: keep-alive\r\n\
+Cookie: session=3f2a9c1d8e7b6a5f4e3d2c1b0a9f8e7d; theme=dark; consent=1\r\n\
+Sec-Fetch-Dest: style\r\n\
+Sec-Fetch-Mode: no-cors\r\n\
+Sec-Fetch-Site: same-origin\r\n\
+If-Modified-Since: Mon, 01 Jan 2024 00:00:00 GMT\r\n\
+If-None-Match: \"1a2b-5f3c7e9d\"\r\n\
+Cache-Control: max-age=0\r\n\
+DNT: 1\r\n\
+\r\n";
+
+/// Benchmarks parsing of the representative request from memory.
+fn benchmark_request_parsing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("request_parsing");
+    let _ = group.throughput(Throughput::Bytes(REQUEST.len() as u64));
+    let _ = group.bench_function("from_reader", |b| {
+        b.iter(|| Request::from_reader(black_box(REQUEST)).unwrap())
+    });
+    group.finish();
+}
+
+// Criterion group and main function to set up and run the benchmark.
+criterion_group!(benches, benchmark_request_parsing);
+criterion_main!(benches);
@@ -0,0 +1,103 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// See LICENSE-APACHE.md and LICENSE-MIT.md in the repository root for full license information.
+
+#![allow(missing_docs)]
+
+//! # Round-Trip Benchmark
+//!
+//! This benchmark measures end-to-end requests per second: a `Server` is bound to an
+//! ephemeral port, and batches of concurrent clients each fetch a small file over
+//! loopback and read the full response.
+//!
+//! ## How it works
+//!
+//! - A fixture directory containing a small HTML file is created.
+//! - A server is started on `127.0.0.1:0` in a background thread.
+//! - For each configuration, `workers` client threads issue `REQUESTS_PER_WORKER`
+//!   sequential GET requests each.
+//! - Criterion reports the throughput in requests per second.
+//!
+//! The server currently spawns one thread per connection, so `workers` controls the
+//! number of connections in flight at once. Compare configurations with
+//! `cargo bench --bench roundtrip_benchmark`.
+
+use criterion::{
+    criterion_group, criterion_main, BenchmarkId, Criterion, Throughput,
+};
+use http_handle::Server;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread;
+use tempfile::TempDir;
+
+/// Number of sequential requests each client thread issues per iteration.
+const REQUESTS_PER_WORKER: usize = 8;
+
+/// Worker-thread counts compared by the benchmark.
+const WORKER_COUNTS: [usize; 4] = [1, 4, 16, 64];
+
+/// Fetches `/index.html` from `address` and returns the response size.
+fn fetch(address: SocketAddr) -> usize {
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(
+        stream,
+        "GET /index.html HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+    )
+    .unwrap();
+
+    let mut buffer = Vec::new();
+    let _ = stream.read_to_end(&mut buffer).unwrap();
+    assert!(buffer.starts_with(b"HTTP/1.1 200 OK"));
+    buffer.len()
+}
+
+/// Benchmarks concurrent GET round-trips for several worker-thread counts.
+fn benchmark_roundtrip(c: &mut Criterion) {
+    let fixture = TempDir::new().unwrap();
+    fs::write(
+        fixture.path().join("index.html"),
+        "<html><body>Round trip</body></html>",
+    )
+    .unwrap();
+
+    let server =
+        Server::new("127.0.0.1:0", fixture.path().to_str().unwrap());
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let _server_thread = thread::spawn(move || {
+        let _ = server.serve(listener);
+    });
+
+    let mut group = c.benchmark_group("roundtrip");
+    for workers in WORKER_COUNTS {
+        let _ = group.throughput(Throughput::Elements(
+            (workers * REQUESTS_PER_WORKER) as u64,
+        ));
+        let _ = group.bench_with_input(
+            BenchmarkId::from_parameter(workers),
+            &workers,
+            |b, &workers| {
+                b.iter(|| {
+                    let handles: Vec<_> = (0..workers)
+                        .map(|_| {
+                            thread::spawn(move || {
+                                for _ in 0..REQUESTS_PER_WORKER {
+                                    let _ = fetch(address);
+                                }
+                            })
+                        })
+                        .collect();
+                    for handle in handles {
+                        handle.join().unwrap();
+                    }
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+// Criterion group and main function to set up and run the benchmark.
+criterion_group!(benches, benchmark_roundtrip);
+criterion_main!(benches);
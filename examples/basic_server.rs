@@ -12,5 +12,7 @@
 
 fn main() -> std::io::Result<()> {
     let server = Server::new("127.0.0.1:8080", "./public");
+    println!("❯ Serving ./public at http://127.0.0.1:8080");
+    println!("  Press Ctrl+C to stop the server.");
     server.start()
 }
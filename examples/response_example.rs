@@ -70,11 +70,9 @@ fn handle_client(stream: &mut TcpStream) -> Result<(), ServerError> {
     println!("🦀 Server: Handling incoming connection...");
 
     // Create an HTTP response with a 200 OK status
-    let mut response =
-        Response::new(200, "OK", b"<h1>Hello, World!</h1>".to_vec());
+    let mut response = Response::html(200, "<h1>Hello, World!</h1>");
 
     // Add headers to the response
-    response.add_header("Content-Type", "text/html");
     response
         .add_header("Content-Length", &response.body.len().to_string());
 
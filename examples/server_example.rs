@@ -18,9 +18,9 @@
 //! cargo run --example server_example
 //! ```
 
+use http_handle::connection::Connection;
 use http_handle::request::Request;
 use http_handle::ServerError;
-use std::fs;
 use std::io::Result;
 use std::io::{Read, Write};
 use std::net::{TcpListener, TcpStream};
@@ -145,13 +145,13 @@ fn simulate_client_request() -> Result<()> {
 ///
 /// A `Result` indicating success or a `ServerError`.
 fn handle_connection(
-    mut stream: TcpStream,
+    stream: TcpStream,
     document_root: &Path,
 ) -> std::result::Result<(), ServerError> {
-    let request = Request::from_stream(&stream)?;
+    let mut conn = Connection::new(stream);
+    let request = conn.recv_request()?;
     let response = generate_response(&request, document_root)?;
-    response.send(&mut stream)?;
-    stream.flush()?;
+    conn.send_response(&response)?;
     Ok(())
 }
 
@@ -179,20 +179,8 @@ fn generate_response(
     }
 
     if path.is_file() {
-        let contents = fs::read(&path).map_err(ServerError::Io)?;
-        let content_type = "text/html"; // Simplified content type handling for example
-        let mut response =
-            http_handle::response::Response::new(200, "OK", contents);
-        response.add_header("Content-Type", content_type);
-        Ok(response)
+        http_handle::response::Response::from_file(&path)
     } else {
-        let not_found_body = b"404 Not Found".to_vec();
-        let mut response = http_handle::response::Response::new(
-            404,
-            "Not Found",
-            not_found_body,
-        );
-        response.add_header("Content-Type", "text/plain");
-        Ok(response)
+        Ok(http_handle::response::Response::text(404, "404 Not Found"))
     }
 }
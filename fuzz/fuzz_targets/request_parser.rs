@@ -0,0 +1,22 @@
+// SPDX-License-Identifier: Apache-2.0 OR MIT
+// See LICENSE-APACHE.md and LICENSE-MIT.md in the repository root for full license information.
+
+//! Feeds arbitrary bytes to the request parser.
+//!
+//! Parsing must never panic: every input either yields a `Request` or a `ServerError`.
+//! Parsed requests are also formatted and queried, so that accessors are exercised on
+//! whatever the parser accepts.
+
+#![no_main]
+
+use http_handle::request::Request;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(request) = Request::from_reader(data) {
+        let _ = request.to_string();
+        let _ = request.host();
+        let _ = request.target();
+        let _ = request.header("Content-Length");
+    }
+});
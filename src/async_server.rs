@@ -0,0 +1,614 @@
+// src/async_server.rs
+
+//! Asynchronous server module built on Tokio.
+//!
+//! This module provides `AsyncServer`, an alternative to the blocking, thread-per-connection
+//! [`Server::start`] that serves each connection as a Tokio task. Idle connections then cost
+//! a small task rather than an operating system thread, which lets a single process hold
+//! many concurrent connections cheaply.
+//!
+//! The `AsyncServer` reuses the configuration and the response generation of [`Server`].
+//! Connections are kept alive as on the blocking server, up to the same request cap and
+//! read timeout, and responses go through the same processing: interceptors, middleware,
+//! configured headers, `Server-Timing`, live reload and the access log. WebSocket
+//! upgrades are completed too, with the handler then running on Tokio's blocking
+//! thread pool. File system access and request handlers also run on that pool to keep
+//! the reactor responsive, and a handler that panics is answered with
+//! `500 Internal Server Error`.
+//!
+//! Some options only apply to the blocking server: the connection limit, zero-copy
+//! `sendfile(2)`, Unix domain sockets, TLS, file watching and graceful shutdown through
+//! a [`ShutdownHandle`](crate::ShutdownHandle), which upgraded handlers see as never
+//! stopped.
+//!
+//! The module also exposes `read_request` and `send_response`, async counterparts of
+//! [`Request::from_stream`] and [`Response::send`] that work over any
+//! `AsyncRead`/`AsyncWrite` stream.
+//!
+//! This module is only available with the `async` feature.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use http_handle::{AsyncServer, Server};
+//!
+//! let server = AsyncServer::new(Server::new("127.0.0.1:8080", "./public"));
+//! let runtime = tokio::runtime::Runtime::new().unwrap();
+//! runtime.block_on(server.start()).unwrap();
+//! ```
+
+use crate::error::ServerError;
+use crate::request::{Request, RequestParser, MAX_BODY_LENGTH};
+use crate::response::Response;
+use crate::server::{
+    AccessLogEntry, ConnectionSummary, Server, ShutdownHandle,
+};
+use crate::websocket::{self, UpgradedStream};
+use log::{debug, error, info, warn};
+use socket2::SockRef;
+use std::io;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+use tokio::io::{
+    AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncWrite,
+    AsyncWriteExt, BufReader,
+};
+use tokio::net::{TcpListener, TcpStream};
+
+/// Timeout duration for reading a request head (in seconds).
+const TIMEOUT_SECONDS: u64 = 30;
+
+/// An asynchronous HTTP server serving connections as Tokio tasks.
+#[derive(Clone, Debug)]
+pub struct AsyncServer {
+    server: Arc<Server>,
+}
+
+impl AsyncServer {
+    /// Creates a new `AsyncServer` from a `Server` configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `server` - The `Server` holding the address, document root and options.
+    ///
+    /// # Returns
+    ///
+    /// A new `AsyncServer` instance.
+    pub fn new(server: Server) -> Self {
+        AsyncServer {
+            server: Arc::new(server),
+        }
+    }
+
+    /// Binds the configured address and serves connections until an error occurs.
+    ///
+    /// The listening socket is bound with the same backlog and address reuse options as
+    /// [`Server::start`]. This must be called from within a Tokio runtime.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success or an I/O error.
+    pub async fn start(&self) -> io::Result<()> {
+        let listener = self.server.bind()?;
+        listener.set_nonblocking(true)?;
+        self.serve(TcpListener::from_std(listener)?).await
+    }
+
+    /// Serves connections accepted from an already bound Tokio listener.
+    ///
+    /// # Arguments
+    ///
+    /// * `listener` - The bound listener to accept connections from.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success or an I/O error.
+    pub async fn serve(&self, listener: TcpListener) -> io::Result<()> {
+        info!(
+            "Async server is now running at http://{}",
+            listener.local_addr()?
+        );
+
+        loop {
+            match listener.accept().await {
+                Ok((stream, peer)) => {
+                    debug!("Connection accepted from {}", peer);
+                    if let Err(e) = self
+                        .server
+                        .configure_stream(SockRef::from(&stream))
+                    {
+                        warn!("Failed to configure connection: {}", e);
+                    }
+                    let server = Arc::clone(&self.server);
+                    // Detach the task: it runs to completion on its own
+                    drop(tokio::spawn(async move {
+                        if let Err(e) =
+                            handle_connection(stream, server).await
+                        {
+                            error!("Error handling connection: {}", e);
+                        }
+                    }));
+                }
+                Err(e) => warn!("Connection error: {}", e),
+            }
+        }
+    }
+}
+
+/// Reads and parses a request from an asynchronous stream.
+///
+/// The request is parsed incrementally as data arrives, with the same rules as
+/// [`Request::from_stream`], including the body announced by `Content-Length`. Any data
+/// read past the end of the request is discarded.
+///
+/// # Arguments
+///
+/// * `reader` - The stream to read the request from.
+///
+/// # Returns
+///
+/// * `Ok(Request)` - If the request is valid and successfully parsed.
+/// * `Err(ServerError)` - If the request is malformed, too large, or cannot be read in time.
+pub async fn read_request<R: AsyncRead + Unpin>(
+    reader: &mut R,
+) -> Result<Request, ServerError> {
+    parse_request(
+        &mut BufReader::new(reader),
+        MAX_BODY_LENGTH,
+        Duration::from_secs(TIMEOUT_SECONDS),
+    )
+    .await
+}
+
+/// Reads and parses a request from a buffered stream, leaving any data past its end
+/// in the buffer.
+///
+/// # Arguments
+///
+/// * `reader` - The buffered stream to read the request from.
+/// * `max_body_length` - The largest body accepted, in bytes.
+/// * `timeout` - The longest time to wait for the whole request.
+///
+/// # Returns
+///
+/// * `Ok(Request)` - If the request is valid and successfully parsed.
+/// * `Err(ServerError)` - If the request is malformed, too large, or cannot be read in time.
+async fn parse_request<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    max_body_length: u64,
+    timeout: Duration,
+) -> Result<Request, ServerError> {
+    let mut parser = RequestParser::new(max_body_length);
+
+    let read_request = async {
+        while !parser.is_done() {
+            let data = reader.fill_buf().await?;
+            if data.is_empty() {
+                break;
+            }
+            let consumed = parser.feed(data);
+            reader.consume(consumed);
+        }
+        parser.finish()
+    };
+
+    tokio::time::timeout(timeout, read_request)
+        .await
+        .map_err(|_| {
+            ServerError::Timeout(
+                "Timed out reading request".to_string(),
+            )
+        })?
+}
+
+/// Waits for the first bytes of the next request on a connection.
+///
+/// # Arguments
+///
+/// * `reader` - The buffered client connection.
+/// * `timeout` - The longest time to wait.
+///
+/// # Returns
+///
+/// `true` if data arrived, or `false` if the connection was closed, failed or stayed
+/// idle for the whole timeout.
+async fn wait_for_request<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    timeout: Duration,
+) -> bool {
+    matches!(
+        tokio::time::timeout(timeout, reader.fill_buf()).await,
+        Ok(Ok(data)) if !data.is_empty()
+    )
+}
+
+/// Sends a response over an asynchronous stream.
+///
+/// The response is serialized exactly as [`Response::send`] would write it, then written
+/// in a single call and flushed.
+///
+/// # Arguments
+///
+/// * `response` - The response to send.
+/// * `writer` - The stream to write the response to.
+///
+/// # Returns
+///
+/// * `Ok(())` - If the response is successfully sent.
+/// * `Err(ServerError)` - If an error occurs while sending the response.
+pub async fn send_response<W: AsyncWrite + Unpin>(
+    response: &Response,
+    writer: &mut W,
+) -> Result<(), ServerError> {
+    writer.write_all(&response.into_bytes()).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Handles a client connection asynchronously, until it is closed or no longer kept
+/// alive.
+///
+/// # Arguments
+///
+/// * `stream` - The accepted client connection.
+/// * `server` - The shared `Server` configuration used to answer requests.
+///
+/// # Returns
+///
+/// A `Result` indicating success or a `ServerError`.
+async fn handle_connection(
+    stream: TcpStream,
+    server: Arc<Server>,
+) -> Result<(), ServerError> {
+    let mut summary = ConnectionSummary::new(
+        stream.peer_addr().ok().map(|address| address.to_string()),
+    );
+    let mut reader =
+        BufReader::with_capacity(server.read_buffer_size, stream);
+
+    loop {
+        // Before a request starts, a closed or idle connection ends quietly
+        if !wait_for_request(&mut reader, server.read_timeout).await {
+            return Ok(());
+        }
+        summary.requests += 1;
+        let start = Instant::now();
+
+        let mut request = match parse_request(
+            &mut reader,
+            server.max_body_size,
+            server.read_timeout,
+        )
+        .await
+        {
+            Ok(request) => request,
+            Err(e) => {
+                // Best effort: the client may already have gone away
+                let mut response = server.error_response(&e);
+                server.apply_response_headers(&mut response);
+                response.add_header("Connection", "close");
+                let _ =
+                    send_response(&response, reader.get_mut()).await;
+                return Err(e);
+            }
+        };
+        server.intercept_request(&mut request);
+        let mut keep_alive =
+            server.keeps_alive(&request, summary.requests);
+
+        let upgrade = server.websocket_handler(&request).cloned();
+        let mut response = if upgrade.is_some() {
+            websocket::handshake_response(&request).unwrap_or_else(
+                |e| {
+                    warn!(
+                        "WebSocket handshake {} failed: {}",
+                        request, e
+                    );
+                    server.error_response(&e)
+                },
+            )
+        } else if let Some(response) =
+            server.health_check_response(&request)
+        {
+            response
+        } else {
+            let blocking_request = request.clone();
+            let blocking_server = Arc::clone(&server);
+            match tokio::task::spawn_blocking(move || {
+                blocking_server.dispatch(&blocking_request)
+            })
+            .await
+            {
+                Ok(response) => response,
+                Err(e) => {
+                    error!("Handler for {} failed: {}", request, e);
+                    keep_alive = false;
+                    server.error_response(&ServerError::Custom(
+                        format!("Handler failed: {}", e),
+                    ))
+                }
+            }
+        };
+        let (cache, upgraded) = server.finish_response(
+            &request,
+            &mut response,
+            start,
+            keep_alive,
+            upgrade.is_some(),
+        );
+
+        send_response(&response, reader.get_mut()).await?;
+        let bytes = if response.allows_body() {
+            response.body.len() as u64
+        } else {
+            0
+        };
+        summary.bytes += bytes;
+        server.log_access(&AccessLogEntry {
+            time: SystemTime::now(),
+            request: &request,
+            status: response.status_code,
+            bytes,
+            duration: start.elapsed(),
+            remote_addr: summary.remote_addr.as_deref(),
+            cache,
+        });
+
+        if let Some(handler) = upgrade.filter(|_| upgraded) {
+            // Hand over the connection, as a blocking socket, with anything the
+            // client sent after the request
+            let buffered = reader.buffer().to_vec();
+            let stream = reader.into_inner().into_std()?;
+            stream.set_nonblocking(false)?;
+            let upgraded_stream = UpgradedStream::new(
+                buffered,
+                Box::new(stream),
+                ShutdownHandle::new(),
+            );
+            tokio::task::spawn_blocking(move || {
+                (handler.0)(&request, upgraded_stream)
+            })
+            .await
+            .map_err(|e| {
+                ServerError::Custom(format!(
+                    "WebSocket handler failed: {}",
+                    e
+                ))
+            })?;
+            return Ok(());
+        }
+        if !keep_alive {
+            let _ = reader.get_mut().shutdown().await;
+            return Ok(());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+    use tokio::io::AsyncReadExt;
+
+    fn runtime() -> tokio::runtime::Runtime {
+        tokio::runtime::Builder::new_multi_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_read_request() {
+        let mut input: &[u8] =
+            b"GET /index.html HTTP/1.1\r\nHost: localhost\r\n\r\n";
+        let request =
+            runtime().block_on(read_request(&mut input)).unwrap();
+
+        assert_eq!(request.method(), "GET");
+        assert_eq!(request.path(), "/index.html");
+        assert_eq!(request.host(), Some("localhost"));
+    }
+
+    #[test]
+    fn test_read_request_with_body() {
+        let mut input: &[u8] =
+            b"POST /submit HTTP/1.1\r\nContent-Length: 7\r\n\r\na=1&b=2";
+        let request =
+            runtime().block_on(read_request(&mut input)).unwrap();
+        assert_eq!(request.body(), b"a=1&b=2");
+
+        let mut input: &[u8] =
+            b"POST /submit HTTP/1.1\r\nContent-Length: 10\r\n\r\nshort";
+        let result = runtime().block_on(read_request(&mut input));
+        assert!(matches!(result, Err(ServerError::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn test_read_request_too_long() {
+        let mut data = b"GET /".to_vec();
+        data.extend(vec![b'a'; 64 * 1024]);
+        let mut input: &[u8] = &data;
+
+        let result = runtime().block_on(read_request(&mut input));
+        assert!(matches!(result, Err(ServerError::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn test_send_response_matches_blocking_send() {
+        let mut response = Response::new(200, "OK", b"Hello".to_vec());
+        response.add_header("Content-Type", "text/plain");
+
+        let mut expected = Vec::new();
+        response.send(&mut expected).unwrap();
+
+        let mut written = Vec::new();
+        runtime()
+            .block_on(send_response(&response, &mut written))
+            .unwrap();
+        assert_eq!(written, expected);
+    }
+
+    #[test]
+    fn test_async_server_serves_file() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("index.html"),
+            b"<h1>Async</h1>",
+        )
+        .unwrap();
+        let server = AsyncServer::new(Server::new(
+            "127.0.0.1:0",
+            temp_dir.path().to_str().unwrap(),
+        ));
+
+        runtime().block_on(async move {
+            let listener =
+                TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let server_task =
+                tokio::spawn(
+                    async move { server.serve(listener).await },
+                );
+
+            let mut client = TcpStream::connect(addr).await.unwrap();
+            client
+                .write_all(
+                    b"GET / HTTP/1.1\r\nHost: localhost\r\n\
+                      Connection: close\r\n\r\n",
+                )
+                .await
+                .unwrap();
+            let mut response = Vec::new();
+            let _ = client.read_to_end(&mut response).await.unwrap();
+
+            let response = String::from_utf8(response).unwrap();
+            assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+            assert!(response.ends_with("<h1>Async</h1>"));
+            server_task.abort();
+        });
+    }
+
+    /// Serves `server` on a local port and sends `raw` over one connection,
+    /// returning everything received until the server closes it.
+    fn exchange(server: Server, raw: &'static [u8]) -> String {
+        let server = AsyncServer::new(server);
+        runtime().block_on(async move {
+            let listener =
+                TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let addr = listener.local_addr().unwrap();
+            let server_task =
+                tokio::spawn(
+                    async move { server.serve(listener).await },
+                );
+
+            let mut client = TcpStream::connect(addr).await.unwrap();
+            client.write_all(raw).await.unwrap();
+            let mut response = Vec::new();
+            let _ = tokio::time::timeout(
+                Duration::from_secs(5),
+                client.read_to_end(&mut response),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+            server_task.abort();
+            String::from_utf8(response).unwrap()
+        })
+    }
+
+    #[test]
+    fn test_async_keep_alive() {
+        let temp_dir = TempDir::new().unwrap();
+        fs::write(
+            temp_dir.path().join("index.html"),
+            b"<h1>Async</h1>",
+        )
+        .unwrap();
+        let server = Server::new(
+            "127.0.0.1:0",
+            temp_dir.path().to_str().unwrap(),
+        );
+
+        let response = exchange(
+            server,
+            b"GET / HTTP/1.1\r\n\r\nGET /missing HTTP/1.1\r\n\r\n\
+              GET / HTTP/1.1\r\nConnection: close\r\n\r\n",
+        );
+        let statuses: Vec<&str> = response
+            .split("HTTP/1.1 ")
+            .skip(1)
+            .map(|response| &response[..3])
+            .collect();
+        assert_eq!(statuses, ["200", "404", "200"]);
+        assert_eq!(response.matches("Connection: close").count(), 1);
+        assert!(response.ends_with("<h1>Async</h1>"));
+    }
+
+    #[test]
+    fn test_async_head_drops_handler_body() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut server = Server::new(
+            "127.0.0.1:0",
+            temp_dir.path().to_str().unwrap(),
+        );
+        server.set_fallback(|_: &Request| -> Response {
+            Response::text(200, "dynamic")
+        });
+
+        let response = exchange(
+            server,
+            b"HEAD /page HTTP/1.1\r\nConnection: close\r\n\r\n",
+        );
+        assert!(
+            response.starts_with("HTTP/1.1 200 OK\r\n"),
+            "{}",
+            response
+        );
+        assert!(response.contains("Content-Length: 7\r\n"));
+        assert!(response.ends_with("\r\n\r\n"), "{}", response);
+    }
+
+    #[test]
+    fn test_async_handler_panic_returns_500() {
+        let temp_dir = TempDir::new().unwrap();
+        let mut server = Server::new(
+            "127.0.0.1:0",
+            temp_dir.path().to_str().unwrap(),
+        );
+        server.set_fallback(|_: &Request| -> Response {
+            panic!("handler bug")
+        });
+
+        let response = exchange(server, b"GET /boom HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with("HTTP/1.1 500 "), "{}", response);
+        assert!(response.contains("Connection: close\r\n"));
+    }
+
+    #[test]
+    fn test_async_websocket_upgrade() {
+        use std::io::Write;
+
+        let temp_dir = TempDir::new().unwrap();
+        let mut server = Server::new(
+            "127.0.0.1:0",
+            temp_dir.path().to_str().unwrap(),
+        );
+        server.set_websocket_handler(
+            "/live",
+            |_: &Request, mut stream: UpgradedStream| {
+                stream.write_all(b"upgraded").unwrap();
+            },
+        );
+
+        let response = exchange(
+            server,
+            b"GET /live HTTP/1.1\r\nHost: localhost\r\n\
+              Upgrade: websocket\r\nConnection: Upgrade\r\n\
+              Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+              Sec-WebSocket-Version: 13\r\n\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 101 "), "{}", response);
+        assert!(response.ends_with("\r\n\r\nupgraded"));
+    }
+}
@@ -0,0 +1,194 @@
+// src/cache.rs
+
+//! In-memory file cache module for serving frequently requested files without disk reads.
+//!
+//! The cache keeps the contents of recently served files up to a total size. Each entry
+//! records the length and modification time of the file it was read from, and is only
+//! used while the file on disk still has both, so edits are picked up on the next
+//! request. When a new file does not fit, the oldest entries are evicted first.
+
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::SystemTime;
+
+/// Whether a file was served from the cache.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum CacheStatus {
+    /// The contents were found in the cache.
+    Hit,
+    /// The contents were read from disk.
+    Miss,
+}
+
+impl CacheStatus {
+    /// Returns the status as sent in the `X-Cache` header (`HIT` or `MISS`).
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            CacheStatus::Hit => "HIT",
+            CacheStatus::Miss => "MISS",
+        }
+    }
+}
+
+/// A cached copy of a file.
+#[derive(Debug)]
+struct CachedFile {
+    /// The contents of the file.
+    contents: Vec<u8>,
+    /// The modification time of the file when it was read.
+    modified: Option<SystemTime>,
+}
+
+/// The entries of a cache, with their insertion order.
+#[derive(Debug, Default)]
+struct Entries {
+    /// The cached files by path.
+    files: HashMap<PathBuf, CachedFile>,
+    /// The cached paths, oldest first.
+    order: VecDeque<PathBuf>,
+    /// The total size of the cached contents, in bytes.
+    size: usize,
+}
+
+/// A size-bounded cache of file contents, shared by every connection of a server.
+#[derive(Debug)]
+pub(crate) struct FileCache {
+    /// The largest total size of the cached contents, in bytes.
+    capacity: usize,
+    /// The cached files.
+    entries: Mutex<Entries>,
+}
+
+impl FileCache {
+    /// Creates an empty cache.
+    ///
+    /// # Arguments
+    ///
+    /// * `capacity` - The largest total size of the cached contents, in bytes.
+    pub(crate) fn new(capacity: usize) -> Self {
+        FileCache {
+            capacity,
+            entries: Mutex::new(Entries::default()),
+        }
+    }
+
+    /// Reads a file, from the cache when it holds a current copy.
+    ///
+    /// A file read from disk is added to the cache if it is no larger than the
+    /// capacity, evicting the oldest entries to make room.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file to read.
+    /// * `metadata` - The current metadata of the file.
+    ///
+    /// # Returns
+    ///
+    /// The contents of the file and whether they came from the cache, or the error
+    /// raised while reading the file.
+    pub(crate) fn read(
+        &self,
+        path: &Path,
+        metadata: &fs::Metadata,
+    ) -> std::io::Result<(Vec<u8>, CacheStatus)> {
+        let modified = metadata.modified().ok();
+        {
+            let entries = self
+                .entries
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            if let Some(cached) = entries.files.get(path) {
+                if cached.modified == modified
+                    && cached.contents.len() as u64 == metadata.len()
+                {
+                    return Ok((
+                        cached.contents.clone(),
+                        CacheStatus::Hit,
+                    ));
+                }
+            }
+        }
+
+        let contents = fs::read(path)?;
+        if contents.len() <= self.capacity {
+            let mut entries = self
+                .entries
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            entries.remove(path);
+            while entries.size + contents.len() > self.capacity {
+                match entries.order.front().cloned() {
+                    Some(oldest) => entries.remove(&oldest),
+                    None => break,
+                }
+            }
+            entries.size += contents.len();
+            entries.order.push_back(path.to_path_buf());
+            let _ = entries.files.insert(
+                path.to_path_buf(),
+                CachedFile {
+                    contents: contents.clone(),
+                    modified,
+                },
+            );
+        }
+        Ok((contents, CacheStatus::Miss))
+    }
+}
+
+impl Entries {
+    /// Removes the entry for a path, if cached.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the entry to remove.
+    fn remove(&mut self, path: &Path) {
+        if let Some(cached) = self.files.remove(path) {
+            self.size -= cached.contents.len();
+            self.order.retain(|cached_path| cached_path != path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_and_evict() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let first = temp_dir.path().join("first.txt");
+        let second = temp_dir.path().join("second.txt");
+        fs::write(&first, "0123456789").unwrap();
+        fs::write(&second, "abcdefghij").unwrap();
+        let cache = FileCache::new(15);
+        let read = |path: &Path| {
+            cache.read(path, &fs::metadata(path).unwrap()).unwrap()
+        };
+
+        assert_eq!(
+            read(&first),
+            (b"0123456789".to_vec(), CacheStatus::Miss)
+        );
+        assert_eq!(read(&first).1, CacheStatus::Hit);
+
+        // Caching the second file evicts the first
+        assert_eq!(read(&second).1, CacheStatus::Miss);
+        assert_eq!(read(&second).1, CacheStatus::Hit);
+        assert_eq!(read(&first).1, CacheStatus::Miss);
+
+        // A changed file is read again
+        fs::write(&first, "changed").unwrap();
+        assert_eq!(
+            read(&first),
+            (b"changed".to_vec(), CacheStatus::Miss)
+        );
+
+        // Files larger than the cache are never cached
+        fs::write(&second, "a much longer file").unwrap();
+        assert_eq!(read(&second).1, CacheStatus::Miss);
+        assert_eq!(read(&second).1, CacheStatus::Miss);
+    }
+}
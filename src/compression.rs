@@ -0,0 +1,221 @@
+// src/compression.rs
+
+//! Compression module for encoding response bodies.
+//!
+//! This module decides which responses are worth compressing and encodes their bodies
+//! with gzip or Brotli. Only textual content types are compressed, since images and
+//! archives are usually compressed already. The coding itself is chosen by
+//! [`negotiate_encoding`](crate::negotiation::negotiate_encoding).
+//!
+//! This module is only available with the `gzip` or `brotli` feature.
+
+#[cfg(feature = "gzip")]
+use flate2::{write::GzEncoder, Compression};
+use std::io::{self, Write};
+
+/// Brotli quality used for responses, trading some ratio for speed since bodies are
+/// compressed on every request.
+#[cfg(feature = "brotli")]
+const BROTLI_QUALITY: u32 = 5;
+
+/// Base-2 logarithm of the Brotli window size.
+#[cfg(feature = "brotli")]
+const BROTLI_WINDOW_BITS: u32 = 22;
+
+/// The highest compression level accepted by every enabled codec: 9 for gzip, 11 for
+/// Brotli.
+#[cfg(feature = "gzip")]
+pub(crate) const MAX_LEVEL: u32 = 9;
+
+/// The highest compression level accepted by every enabled codec: 9 for gzip, 11 for
+/// Brotli.
+#[cfg(not(feature = "gzip"))]
+pub(crate) const MAX_LEVEL: u32 = 11;
+
+/// Checks whether a content type is worth compressing.
+///
+/// # Arguments
+///
+/// * `content_type` - The `Content-Type` of the response, possibly with parameters.
+///
+/// # Returns
+///
+/// `true` for textual types such as HTML, CSS, JavaScript, JSON and SVG.
+pub(crate) fn is_compressible(content_type: &str) -> bool {
+    let media_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase();
+    media_type.starts_with("text/")
+        || matches!(
+            media_type.as_str(),
+            "application/javascript"
+                | "application/json"
+                | "application/xml"
+                | "image/svg+xml"
+        )
+}
+
+/// Checks whether a content type is matched by a list of media types.
+///
+/// # Arguments
+///
+/// * `content_type` - The `Content-Type` of the response, possibly with parameters.
+/// * `media_types` - The media types to match, such as `text/html`, or a whole top-level
+///   type such as `text/*`. Case is ignored.
+///
+/// # Returns
+///
+/// `true` if one of the media types matches.
+pub(crate) fn is_listed(
+    content_type: &str,
+    media_types: &[String],
+) -> bool {
+    let media_type =
+        content_type.split(';').next().unwrap_or("").trim();
+    media_types
+        .iter()
+        .any(|listed| match listed.strip_suffix("/*") {
+            Some(top_level) => media_type
+                .split('/')
+                .next()
+                .map_or(false, |t| t.eq_ignore_ascii_case(top_level)),
+            None => media_type.eq_ignore_ascii_case(listed),
+        })
+}
+
+/// Compresses data with the given content coding.
+///
+/// # Arguments
+///
+/// * `coding` - The content coding to apply (`gzip` or `br`).
+/// * `data` - The bytes to compress.
+/// * `level` - The compression level, at most [`MAX_LEVEL`], or `None` for the
+///   codec's default.
+///
+/// # Returns
+///
+/// A `Result` containing the compressed bytes, or an I/O error if the coding is not
+/// supported or compression fails.
+pub(crate) fn encode(
+    coding: &str,
+    data: &[u8],
+    level: Option<u32>,
+) -> io::Result<Vec<u8>> {
+    match coding {
+        #[cfg(feature = "gzip")]
+        "gzip" => gzip(
+            data,
+            level.map_or_else(Compression::default, Compression::new),
+        ),
+        #[cfg(feature = "brotli")]
+        "br" => brotli(data, level.unwrap_or(BROTLI_QUALITY)),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Unsupported content coding: {}", coding),
+        )),
+    }
+}
+
+/// Compresses data with gzip.
+///
+/// # Arguments
+///
+/// * `data` - The bytes to compress.
+/// * `level` - The compression level.
+///
+/// # Returns
+///
+/// A `Result` containing the compressed bytes or an I/O error.
+#[cfg(feature = "gzip")]
+fn gzip(data: &[u8], level: Compression) -> io::Result<Vec<u8>> {
+    let mut encoder =
+        GzEncoder::new(Vec::with_capacity(data.len() / 2), level);
+    encoder.write_all(data)?;
+    encoder.finish()
+}
+
+/// Compresses data with Brotli.
+///
+/// # Arguments
+///
+/// * `data` - The bytes to compress.
+/// * `quality` - The Brotli quality, from 0 to 11.
+///
+/// # Returns
+///
+/// A `Result` containing the compressed bytes or an I/O error.
+#[cfg(feature = "brotli")]
+fn brotli(data: &[u8], quality: u32) -> io::Result<Vec<u8>> {
+    let mut encoder = brotli::CompressorWriter::new(
+        Vec::with_capacity(data.len() / 2),
+        4096,
+        quality,
+        BROTLI_WINDOW_BITS,
+    );
+    encoder.write_all(data)?;
+    Ok(encoder.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_is_compressible() {
+        assert!(is_compressible("text/html; charset=utf-8"));
+        assert!(is_compressible("application/json; charset=utf-8"));
+        assert!(is_compressible("image/svg+xml; charset=utf-8"));
+        assert!(!is_compressible("image/png"));
+        assert!(!is_compressible("application/octet-stream"));
+    }
+
+    #[test]
+    fn test_is_listed() {
+        let media_types =
+            vec!["text/*".to_string(), "application/json".to_string()];
+        assert!(is_listed("text/css; charset=utf-8", &media_types));
+        assert!(is_listed("Application/JSON", &media_types));
+        assert!(!is_listed("application/javascript", &media_types));
+        assert!(!is_listed("textual/plain", &media_types));
+        assert!(!is_listed("text/html", &[]));
+    }
+
+    #[test]
+    fn test_encode_rejects_unsupported_coding() {
+        assert!(encode("compress", b"data", None).is_err());
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip_roundtrip() {
+        let data = b"hello hello hello hello hello".repeat(10);
+        let compressed = encode("gzip", &data, None).unwrap();
+        assert!(compressed.len() < data.len());
+        let stored = encode("gzip", &data, Some(0)).unwrap();
+        assert!(stored.len() > data.len());
+
+        let mut decompressed = Vec::new();
+        let _ = flate2::read::GzDecoder::new(compressed.as_slice())
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, data);
+    }
+
+    #[cfg(feature = "brotli")]
+    #[test]
+    fn test_brotli_roundtrip() {
+        let data = b"hello hello hello hello hello".repeat(10);
+        let compressed = encode("br", &data, Some(11)).unwrap();
+        assert!(compressed.len() < data.len());
+
+        let mut decompressed = Vec::new();
+        let _ = brotli::Decompressor::new(compressed.as_slice(), 4096)
+            .read_to_end(&mut decompressed)
+            .unwrap();
+        assert_eq!(decompressed, data);
+    }
+}
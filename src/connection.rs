@@ -0,0 +1,162 @@
+// src/connection.rs
+
+//! Connection module tying request parsing and response writing to one stream.
+//!
+//! A [`Connection`] wraps any bidirectional stream, such as a `TcpStream` or an
+//! in-memory mock, and runs the read-parse-respond cycle over it: [`recv_request`]
+//! parses the next request and [`send_response`] writes the answer. Reads are
+//! buffered, so requests pipelined by the client are parsed one after the other
+//! without losing bytes between them.
+//!
+//! [`recv_request`]: Connection::recv_request
+//! [`send_response`]: Connection::send_response
+//!
+//! # Example
+//!
+//! ```rust
+//! use http_handle::connection::Connection;
+//! use http_handle::response::Response;
+//! use std::io::Cursor;
+//!
+//! let raw = b"GET /hello HTTP/1.1\r\nHost: example.com\r\n\r\n".to_vec();
+//! let mut conn = Connection::new(Cursor::new(raw));
+//!
+//! let request = conn.recv_request().unwrap();
+//! assert_eq!(request.path(), "/hello");
+//! conn.send_response(&Response::text(200, "Hello")).unwrap();
+//! ```
+
+use crate::error::ServerError;
+use crate::request::{Request, MAX_BODY_LENGTH};
+use crate::response::Response;
+use std::io::{BufReader, Read, Write};
+
+/// A stream carrying HTTP requests in and responses out.
+#[derive(Debug)]
+pub struct Connection<S> {
+    /// The stream, buffered for reading.
+    reader: BufReader<S>,
+    /// The largest request body accepted, in bytes.
+    max_body_length: u64,
+}
+
+impl<S: Read + Write> Connection<S> {
+    /// Creates a connection over a stream.
+    ///
+    /// Request bodies are limited to 1 MiB, as with [`Request::from_reader`].
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` - The stream to read requests from and write responses to.
+    ///
+    /// # Returns
+    ///
+    /// A new `Connection` instance.
+    pub fn new(stream: S) -> Self {
+        Connection {
+            reader: BufReader::new(stream),
+            max_body_length: MAX_BODY_LENGTH,
+        }
+    }
+
+    /// Sets the largest request body accepted, in bytes.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_body_length` - The body size limit; larger requests are refused with
+    ///   `ServerError::PayloadTooLarge`.
+    pub fn set_max_body_length(&mut self, max_body_length: u64) {
+        self.max_body_length = max_body_length;
+    }
+
+    /// Reads and parses the next request from the stream.
+    ///
+    /// Only the bytes of this request are consumed, so a request the client has
+    /// already pipelined behind it is returned by the next call.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Request)` - If a complete, valid request was read.
+    /// * `Err(ServerError)` - If the request is malformed, its body is too large, or
+    ///   the stream cannot be read.
+    pub fn recv_request(&mut self) -> Result<Request, ServerError> {
+        Request::parse_with_limit(
+            &mut self.reader,
+            self.max_body_length,
+        )
+    }
+
+    /// Writes a response to the stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `response` - The response to send.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the response was written and flushed.
+    /// * `Err(ServerError)` - If the stream cannot be written.
+    pub fn send_response(
+        &mut self,
+        response: &Response,
+    ) -> Result<(), ServerError> {
+        response.send(self.reader.get_mut())
+    }
+
+    /// Returns a reference to the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        self.reader.get_ref()
+    }
+
+    /// Returns a mutable reference to the underlying stream.
+    ///
+    /// Reading from it directly may skip data already buffered by the connection.
+    pub fn get_mut(&mut self) -> &mut S {
+        self.reader.get_mut()
+    }
+
+    /// Unwraps the connection, returning the underlying stream.
+    ///
+    /// Any buffered but unparsed request data is lost.
+    pub fn into_inner(self) -> S {
+        self.reader.into_inner()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_request_response_cycle() {
+        let raw = b"GET /first HTTP/1.1\r\nHost: a\r\n\r\n\
+                    POST /second HTTP/1.1\r\nContent-Length: 2\r\n\r\nhi";
+        let mut conn = Connection::new(Cursor::new(raw.to_vec()));
+
+        let first = conn.recv_request().unwrap();
+        assert_eq!(first.method(), "GET");
+        assert_eq!(first.path(), "/first");
+        let second = conn.recv_request().unwrap();
+        assert_eq!(second.path(), "/second");
+        assert_eq!(second.body(), b"hi");
+        assert!(conn.recv_request().is_err());
+
+        let response = Response::text(200, "done");
+        conn.send_response(&response).unwrap();
+        let written = conn.into_inner().into_inner();
+        assert_eq!(&written[..raw.len()], &raw[..]);
+        assert_eq!(&written[raw.len()..], response.into_bytes());
+    }
+
+    #[test]
+    fn test_max_body_length() {
+        let raw = b"POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello";
+        let mut conn = Connection::new(Cursor::new(raw.to_vec()));
+        conn.set_max_body_length(4);
+        assert!(matches!(
+            conn.recv_request(),
+            Err(ServerError::PayloadTooLarge(_))
+        ));
+    }
+}
@@ -0,0 +1,335 @@
+// src/date.rs
+
+//! HTTP date module for formatting and parsing timestamps in headers.
+//!
+//! Headers such as `Last-Modified` and `If-Modified-Since` carry timestamps in the
+//! RFC 1123 format preferred by HTTP (the IMF-fixdate of RFC 9110, e.g.
+//! `Sun, 06 Nov 1994 08:49:37 GMT`). Dates are always sent in that format, while the
+//! obsolete RFC 850 and C `asctime()` formats are also accepted when parsing, as
+//! recipients are required to.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Abbreviated day names, starting on Sunday.
+const WEEKDAYS: [&str; 7] =
+    ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+/// Abbreviated month names, starting in January.
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep",
+    "Oct", "Nov", "Dec",
+];
+
+/// Number of seconds in a day.
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Formats a timestamp as an HTTP date.
+///
+/// Sub-second precision is dropped, and times before the Unix epoch are formatted as
+/// the epoch itself.
+///
+/// # Arguments
+///
+/// * `time` - The timestamp to format.
+///
+/// # Returns
+///
+/// The date in IMF-fixdate format, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`.
+pub(crate) fn format_http_date(time: SystemTime) -> String {
+    let seconds =
+        time.duration_since(UNIX_EPOCH)
+            .map_or(0, |duration| duration.as_secs()) as i64;
+    let days = seconds.div_euclid(SECONDS_PER_DAY);
+    let time_of_day = seconds.rem_euclid(SECONDS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+    // 1 January 1970 was a Thursday
+    let weekday = (days + 4).rem_euclid(7) as usize;
+
+    format!(
+        "{}, {:02} {} {:04} {:02}:{:02}:{:02} GMT",
+        WEEKDAYS[weekday],
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        time_of_day / 3600,
+        time_of_day % 3600 / 60,
+        time_of_day % 60
+    )
+}
+
+/// Formats a timestamp as an RFC 3339 date and time in UTC, for log records.
+///
+/// Times before the Unix epoch are formatted as the epoch itself.
+///
+/// # Arguments
+///
+/// * `time` - The timestamp to format.
+///
+/// # Returns
+///
+/// The date with millisecond precision, e.g. `1994-11-06T08:49:37.000Z`.
+pub(crate) fn format_rfc3339(time: SystemTime) -> String {
+    let since_epoch =
+        time.duration_since(UNIX_EPOCH).unwrap_or(Duration::ZERO);
+    let seconds = since_epoch.as_secs() as i64;
+    let time_of_day = seconds.rem_euclid(SECONDS_PER_DAY);
+    let (year, month, day) =
+        civil_from_days(seconds.div_euclid(SECONDS_PER_DAY));
+
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        time_of_day / 3600,
+        time_of_day % 3600 / 60,
+        time_of_day % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Parses an HTTP date in any of the three formats allowed by RFC 9110.
+///
+/// The day name is not checked against the date, and two-digit RFC 850 years are
+/// taken to be between 1970 and 2069.
+///
+/// # Arguments
+///
+/// * `value` - The header value, e.g. `Sun, 06 Nov 1994 08:49:37 GMT`,
+///   `Sunday, 06-Nov-94 08:49:37 GMT` or `Sun Nov  6 08:49:37 1994`.
+///
+/// # Returns
+///
+/// The timestamp, or `None` if the value is not a valid HTTP date.
+pub(crate) fn parse_http_date(value: &str) -> Option<SystemTime> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let (day, month, year, time) = match parts.as_slice() {
+        // IMF-fixdate: Sun, 06 Nov 1994 08:49:37 GMT
+        [weekday, day, month, year, time, "GMT"]
+            if weekday.ends_with(',') =>
+        {
+            (
+                parse_number(day, 2)?,
+                *month,
+                parse_number(year, 4)?,
+                *time,
+            )
+        }
+        // RFC 850: Sunday, 06-Nov-94 08:49:37 GMT
+        [weekday, date, time, "GMT"] if weekday.ends_with(',') => {
+            let mut fields = date.split('-');
+            let day = parse_number(fields.next()?, 2)?;
+            let month = fields.next()?;
+            let year = parse_number(fields.next()?, 2)?;
+            if fields.next().is_some() {
+                return None;
+            }
+            let year =
+                if year < 70 { 2000 + year } else { 1900 + year };
+            (day, month, year, *time)
+        }
+        // asctime: Sun Nov  6 08:49:37 1994
+        [_, month, day, time, year] => {
+            let day = parse_number(day, 1)
+                .or_else(|| parse_number(day, 2))?;
+            (day, *month, parse_number(year, 4)?, *time)
+        }
+        _ => return None,
+    };
+
+    let month = MONTHS
+        .iter()
+        .position(|name| *name == month)
+        .map(|index| index as i64 + 1)?;
+    if day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+
+    let mut clock = time.split(':');
+    let hour = parse_number(clock.next()?, 2)?;
+    let minute = parse_number(clock.next()?, 2)?;
+    let second = parse_number(clock.next()?, 2)?;
+    // A leap second is allowed
+    if clock.next().is_some() || hour > 23 || minute > 59 || second > 60
+    {
+        return None;
+    }
+
+    let seconds = days_from_civil(year, month, day) * SECONDS_PER_DAY
+        + hour * 3600
+        + minute * 60
+        + second;
+    if seconds >= 0 {
+        UNIX_EPOCH.checked_add(Duration::from_secs(seconds as u64))
+    } else {
+        UNIX_EPOCH
+            .checked_sub(Duration::from_secs(seconds.unsigned_abs()))
+    }
+}
+
+/// Parses a field made of exactly `digits` ASCII digits.
+///
+/// # Arguments
+///
+/// * `field` - The text to parse.
+/// * `digits` - The required number of digits.
+///
+/// # Returns
+///
+/// The number, or `None` if the field has the wrong length or a non-digit.
+fn parse_number(field: &str, digits: usize) -> Option<i64> {
+    if field.len() != digits
+        || !field.bytes().all(|b| b.is_ascii_digit())
+    {
+        return None;
+    }
+    field.parse().ok()
+}
+
+/// Returns the number of days in a month of the proleptic Gregorian calendar.
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Converts a civil date to the number of days since 1 January 1970.
+///
+/// This is Howard Hinnant's `days_from_civil` algorithm.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4
+        - year_of_era / 100
+        + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Converts a number of days since 1 January 1970 to a civil date.
+///
+/// This is Howard Hinnant's `civil_from_days` algorithm.
+///
+/// # Returns
+///
+/// The year, month (1 to 12) and day of the month (1 to 31).
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era = (day_of_era - day_of_era / 1460
+        + day_of_era / 36_524
+        - day_of_era / 146_096)
+        / 365;
+    let day_of_year = day_of_era
+        - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `Sun, 06 Nov 1994 08:49:37 GMT`, the example date of RFC 9110.
+    fn example_date() -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(784_111_777)
+    }
+
+    #[test]
+    fn test_format_http_date() {
+        assert_eq!(
+            format_http_date(example_date()),
+            "Sun, 06 Nov 1994 08:49:37 GMT"
+        );
+        assert_eq!(
+            format_http_date(UNIX_EPOCH),
+            "Thu, 01 Jan 1970 00:00:00 GMT"
+        );
+        assert_eq!(
+            format_http_date(
+                UNIX_EPOCH + Duration::from_millis(951_782_400_999)
+            ),
+            "Tue, 29 Feb 2000 00:00:00 GMT"
+        );
+    }
+
+    #[test]
+    fn test_format_rfc3339() {
+        assert_eq!(
+            format_rfc3339(example_date()),
+            "1994-11-06T08:49:37.000Z"
+        );
+        assert_eq!(
+            format_rfc3339(
+                UNIX_EPOCH + Duration::from_millis(951_782_400_999)
+            ),
+            "2000-02-29T00:00:00.999Z"
+        );
+    }
+
+    #[test]
+    fn test_parse_http_date_formats() {
+        for value in [
+            "Sun, 06 Nov 1994 08:49:37 GMT",
+            "Sunday, 06-Nov-94 08:49:37 GMT",
+            "Sun Nov  6 08:49:37 1994",
+        ] {
+            assert_eq!(
+                parse_http_date(value),
+                Some(example_date()),
+                "{}",
+                value
+            );
+        }
+        assert_eq!(
+            parse_http_date("Thursday, 01-Jan-70 00:00:00 GMT"),
+            Some(UNIX_EPOCH)
+        );
+        assert_eq!(
+            parse_http_date("Wed, 31 Dec 1969 23:59:59 GMT"),
+            UNIX_EPOCH.checked_sub(Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn test_parse_http_date_round_trip() {
+        let time = UNIX_EPOCH + Duration::from_secs(4_102_444_799);
+        let formatted = format_http_date(time);
+        assert_eq!(formatted, "Thu, 31 Dec 2099 23:59:59 GMT");
+        assert_eq!(parse_http_date(&formatted), Some(time));
+    }
+
+    #[test]
+    fn test_parse_http_date_rejects_invalid() {
+        for value in [
+            "",
+            "yesterday",
+            "Sun, 06 Nov 1994 08:49:37",
+            "Sun, 06 Nov 1994 08:49:37 PST",
+            "Sun, 6 Nov 1994 08:49:37 GMT",
+            "Sun, 06 nov 1994 08:49:37 GMT",
+            "Sun, 31 Nov 1994 08:49:37 GMT",
+            "Tue, 29 Feb 2100 00:00:00 GMT",
+            "Sun, 06 Nov 1994 24:00:00 GMT",
+            "Sun, 06 Nov 1994 08:49 GMT",
+            "Sun, 06 Nov 94 08:49:37 GMT",
+            "Sunday, 06-Nov-1994 08:49:37 GMT",
+        ] {
+            assert_eq!(parse_http_date(value), None, "{}", value);
+        }
+    }
+}
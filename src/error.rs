@@ -56,6 +56,26 @@ pub enum ServerError {
     #[error("Forbidden: {0}")]
     Forbidden(String),
 
+    /// The request method is valid but not implemented by the server.
+    #[error("Not implemented: {0}")]
+    NotImplemented(String),
+
+    /// The request body is larger than the server accepts.
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
+    /// The request path is longer than the server accepts.
+    #[error("URI too long: {0}")]
+    UriTooLong(String),
+
+    /// The client did not send its request within the read timeout.
+    #[error("Timeout: {0}")]
+    Timeout(String),
+
+    /// The request carries an `Expect` header that the server cannot meet.
+    #[error("Expectation failed: {0}")]
+    ExpectationFailed(String),
+
     /// A custom error type for unexpected scenarios.
     #[error("Custom error: {0}")]
     Custom(String),
@@ -129,6 +149,67 @@ pub fn forbidden<T: Into<String>>(message: T) -> Self {
     }
 }
 
+impl ServerError {
+    /// Creates a new `NotImplemented` error with the given message.
+    ///
+    /// # Arguments
+    ///
+    /// * `message` - A string slice that holds the error message.
+    ///
+    /// # Returns
+    ///
+    /// A `ServerError::NotImplemented` variant.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::ServerError;
+    ///
+    /// let error = ServerError::not_implemented("PATCH");
+    /// assert!(matches!(error, ServerError::NotImplemented(_)));
+    /// ```
+    pub fn not_implemented<T: Into<String>>(message: T) -> Self {
+        ServerError::NotImplemented(message.into())
+    }
+
+    /// Returns the HTTP status code that best describes this error.
+    ///
+    /// I/O errors are classified by their `io::ErrorKind`: a file that disappeared is
+    /// reported as `404 Not Found`, while any other failure (such as a disk error while
+    /// reading) is a `500 Internal Server Error`.
+    ///
+    /// # Returns
+    ///
+    /// The status code to send to the client (e.g., 404 for `NotFound`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::ServerError;
+    ///
+    /// assert_eq!(ServerError::not_found("/missing").status_code(), 404);
+    /// assert_eq!(ServerError::not_implemented("PATCH").status_code(), 501);
+    /// ```
+    pub fn status_code(&self) -> u16 {
+        match self {
+            ServerError::InvalidRequest(_) => 400,
+            ServerError::Forbidden(_) => 403,
+            ServerError::NotFound(_) => 404,
+            ServerError::Timeout(_) => 408,
+            ServerError::PayloadTooLarge(_) => 413,
+            ServerError::UriTooLong(_) => 414,
+            ServerError::ExpectationFailed(_) => 417,
+            ServerError::NotImplemented(_) => 501,
+            ServerError::Io(e)
+                if e.kind() == io::ErrorKind::NotFound =>
+            {
+                404
+            }
+            ServerError::Io(_) | ServerError::Custom(_) => 500,
+        }
+    }
+}
+
 impl From<&str> for ServerError {
     /// Converts a string slice into a `ServerError::Custom` variant.
     ///
@@ -311,4 +392,57 @@ fn test_io_error_generic() {
             "I/O error: generic I/O error"
         );
     }
+
+    /// Test case for creating a `ServerError::NotImplemented` using the `not_implemented` method.
+    #[test]
+    fn test_not_implemented_creation() {
+        let error = ServerError::not_implemented("PATCH");
+        assert!(matches!(error, ServerError::NotImplemented(_)));
+        assert_eq!(error.to_string(), "Not implemented: PATCH");
+    }
+
+    /// Test case for mapping each `ServerError` variant to its HTTP status code.
+    #[test]
+    fn test_status_code_mapping() {
+        assert_eq!(
+            ServerError::invalid_request("bad").status_code(),
+            400
+        );
+        assert_eq!(ServerError::forbidden("denied").status_code(), 403);
+        assert_eq!(ServerError::not_found("/x").status_code(), 404);
+        assert_eq!(
+            ServerError::not_implemented("PUT").status_code(),
+            501
+        );
+        assert_eq!(
+            ServerError::PayloadTooLarge("2 MB".into()).status_code(),
+            413
+        );
+        assert_eq!(
+            ServerError::UriTooLong("/long".into()).status_code(),
+            414
+        );
+        assert_eq!(
+            ServerError::ExpectationFailed("2 MB".into()).status_code(),
+            417
+        );
+        assert_eq!(
+            ServerError::Timeout("request line".into()).status_code(),
+            408
+        );
+        assert_eq!(ServerError::from("oops").status_code(), 500);
+        let io_error = io::Error::new(io::ErrorKind::Other, "disk");
+        assert_eq!(ServerError::from(io_error).status_code(), 500);
+    }
+
+    /// Test case for classifying I/O errors by their kind.
+    #[test]
+    fn test_io_error_status_code_by_kind() {
+        let not_found = io::Error::new(io::ErrorKind::NotFound, "gone");
+        assert_eq!(ServerError::from(not_found).status_code(), 404);
+
+        let short_read =
+            io::Error::new(io::ErrorKind::UnexpectedEof, "short read");
+        assert_eq!(ServerError::from(short_read).status_code(), 500);
+    }
 }
@@ -0,0 +1,109 @@
+// src/files.rs
+
+//! Static file module for describing files and the errors raised while reading them.
+//!
+//! The server and [`Response::from_file`](crate::response::Response::from_file) both
+//! serve files, so both take their `Content-Type` and their classification of file
+//! system errors from here, and always agree on them.
+
+use crate::error::ServerError;
+use std::io;
+use std::path::Path;
+
+/// Classifies an error raised while accessing a file being served.
+///
+/// A file that exists but cannot be read because of its permissions is reported as
+/// `Forbidden` (403) and a file that has disappeared as `NotFound` (404), so clients
+/// and logs get an accurate signal. Any other failure stays an I/O error (500).
+///
+/// # Arguments
+///
+/// * `error` - The I/O error returned by the file system.
+/// * `path` - The file that was being accessed.
+///
+/// # Returns
+///
+/// The `ServerError` describing the failure.
+pub(crate) fn file_error(error: io::Error, path: &Path) -> ServerError {
+    match error.kind() {
+        io::ErrorKind::PermissionDenied => ServerError::forbidden(
+            format!("Permission denied: {}", path.display()),
+        ),
+        io::ErrorKind::NotFound => {
+            ServerError::not_found(path.display().to_string())
+        }
+        _ => ServerError::Io(error),
+    }
+}
+
+/// Determines the content type based on the file extension.
+///
+/// Textual types (HTML, CSS, JavaScript, JSON and SVG) carry `charset=utf-8` so that
+/// browsers do not have to guess the encoding; binary types are left unchanged.
+///
+/// # Arguments
+///
+/// * `path` - A `Path` representing the file path.
+///
+/// # Returns
+///
+/// A string slice representing the content type.
+pub(crate) fn get_content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(std::ffi::OsStr::to_str) {
+        Some("html") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "application/javascript; charset=utf-8",
+        Some("json") => "application/json; charset=utf-8",
+        Some("png") => "image/png",
+        Some("ico") => "image/x-icon",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("svg") => "image/svg+xml; charset=utf-8",
+        _ => "application/octet-stream",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_get_content_type() {
+        assert_eq!(
+            get_content_type(Path::new("test.html")),
+            "text/html; charset=utf-8"
+        );
+        assert_eq!(
+            get_content_type(Path::new("style.css")),
+            "text/css; charset=utf-8"
+        );
+        assert_eq!(
+            get_content_type(Path::new("script.js")),
+            "application/javascript; charset=utf-8"
+        );
+        assert_eq!(
+            get_content_type(Path::new("data.json")),
+            "application/json; charset=utf-8"
+        );
+        assert_eq!(
+            get_content_type(Path::new("image.png")),
+            "image/png"
+        );
+        assert_eq!(
+            get_content_type(Path::new("photo.jpg")),
+            "image/jpeg"
+        );
+        assert_eq!(
+            get_content_type(Path::new("animation.gif")),
+            "image/gif"
+        );
+        assert_eq!(
+            get_content_type(Path::new("icon.svg")),
+            "image/svg+xml; charset=utf-8"
+        );
+        assert_eq!(
+            get_content_type(Path::new("unknown.xyz")),
+            "application/octet-stream"
+        );
+    }
+}
@@ -0,0 +1,238 @@
+// src/interop.rs
+
+//! Interoperability module for the `http` crate's request and response types.
+//!
+//! Conversions between [`Request`]/[`Response`] and `http::Request<Vec<u8>>`/
+//! `http::Response<Vec<u8>>` let middleware and tooling written for the `http` crate
+//! work with this crate's types.
+//!
+//! - A `Response` converts into an `http::Response` and back infallibly.
+//! - Requests convert with `TryFrom` in both directions, failing with
+//!   `ServerError::InvalidRequest` when a part cannot be represented on the other side,
+//!   such as an HTTP/2 version or a header value that is not valid UTF-8.
+//!
+//! The `http` crate stores header names in lowercase, so names coming back from it are
+//! lowercase; header lookups in this crate are case-insensitive either way.
+//!
+//! This module is only available with the `http` feature.
+
+use crate::error::ServerError;
+use crate::request::Request;
+use crate::response::{reason_phrase, Response};
+use http::header::{HeaderName, HeaderValue};
+use http::{StatusCode, Version};
+use std::convert::TryFrom;
+
+impl From<Response> for http::Response<Vec<u8>> {
+    /// Converts a response into an `http::Response`.
+    ///
+    /// The status text is dropped in favour of the canonical reason phrase, a status
+    /// code outside 100 to 999 becomes `500 Internal Server Error`, and headers whose
+    /// name or value the `http` crate rejects are skipped.
+    fn from(response: Response) -> Self {
+        let mut converted = http::Response::new(response.body);
+        *converted.status_mut() =
+            StatusCode::from_u16(response.status_code)
+                .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+        for (name, value) in &response.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                let _ = converted.headers_mut().append(name, value);
+            }
+        }
+        converted
+    }
+}
+
+impl From<http::Response<Vec<u8>>> for Response {
+    /// Converts an `http::Response` into a response.
+    ///
+    /// The status text is the canonical reason phrase of the status code, and header
+    /// values that are not valid UTF-8 are converted lossily.
+    fn from(response: http::Response<Vec<u8>>) -> Self {
+        let (parts, body) = response.into_parts();
+        let status = parts.status.as_u16();
+        let mut converted =
+            Response::new(status, reason_phrase(status), body);
+        for (name, value) in &parts.headers {
+            converted.add_header(
+                name.as_str(),
+                &String::from_utf8_lossy(value.as_bytes()),
+            );
+        }
+        converted
+    }
+}
+
+impl TryFrom<http::Request<Vec<u8>>> for Request {
+    type Error = ServerError;
+
+    /// Converts an `http::Request` into a request.
+    ///
+    /// The request target is the path and query of the URI. When the URI carries an
+    /// authority and there is no `Host` header, one is added from it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ServerError::InvalidRequest` for versions other than HTTP/1.0 and
+    /// HTTP/1.1, and for header values that are not valid UTF-8.
+    fn try_from(
+        request: http::Request<Vec<u8>>,
+    ) -> Result<Self, Self::Error> {
+        let (parts, body) = request.into_parts();
+        let version = match parts.version {
+            Version::HTTP_10 => "HTTP/1.0",
+            Version::HTTP_11 => "HTTP/1.1",
+            other => {
+                return Err(ServerError::invalid_request(format!(
+                    "Unsupported HTTP version: {:?}",
+                    other
+                )))
+            }
+        };
+        let target = parts
+            .uri
+            .path_and_query()
+            .map_or("/", |target| target.as_str());
+
+        let mut converted =
+            Request::new(parts.method.as_str(), target, version);
+        for (name, value) in &parts.headers {
+            let value = value.to_str().map_err(|_| {
+                ServerError::invalid_request(format!(
+                    "Invalid value for header {}",
+                    name
+                ))
+            })?;
+            converted.add_header(name.as_str(), value);
+        }
+        if converted.header("Host").is_none() {
+            if let Some(authority) = parts.uri.authority() {
+                converted.add_header("Host", authority.as_str());
+            }
+        }
+        converted.body = body;
+        Ok(converted)
+    }
+}
+
+impl TryFrom<Request> for http::Request<Vec<u8>> {
+    type Error = ServerError;
+
+    /// Converts a request into an `http::Request`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `ServerError::InvalidRequest` if the method, target, version or a header
+    /// is not accepted by the `http` crate.
+    fn try_from(request: Request) -> Result<Self, Self::Error> {
+        let version = match request.version.as_str() {
+            "HTTP/1.0" => Version::HTTP_10,
+            "HTTP/1.1" => Version::HTTP_11,
+            other => {
+                return Err(ServerError::invalid_request(format!(
+                    "Unsupported HTTP version: {}",
+                    other
+                )))
+            }
+        };
+        let mut builder = http::Request::builder()
+            .method(request.method.as_str())
+            .uri(request.path.as_str())
+            .version(version);
+        for (name, value) in &request.headers {
+            builder = builder.header(name.as_str(), value.as_str());
+        }
+        builder.body(request.body).map_err(|e| {
+            ServerError::invalid_request(format!(
+                "Cannot convert request: {}",
+                e
+            ))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::convert::TryInto;
+
+    #[test]
+    fn test_response_round_trip() {
+        let mut response =
+            Response::new(201, "Created", b"{}".to_vec());
+        response.add_header("Content-Type", "application/json");
+        response.add_header("Set-Cookie", "a=1");
+        response.add_header("Set-Cookie", "b=2");
+
+        let converted: http::Response<Vec<u8>> =
+            response.clone().into();
+        assert_eq!(converted.status(), StatusCode::CREATED);
+        assert_eq!(converted.body(), b"{}");
+        assert_eq!(
+            converted.headers()["content-type"],
+            "application/json"
+        );
+        assert_eq!(
+            converted.headers().get_all("set-cookie").iter().count(),
+            2
+        );
+
+        let back = Response::from(converted);
+        assert_eq!(back.status_code, 201);
+        assert_eq!(back.status_text, "Created");
+        assert_eq!(back.body, response.body);
+        assert_eq!(back.headers.len(), response.headers.len());
+        for ((name, value), (original_name, original_value)) in
+            back.headers.iter().zip(&response.headers)
+        {
+            assert!(name.eq_ignore_ascii_case(original_name));
+            assert_eq!(value, original_value);
+        }
+
+        // Headers the `http` crate rejects are skipped
+        let mut response = Response::new(200, "OK", Vec::new());
+        response
+            .headers
+            .push(("Bad Name".to_string(), "value".to_string()));
+        let converted: http::Response<Vec<u8>> = response.into();
+        assert!(converted.headers().is_empty());
+    }
+
+    #[test]
+    fn test_request_conversions() {
+        let request = http::Request::builder()
+            .method("POST")
+            .uri("http://example.com/submit?draft=1")
+            .header("Content-Type", "text/plain")
+            .body(b"hello".to_vec())
+            .unwrap();
+        let request: Request = request.try_into().unwrap();
+        assert_eq!(request.method(), "POST");
+        assert_eq!(request.path(), "/submit?draft=1");
+        assert_eq!(request.version(), "HTTP/1.1");
+        assert_eq!(request.header("Content-Type"), Some("text/plain"));
+        assert_eq!(request.header("Host"), Some("example.com"));
+        assert_eq!(request.body, b"hello");
+
+        let converted: http::Request<Vec<u8>> =
+            request.try_into().unwrap();
+        assert_eq!(converted.method(), http::Method::POST);
+        assert_eq!(converted.uri(), "/submit?draft=1");
+        assert_eq!(converted.headers()["host"], "example.com");
+        assert_eq!(converted.body(), b"hello");
+
+        let http2 = http::Request::builder()
+            .version(Version::HTTP_2)
+            .body(Vec::new())
+            .unwrap();
+        assert!(matches!(
+            Request::try_from(http2),
+            Err(ServerError::InvalidRequest(_))
+        ));
+        let invalid = Request::new("GET", "/", "HTTP/9.9");
+        assert!(http::Request::try_from(invalid).is_err());
+    }
+}
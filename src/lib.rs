@@ -18,6 +18,12 @@
 //! - [`request`]: Handles incoming HTTP requests, parsing and validation.
 //! - [`response`]: Provides utilities for crafting HTTP responses.
 //! - [`error`]: Defines errors related to the server's operation.
+//! - [`connection`]: Ties reading requests and writing responses to a single stream.
+//! - [`negotiation`]: Chooses between response variants from the client's `Accept-*` headers.
+//! - [`middleware`]: Wraps request handling in composable layers.
+//! - [`websocket`]: Performs the WebSocket opening handshake and hands over the connection.
+//! - [`livereload`]: Reloads browsers viewing the site when it changes.
+//! - `async_server`: An asynchronous server built on Tokio (requires the `async` feature).
 //!
 
 /// The `server` module contains the core `Server` struct and associated methods for starting
@@ -34,5 +40,67 @@
 /// those related to connections and malformed requests.
 pub mod error;
 
+/// The `connection` module wraps a stream to read requests from it and write responses
+/// to it.
+pub mod connection;
+
+/// The `negotiation` module picks the best response variant from the client's
+/// preferences, such as the content coding named in `Accept-Encoding`.
+pub mod negotiation;
+
+/// The `middleware` module lets layers of request handling, such as authentication or
+/// logging, wrap the static file handler.
+pub mod middleware;
+
+/// The `websocket` module answers WebSocket upgrade requests and hands the upgraded
+/// connection to a user handler.
+pub mod websocket;
+
+/// The `livereload` module injects a reload script into HTML pages and tells connected
+/// browsers to reload.
+pub mod livereload;
+
+/// The `async_server` module provides an asynchronous server built on Tokio, available with
+/// the `async` feature.
+#[cfg(feature = "async")]
+pub mod async_server;
+
+/// The `date` module formats and parses the timestamps used in HTTP headers.
+mod date;
+
+/// The `cache` module keeps the contents of recently served files in memory.
+mod cache;
+
+/// The `files` module gives static files their content type and classifies the errors
+/// raised while reading them.
+mod files;
+
+/// The `compression` module encodes response bodies with gzip or Brotli, available with
+/// the `gzip` or `brotli` feature.
+#[cfg(any(feature = "gzip", feature = "brotli"))]
+mod compression;
+
+/// The `interop` module converts requests and responses to and from the `http` crate's
+/// types, available with the `http` feature.
+#[cfg(feature = "http")]
+mod interop;
+
+/// The `watch` module reloads browsers when files under the document roots change,
+/// available with the `watch` feature.
+#[cfg(feature = "watch")]
+mod watch;
+
+/// The `tls` module serves connections over HTTPS with rustls, available with the
+/// `tls` feature.
+#[cfg(feature = "tls")]
+mod tls;
+
+pub use connection::Connection;
 pub use error::ServerError;
-pub use server::Server;
+pub use server::{
+    ConnectionLimitPolicy, LogFormat, Server, ShutdownHandle,
+    UnsupportedMethodPolicy,
+};
+
+#[cfg(feature = "async")]
+pub use async_server::AsyncServer;
@@ -0,0 +1,321 @@
+// src/livereload.rs
+
+//! Live reload module for refreshing browsers when a site changes during development.
+//!
+//! With [`Server::enable_live_reload`](crate::Server::enable_live_reload), a small
+//! script is injected into every HTML page. It opens a WebSocket to
+//! [`LIVE_RELOAD_PATH`] and reloads the page when the server sends a message there,
+//! which happens each time [`ReloadHandle::reload`] is called.
+//!
+//! # Example
+//!
+//! ```rust
+//! use http_handle::Server;
+//!
+//! let mut server = Server::new("127.0.0.1:8080", "./public");
+//! server.enable_live_reload();
+//! let reload = server.live_reload_handle().unwrap();
+//!
+//! // ... after rebuilding the site:
+//! reload.reload();
+//! ```
+
+use crate::request::Request;
+use crate::response::Response;
+use crate::websocket::UpgradedStream;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
+use std::time::Duration;
+
+/// The path of the WebSocket endpoint the injected script connects to.
+pub const LIVE_RELOAD_PATH: &str = "/__livereload";
+
+/// The script injected into HTML pages, reloading them on any message.
+const RELOAD_SCRIPT: &str = concat!(
+    "<script>(function(){",
+    "var p=location.protocol===\"https:\"?\"wss:\":\"ws:\";",
+    "var s=new WebSocket(p+\"//\"+location.host+\"/__livereload\");",
+    "s.onmessage=function(){location.reload();};",
+    "})();</script>"
+);
+
+/// How long a reload connection waits before checking on the client and the server.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The number of polls between pings checking that the client is still there.
+const POLLS_PER_PING: u32 = 30;
+
+/// A handle for telling connected browsers to reload.
+///
+/// Clones share the same state, so a handle can be kept by a build tool or a file
+/// watcher on another thread. Handles are equal when they share the same state.
+#[derive(Clone, Debug, Default)]
+pub struct ReloadHandle {
+    /// The number of reloads requested so far, and the condition signalled on each.
+    state: Arc<(Mutex<u64>, Condvar)>,
+}
+
+impl ReloadHandle {
+    /// Tells every browser connected to the reload endpoint to reload the page.
+    pub fn reload(&self) {
+        let (generation, changed) = &*self.state;
+        *generation.lock().unwrap_or_else(PoisonError::into_inner) += 1;
+        changed.notify_all();
+    }
+
+    /// Returns the number of reloads requested so far.
+    pub(crate) fn generation(&self) -> u64 {
+        *self.state.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Waits until a reload is requested after `seen`, or the timeout elapses.
+    ///
+    /// # Arguments
+    ///
+    /// * `seen` - The generation already handled.
+    /// * `timeout` - The longest time to wait.
+    ///
+    /// # Returns
+    ///
+    /// `true` if a reload was requested.
+    fn wait_for_reload(&self, seen: u64, timeout: Duration) -> bool {
+        let (generation, changed) = &*self.state;
+        let guard =
+            generation.lock().unwrap_or_else(PoisonError::into_inner);
+        let (guard, _) = changed
+            .wait_timeout_while(guard, timeout, |generation| {
+                *generation == seen
+            })
+            .unwrap_or_else(PoisonError::into_inner);
+        *guard != seen
+    }
+
+    /// Serves a browser connected to the reload endpoint.
+    ///
+    /// The connection is held open until a reload is requested, which is sent as a
+    /// `reload` text message, until the server shuts down, or until a periodic ping
+    /// finds the client gone.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` - The upgraded WebSocket connection.
+    pub(crate) fn serve(&self, mut stream: UpgradedStream) {
+        let seen = self.generation();
+        let mut polls = 0;
+        loop {
+            if self.wait_for_reload(seen, POLL_INTERVAL) {
+                let _ = stream.write_all(&text_frame("reload"));
+                return;
+            }
+            if stream.shutdown_handle().is_stopped() {
+                // A close frame without a status code
+                let _ = stream.write_all(&[0x88, 0x00]);
+                return;
+            }
+            polls += 1;
+            if polls % POLLS_PER_PING == 0
+                && stream.write_all(&[0x89, 0x00]).is_err()
+            {
+                return;
+            }
+        }
+    }
+}
+
+// Handles compare and hash by identity, as clones share their state
+impl PartialEq for ReloadHandle {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.state, &other.state)
+    }
+}
+
+impl Eq for ReloadHandle {}
+
+impl Hash for ReloadHandle {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (Arc::as_ptr(&self.state) as *const () as usize).hash(state);
+    }
+}
+
+/// Builds an unmasked WebSocket text frame, as sent by servers.
+///
+/// # Arguments
+///
+/// * `text` - The message, shorter than 126 bytes.
+///
+/// # Returns
+///
+/// The encoded frame.
+fn text_frame(text: &str) -> Vec<u8> {
+    let mut frame = vec![0x81, text.len() as u8];
+    frame.extend_from_slice(text.as_bytes());
+    frame
+}
+
+/// Injects the reload script into an HTML response.
+///
+/// Only full `200 OK` responses with an HTML content type and an unencoded body are
+/// changed. The script goes right before the last `</body>` tag, matched
+/// case-insensitively, or at the end of documents without one. Any `Content-Length`
+/// header is updated to the new length, the `ETag` is made weak and byte ranges are
+/// no longer advertised, since the page no longer matches the file.
+///
+/// The body of a response to `HEAD` is left empty, and only its `Content-Length` grows
+/// by the length of the script, to match what the same `GET` request would receive.
+///
+/// # Arguments
+///
+/// * `request` - The request being answered.
+/// * `response` - The response to modify in place.
+pub(crate) fn inject_script(
+    request: &Request,
+    response: &mut Response,
+) {
+    let is_html = response.headers.iter().any(|(name, value)| {
+        name.eq_ignore_ascii_case("Content-Type")
+            && value
+                .trim_start()
+                .to_ascii_lowercase()
+                .starts_with("text/html")
+    });
+    let is_encoded = response
+        .headers
+        .iter()
+        .any(|(name, _)| name.eq_ignore_ascii_case("Content-Encoding"));
+    if response.status_code != 200 || !is_html || is_encoded {
+        return;
+    }
+    if request.method().eq_ignore_ascii_case("HEAD") {
+        let length = response
+            .headers
+            .iter()
+            .find(|(name, _)| {
+                name.eq_ignore_ascii_case("Content-Length")
+            })
+            .and_then(|(_, value)| value.trim().parse::<usize>().ok());
+        if let Some(length) = length {
+            response.set_header(
+                "Content-Length",
+                &(length + RELOAD_SCRIPT.len()).to_string(),
+            );
+        }
+        mark_rewritten(response);
+        return;
+    }
+
+    let position = response
+        .body
+        .windows(7)
+        .rposition(|window| window.eq_ignore_ascii_case(b"</body>"))
+        .unwrap_or(response.body.len());
+    let mut body =
+        Vec::with_capacity(response.body.len() + RELOAD_SCRIPT.len());
+    body.extend_from_slice(&response.body[..position]);
+    body.extend_from_slice(RELOAD_SCRIPT.as_bytes());
+    body.extend_from_slice(&response.body[position..]);
+    response.set_body(body);
+    mark_rewritten(response);
+}
+
+/// Updates the file headers of a response whose body no longer matches the file.
+///
+/// The `ETag` is made weak, so it no longer claims byte-for-byte equality with the
+/// file while still revalidating through `If-None-Match`, and `Accept-Ranges: bytes`
+/// is replaced by `none` since offsets into the page are not served.
+///
+/// # Arguments
+///
+/// * `response` - The rewritten response.
+fn mark_rewritten(response: &mut Response) {
+    for (name, value) in &mut response.headers {
+        if name.eq_ignore_ascii_case("ETag") && !value.starts_with("W/")
+        {
+            value.insert_str(0, "W/");
+        } else if name.eq_ignore_ascii_case("Accept-Ranges") {
+            *value = "none".to_string();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get() -> Request {
+        Request::new("GET", "/", "HTTP/1.1")
+    }
+
+    fn html_response(body: &str) -> Response {
+        let mut response = Response::html(200, body);
+        response.add_header("Content-Length", &body.len().to_string());
+        response
+    }
+
+    #[test]
+    fn test_inject_before_body_end() {
+        let mut response =
+            html_response("<html><body><p>Hi</p></BODY></html>");
+        inject_script(&get(), &mut response);
+        let body = String::from_utf8(response.body.clone()).unwrap();
+        assert_eq!(
+            body,
+            format!(
+                "<html><body><p>Hi</p>{}</BODY></html>",
+                RELOAD_SCRIPT
+            )
+        );
+        assert!(response.headers.contains(&(
+            "Content-Length".to_string(),
+            body.len().to_string()
+        )));
+    }
+
+    #[test]
+    fn test_inject_without_body_tag() {
+        let mut response = html_response("<p>Fragment</p>");
+        inject_script(&get(), &mut response);
+        assert_eq!(
+            response.body,
+            format!("<p>Fragment</p>{}", RELOAD_SCRIPT).as_bytes()
+        );
+
+        // Other content is left untouched
+        let mut response = Response::text(200, "</body>");
+        inject_script(&get(), &mut response);
+        assert_eq!(response.body, b"</body>");
+        let mut response = Response::html(404, "<body></body>");
+        inject_script(&get(), &mut response);
+        assert_eq!(response.body, b"<body></body>");
+    }
+
+    #[test]
+    fn test_head_keeps_body_empty() {
+        let mut response = html_response("<body></body>");
+        response.body.clear();
+        let head = Request::new("HEAD", "/", "HTTP/1.1");
+        inject_script(&head, &mut response);
+        assert!(response.body.is_empty());
+        assert!(response.headers.contains(&(
+            "Content-Length".to_string(),
+            (13 + RELOAD_SCRIPT.len()).to_string()
+        )));
+    }
+
+    #[test]
+    fn test_reload_wakes_waiters() {
+        let handle = ReloadHandle::default();
+        let seen = handle.generation();
+        assert!(
+            !handle.wait_for_reload(seen, Duration::from_millis(10))
+        );
+
+        let waiter = handle.clone();
+        let thread = std::thread::spawn(move || {
+            waiter.wait_for_reload(seen, Duration::from_secs(5))
+        });
+        handle.reload();
+        assert!(thread.join().unwrap());
+        assert_eq!(handle.generation(), seen + 1);
+    }
+}
@@ -0,0 +1,152 @@
+// src/middleware.rs
+
+//! Middleware module for wrapping request handling in composable layers.
+//!
+//! A [`Middleware`] receives each request together with a [`Next`] handle to the rest
+//! of the chain. Calling [`Next::run`] passes the request on and returns the response
+//! built further down, which the middleware may inspect or modify before returning it.
+//! Returning a response without calling `next` short-circuits the chain, so neither
+//! the later middleware nor the static file handler see the request.
+//!
+//! Middleware added with [`Server::add_middleware`](crate::Server::add_middleware) run
+//! in the order they were added: the first one added is the outermost layer, and the
+//! static file handler is the end of the chain.
+//!
+//! # Example
+//!
+//! ```rust
+//! use http_handle::middleware::{Middleware, Next};
+//! use http_handle::request::Request;
+//! use http_handle::response::Response;
+//! use http_handle::Server;
+//!
+//! #[derive(Debug)]
+//! struct RequireToken;
+//!
+//! impl Middleware for RequireToken {
+//!     fn handle(&self, request: &Request, next: Next<'_>) -> Response {
+//!         if request.header("Authorization") == Some("Bearer secret") {
+//!             next.run(request)
+//!         } else {
+//!             Response::text(401, "Unauthorized")
+//!         }
+//!     }
+//! }
+//!
+//! let mut server = Server::new("127.0.0.1:8080", "./public");
+//! server.add_middleware(Box::new(RequireToken));
+//! ```
+
+use crate::request::Request;
+use crate::response::Response;
+use crate::server::Handler;
+use std::fmt;
+
+/// A layer of request handling wrapped around the rest of the chain.
+///
+/// Middleware are shared by every connection, so they must be `Send` and `Sync`; use
+/// atomics or locks for any state they keep.
+pub trait Middleware: Send + Sync {
+    /// Handles a request, usually by passing it on with `next`.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The request being handled.
+    /// * `next` - The rest of the chain, ending with the static file handler.
+    ///
+    /// # Returns
+    ///
+    /// The response to send, from `next` or built by the middleware itself.
+    fn handle(&self, request: &Request, next: Next<'_>) -> Response;
+}
+
+/// The rest of a middleware chain, handed to each [`Middleware`].
+pub struct Next<'a> {
+    /// The middleware still to run, outermost first.
+    chain: &'a [Handler<dyn Middleware>],
+    /// The handler at the end of the chain.
+    endpoint: &'a dyn Fn(&Request) -> Response,
+}
+
+impl<'a> Next<'a> {
+    /// Creates the handle running a chain of middleware, then the endpoint.
+    ///
+    /// # Arguments
+    ///
+    /// * `chain` - The middleware to run, outermost first.
+    /// * `endpoint` - The handler at the end of the chain.
+    pub(crate) fn new(
+        chain: &'a [Handler<dyn Middleware>],
+        endpoint: &'a dyn Fn(&Request) -> Response,
+    ) -> Self {
+        Next { chain, endpoint }
+    }
+
+    /// Passes a request to the rest of the chain.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The request to hand on, usually the one received.
+    ///
+    /// # Returns
+    ///
+    /// The response built by the rest of the chain.
+    pub fn run(self, request: &Request) -> Response {
+        match self.chain.split_first() {
+            Some((middleware, rest)) => middleware
+                .0
+                .handle(request, Next::new(rest, self.endpoint)),
+            None => (self.endpoint)(request),
+        }
+    }
+}
+
+impl fmt::Debug for Next<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Next")
+            .field("remaining", &self.chain.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    /// Records the order in which it runs, before and after the rest of the chain.
+    struct Trace(&'static str, Arc<Mutex<Vec<String>>>);
+
+    impl Middleware for Trace {
+        fn handle(
+            &self,
+            request: &Request,
+            next: Next<'_>,
+        ) -> Response {
+            self.1.lock().unwrap().push(format!("{} in", self.0));
+            let response = next.run(request);
+            self.1.lock().unwrap().push(format!("{} out", self.0));
+            response
+        }
+    }
+
+    #[test]
+    fn test_chain_order() {
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let chain: Vec<Handler<dyn Middleware>> = vec![
+            Handler(Arc::new(Trace("outer", Arc::clone(&log)))),
+            Handler(Arc::new(Trace("inner", Arc::clone(&log)))),
+        ];
+        let endpoint = |request: &Request| {
+            Response::text(200, request.path().to_string())
+        };
+
+        let request = Request::new("GET", "/chain", "HTTP/1.1");
+        let response = Next::new(&chain, &endpoint).run(&request);
+        assert_eq!(response.body, b"/chain");
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["outer in", "inner in", "inner out", "outer out"]
+        );
+    }
+}
@@ -0,0 +1,377 @@
+// src/negotiation.rs
+
+//! Content negotiation module for choosing between response variants.
+//!
+//! This module interprets the proactive negotiation headers sent by clients, such as
+//! `Accept-Encoding` and `Accept-Language`, including their quality values (`q=`),
+//! and picks the best variant among those the server can produce.
+//!
+//! # Example
+//!
+//! ```rust
+//! use http_handle::negotiation::negotiate_encoding;
+//!
+//! let supported = ["br", "gzip", "identity"];
+//! let accept_encoding = "gzip;q=0.5, br;q=1.0, identity;q=0";
+//! assert_eq!(negotiate_encoding(accept_encoding, &supported), Some("br"));
+//! ```
+
+use std::cmp::Ordering;
+
+/// The content coding that leaves the body unchanged.
+const IDENTITY: &str = "identity";
+
+/// Picks the best content coding for a response from an `Accept-Encoding` header.
+///
+/// Each supported coding gets the quality value the client gave it, or the one given
+/// to `*` if it is not listed; codings matched by neither are not acceptable. The
+/// coding with the highest non-zero quality wins, and ties go to the coding listed
+/// first in `supported`, so the server's preference decides between equally acceptable
+/// codings.
+///
+/// `identity` follows RFC 9110: it is acceptable even when unlisted, unless refused
+/// with `identity;q=0`, or with `*;q=0` when it is not listed itself. An unlisted
+/// `identity` is only chosen when no listed coding is acceptable. Include `identity`
+/// in `supported` when the body may be sent unencoded.
+///
+/// # Arguments
+///
+/// * `accept_encoding` - The `Accept-Encoding` header value.
+/// * `supported` - The codings the server can produce, most preferred first.
+///
+/// # Returns
+///
+/// The chosen coding, or `None` if the client accepts none of them, in which case the
+/// server should answer `406 Not Acceptable`.
+///
+/// # Examples
+///
+/// ```
+/// use http_handle::negotiation::negotiate_encoding;
+///
+/// let supported = ["gzip", "identity"];
+/// assert_eq!(negotiate_encoding("gzip", &supported), Some("gzip"));
+/// assert_eq!(negotiate_encoding("br", &supported), Some("identity"));
+/// assert_eq!(negotiate_encoding("br, identity;q=0", &supported), None);
+/// ```
+pub fn negotiate_encoding<'a>(
+    accept_encoding: &str,
+    supported: &[&'a str],
+) -> Option<&'a str> {
+    let preferences = parse_quality_list(accept_encoding);
+    let quality_of = |coding: &str| {
+        preferences
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(coding))
+            .map(|&(_, quality)| quality)
+    };
+
+    let mut best: Option<(&'a str, f32)> = None;
+    for &coding in supported {
+        let quality =
+            match quality_of(coding).or_else(|| quality_of("*")) {
+                Some(quality) => quality,
+                // Below any listed quality, so only chosen as a last resort
+                None if coding.eq_ignore_ascii_case(IDENTITY) => {
+                    f32::MIN_POSITIVE
+                }
+                None => 0.0,
+            };
+        if quality > 0.0 && best.map_or(true, |(_, q)| quality > q) {
+            best = Some((coding, quality));
+        }
+    }
+    best.map(|(coding, _)| coding)
+}
+
+/// Picks the best available language from an `Accept-Language` header.
+///
+/// Language ranges are tried from the highest quality value down, in the order listed
+/// when qualities are equal. Tags are compared case-insensitively, and each range
+/// matches, in order of preference:
+///
+/// - an available tag equal to it;
+/// - a more specific available tag, so `de` matches `de-AT`;
+/// - an available tag it falls back to by dropping subtags, so `de-AT` matches `de`.
+///
+/// `*` matches the first available tag. Ranges with `q=0` never match, and an
+/// available tag listed with `q=0` is refused even when another range matches it.
+///
+/// # Arguments
+///
+/// * `accept_language` - The `Accept-Language` header value.
+/// * `available` - The language tags of the available variants, most preferred first.
+///
+/// # Returns
+///
+/// The chosen tag as written in `available`, or `None` if no range matches any of
+/// them.
+///
+/// # Examples
+///
+/// ```
+/// use http_handle::negotiation::negotiate_language;
+///
+/// let available = ["en", "de"];
+/// assert_eq!(
+///     negotiate_language("de, en;q=0.5", &available).as_deref(),
+///     Some("de")
+/// );
+/// assert_eq!(
+///     negotiate_language("de-AT", &available).as_deref(),
+///     Some("de")
+/// );
+/// assert_eq!(negotiate_language("fr", &available), None);
+/// ```
+pub fn negotiate_language(
+    accept_language: &str,
+    available: &[&str],
+) -> Option<String> {
+    let mut preferences = parse_quality_list(accept_language);
+    // A stable sort keeps the client's order between equal qualities
+    preferences.sort_by(|(_, a), (_, b)| {
+        b.partial_cmp(a).unwrap_or(Ordering::Equal)
+    });
+    let acceptable: Vec<&str> = available
+        .iter()
+        .copied()
+        .filter(|tag| {
+            !preferences.iter().any(|&(range, quality)| {
+                quality == 0.0 && range.eq_ignore_ascii_case(tag)
+            })
+        })
+        .collect();
+    let find = |predicate: &dyn Fn(&str) -> bool| {
+        acceptable.iter().copied().find(|tag| predicate(tag))
+    };
+
+    preferences
+        .iter()
+        .filter(|&&(_, quality)| quality > 0.0)
+        .find_map(|&(range, _)| {
+            if range == "*" {
+                return acceptable.first().copied();
+            }
+            find(&|tag| tag.eq_ignore_ascii_case(range))
+                .or_else(|| find(&|tag| is_subtag_of(tag, range)))
+                .or_else(|| find(&|tag| is_subtag_of(range, tag)))
+        })
+        .map(str::to_string)
+}
+
+/// Checks whether a language tag extends a shorter one, as `de-AT` extends `de`.
+///
+/// # Arguments
+///
+/// * `tag` - The more specific language tag.
+/// * `prefix` - The shorter language tag.
+///
+/// # Returns
+///
+/// `true` if `tag` is `prefix` followed by one or more subtags.
+fn is_subtag_of(tag: &str, prefix: &str) -> bool {
+    tag.len() > prefix.len()
+        && tag.as_bytes()[prefix.len()] == b'-'
+        && tag[..prefix.len()].eq_ignore_ascii_case(prefix)
+}
+
+/// Splits a header listing weighted values, such as `Accept-Encoding`, into its items.
+///
+/// # Arguments
+///
+/// * `header` - The header value, e.g. `"gzip;q=0.5, br"`.
+///
+/// # Returns
+///
+/// Each non-empty item with its quality value between 0 and 1, which is 1 when no `q`
+/// parameter is given. A malformed or out-of-range quality counts as 0.
+fn parse_quality_list(header: &str) -> Vec<(&str, f32)> {
+    header
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let value = parts.next().unwrap_or("").trim();
+            if value.is_empty() {
+                return None;
+            }
+            let quality = parts
+                .filter_map(|parameter| {
+                    let (key, value) = parameter.split_once('=')?;
+                    key.trim()
+                        .eq_ignore_ascii_case("q")
+                        .then(|| value.trim())
+                })
+                .next()
+                .map_or(Some(1.0), |value| value.parse::<f32>().ok())
+                .filter(|q| (0.0..=1.0).contains(q))
+                .unwrap_or(0.0);
+            Some((value, quality))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_quality_list() {
+        assert_eq!(
+            parse_quality_list("gzip;q=0.5, br , , *;Q=0"),
+            vec![("gzip", 0.5), ("br", 1.0), ("*", 0.0)]
+        );
+        assert_eq!(
+            parse_quality_list("gzip;q=1.5, br;q=x, deflate;level=1"),
+            vec![("gzip", 0.0), ("br", 0.0), ("deflate", 1.0)]
+        );
+        assert!(parse_quality_list("").is_empty());
+    }
+
+    #[test]
+    fn test_negotiate_encoding_q_values() {
+        let gzip_only = ["gzip"];
+        assert_eq!(
+            negotiate_encoding("gzip, deflate", &gzip_only),
+            Some("gzip")
+        );
+        assert_eq!(
+            negotiate_encoding("deflate, GZIP;q=0.5", &gzip_only),
+            Some("gzip")
+        );
+        assert_eq!(negotiate_encoding("gzip;q=0", &gzip_only), None);
+        assert_eq!(
+            negotiate_encoding("gzip;q=0.000", &gzip_only),
+            None
+        );
+        assert_eq!(negotiate_encoding("deflate, br", &gzip_only), None);
+
+        let both = ["br", "gzip"];
+        assert_eq!(negotiate_encoding("gzip, br", &both), Some("br"));
+        assert_eq!(
+            negotiate_encoding("gzip;q=1.0, br;q=0.8", &both),
+            Some("gzip")
+        );
+        assert_eq!(
+            negotiate_encoding("br;q=bogus, gzip;q=0.1", &both),
+            Some("gzip")
+        );
+        assert_eq!(
+            negotiate_encoding("br;q=2, gzip", &both),
+            Some("gzip")
+        );
+        assert_eq!(negotiate_encoding("gzip", &[]), None);
+    }
+
+    #[test]
+    fn test_negotiate_encoding_wildcard() {
+        let both = ["br", "gzip"];
+        assert_eq!(negotiate_encoding("*", &both), Some("br"));
+        assert_eq!(
+            negotiate_encoding("br;q=0, *", &both),
+            Some("gzip")
+        );
+        assert_eq!(
+            negotiate_encoding("*;q=0.1, gzip;q=0.2", &both),
+            Some("gzip")
+        );
+        assert_eq!(negotiate_encoding("*;q=0", &both), None);
+    }
+
+    #[test]
+    fn test_negotiate_encoding_identity() {
+        let supported = ["br", "gzip", "identity"];
+        assert_eq!(
+            negotiate_encoding(
+                "gzip;q=0.5, br;q=1.0, identity;q=0",
+                &supported
+            ),
+            Some("br")
+        );
+
+        // An unlisted identity is acceptable, but only as a last resort
+        assert_eq!(
+            negotiate_encoding("gzip;q=0.1", &supported),
+            Some("gzip")
+        );
+        assert_eq!(
+            negotiate_encoding("", &supported),
+            Some("identity")
+        );
+        assert_eq!(
+            negotiate_encoding("deflate", &supported),
+            Some("identity")
+        );
+
+        // An explicitly listed identity competes on quality
+        assert_eq!(
+            negotiate_encoding("gzip;q=0.5, identity", &supported),
+            Some("identity")
+        );
+
+        // Refusing identity, directly or through the wildcard
+        let identity_only = ["identity"];
+        assert_eq!(
+            negotiate_encoding("deflate, identity;q=0", &identity_only),
+            None
+        );
+        assert_eq!(negotiate_encoding("*;q=0", &identity_only), None);
+        assert_eq!(
+            negotiate_encoding("*;q=0, identity;q=0.5", &identity_only),
+            Some("identity")
+        );
+        assert_eq!(
+            negotiate_encoding("IDENTITY", &identity_only),
+            Some("identity")
+        );
+    }
+
+    #[test]
+    fn test_negotiate_language_q_values() {
+        let available = ["en", "de"];
+        assert_eq!(
+            negotiate_language("de, en;q=0.5", &available).as_deref(),
+            Some("de")
+        );
+        assert_eq!(
+            negotiate_language("de;q=0.4, en;q=0.8", &available)
+                .as_deref(),
+            Some("en")
+        );
+        // Equal qualities keep the client's order
+        assert_eq!(
+            negotiate_language("fr, EN, de", &available).as_deref(),
+            Some("en")
+        );
+        assert_eq!(
+            negotiate_language("*", &available).as_deref(),
+            Some("en")
+        );
+        assert_eq!(
+            negotiate_language("en;q=0, *", &available).as_deref(),
+            Some("de")
+        );
+        assert_eq!(negotiate_language("de;q=0", &available), None);
+        assert_eq!(negotiate_language("", &available), None);
+    }
+
+    #[test]
+    fn test_negotiate_language_prefix_match() {
+        let available = ["en-US", "de", "de-CH"];
+        // A more specific range falls back to its prefix
+        assert_eq!(
+            negotiate_language("de-AT", &available).as_deref(),
+            Some("de")
+        );
+        // An exact match beats a fallback
+        assert_eq!(
+            negotiate_language("de-ch", &available).as_deref(),
+            Some("de-CH")
+        );
+        // A broader range matches a more specific tag
+        assert_eq!(
+            negotiate_language("en", &available).as_deref(),
+            Some("en-US")
+        );
+        assert_eq!(negotiate_language("e, deu", &available), None);
+    }
+}
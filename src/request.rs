@@ -6,9 +6,12 @@
 //! It defines the `Request` struct and associated methods for creating and interacting with HTTP requests in a secure and robust manner.
 
 use crate::error::ServerError;
+use crate::response::is_valid_header_value;
 use std::fmt;
-use std::io::{BufRead, BufReader};
+use std::io::{self, BufRead, BufReader, Read};
+use std::mem;
 use std::net::TcpStream;
+use std::str::FromStr;
 use std::time::Duration;
 
 /// Maximum length allowed for the request line (8KB).
@@ -19,9 +22,160 @@
 const REQUEST_PARTS: usize = 3;
 
 /// Timeout duration for reading from the TCP stream (in seconds).
-const TIMEOUT_SECONDS: u64 = 30;
+pub(crate) const TIMEOUT_SECONDS: u64 = 30;
 
-/// Represents an HTTP request, containing the HTTP method, the requested path, and the HTTP version.
+/// Maximum length allowed for a single header line (8KB).
+const MAX_HEADER_LINE_LENGTH: usize = 8190;
+
+/// Maximum number of headers accepted in a single request.
+const MAX_HEADERS: usize = 100;
+
+/// Default maximum length of a request body read into memory (1 MiB).
+pub(crate) const MAX_BODY_LENGTH: u64 = 1024 * 1024;
+
+/// The form of a request target, as defined in RFC 7230 section 5.3.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RequestTarget {
+    /// An absolute path with an optional query (e.g., `/index.html?lang=en`).
+    ///
+    /// Absolute-form targets (`http://host/path`) are normalized to this form.
+    Origin,
+    /// The asterisk form `*`, only valid for server-wide `OPTIONS` requests.
+    Asterisk,
+}
+
+/// The HTTP methods accepted by the request parser.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Method {
+    /// `GET`: retrieve a representation of the resource.
+    Get,
+    /// `POST`: submit data to be processed by the resource.
+    Post,
+    /// `PUT`: replace the resource with the request payload.
+    Put,
+    /// `DELETE`: remove the resource.
+    Delete,
+    /// `HEAD`: like `GET`, but without the response body.
+    Head,
+    /// `OPTIONS`: describe the communication options for the resource.
+    Options,
+    /// `PATCH`: apply partial modifications to the resource.
+    Patch,
+}
+
+impl Method {
+    /// Returns the method name in its canonical uppercase form.
+    ///
+    /// # Returns
+    ///
+    /// The method name (e.g., "GET").
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Method::Get => "GET",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Delete => "DELETE",
+            Method::Head => "HEAD",
+            Method::Options => "OPTIONS",
+            Method::Patch => "PATCH",
+        }
+    }
+}
+
+impl FromStr for Method {
+    type Err = ServerError;
+
+    /// Parses a method name, ignoring ASCII case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::request::Method;
+    ///
+    /// assert_eq!("get".parse::<Method>().unwrap(), Method::Get);
+    /// assert!("BREW".parse::<Method>().is_err());
+    /// ```
+    fn from_str(method: &str) -> Result<Self, Self::Err> {
+        match method.to_ascii_uppercase().as_str() {
+            "GET" => Ok(Method::Get),
+            "POST" => Ok(Method::Post),
+            "PUT" => Ok(Method::Put),
+            "DELETE" => Ok(Method::Delete),
+            "HEAD" => Ok(Method::Head),
+            "OPTIONS" => Ok(Method::Options),
+            "PATCH" => Ok(Method::Patch),
+            _ => Err(ServerError::invalid_request(format!(
+                "Invalid HTTP method: {}",
+                method
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The HTTP versions accepted by the request parser.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Version {
+    /// HTTP/1.0, where connections close after each response by default.
+    Http10,
+    /// HTTP/1.1, where connections are persistent by default.
+    Http11,
+}
+
+impl Version {
+    /// Returns the version as it appears in a request or status line.
+    ///
+    /// # Returns
+    ///
+    /// The protocol version (e.g., "HTTP/1.1").
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Version::Http10 => "HTTP/1.0",
+            Version::Http11 => "HTTP/1.1",
+        }
+    }
+}
+
+impl FromStr for Version {
+    type Err = ServerError;
+
+    /// Parses a protocol version, ignoring ASCII case.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::request::Version;
+    ///
+    /// assert_eq!("HTTP/1.0".parse::<Version>().unwrap(), Version::Http10);
+    /// assert!("HTTP/2.0".parse::<Version>().is_err());
+    /// ```
+    fn from_str(version: &str) -> Result<Self, Self::Err> {
+        if version.eq_ignore_ascii_case("HTTP/1.0") {
+            Ok(Version::Http10)
+        } else if version.eq_ignore_ascii_case("HTTP/1.1") {
+            Ok(Version::Http11)
+        } else {
+            Err(ServerError::invalid_request(format!(
+                "Invalid HTTP version: {}",
+                version
+            )))
+        }
+    }
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Represents an HTTP request, containing the HTTP method, the requested path, the HTTP version
+/// and the request headers.
 #[derive(Debug, Clone, PartialEq)]
 pub struct Request {
     /// HTTP method of the request.
@@ -30,13 +184,91 @@ pub struct Request {
     pub path: String,
     /// HTTP version of the request.
     pub version: String,
+    /// A list of headers in the request, each represented as a tuple containing the header
+    /// name and its corresponding value, in the order they were received.
+    pub headers: Vec<(String, String)>,
+    /// The request line exactly as received, without the trailing line break. Empty for
+    /// requests built in code.
+    pub raw_line: String,
+    /// The request body, as delimited by `Content-Length`. Empty if the request has no
+    /// body or uses `Transfer-Encoding`, which is not decoded.
+    pub body: Vec<u8>,
 }
 
 impl Request {
-    /// Attempts to create a `Request` from the provided TCP stream by reading the first line.
+    /// Creates a request in memory, without reading it from a connection.
+    ///
+    /// This is meant for testing code that takes a `&Request`, such as a fallback
+    /// handler. Nothing is validated, so the request may hold values the parser would
+    /// reject. It starts without headers or body; add them with
+    /// [`Request::add_header`] and [`Request::set_body`].
+    ///
+    /// # Arguments
+    ///
+    /// * `method` - The HTTP method (e.g., "GET").
+    /// * `path` - The request target (e.g., "/index.html?lang=en").
+    /// * `version` - The HTTP version (e.g., "HTTP/1.1").
+    ///
+    /// # Returns
+    ///
+    /// A new `Request` instance.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::request::Request;
+    ///
+    /// let mut request = Request::new("POST", "/login", "HTTP/1.1");
+    /// request.add_header("Host", "example.com");
+    /// request.add_header("Content-Type", "application/x-www-form-urlencoded");
+    /// request.set_body(b"user=ferris".to_vec());
+    ///
+    /// assert_eq!(request.method(), "POST");
+    /// assert_eq!(request.host(), Some("example.com"));
+    /// assert_eq!(
+    ///     request.form_params(),
+    ///     vec![("user".to_string(), "ferris".to_string())]
+    /// );
+    /// ```
+    pub fn new(method: &str, path: &str, version: &str) -> Self {
+        Request {
+            method: method.to_string(),
+            path: path.to_string(),
+            version: version.to_string(),
+            headers: Vec::new(),
+            raw_line: String::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// Adds a header to the request, after those already present.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the header (e.g., "Accept").
+    /// * `value` - The value of the header (e.g., "text/html").
+    pub fn add_header(&mut self, name: &str, value: &str) {
+        self.headers.push((name.to_string(), value.to_string()));
+    }
+
+    /// Replaces the body of the request.
+    ///
+    /// Headers are left unchanged, so add a `Content-Length` header too if the code
+    /// under test relies on it.
+    ///
+    /// # Arguments
+    ///
+    /// * `body` - The new body of the request.
+    pub fn set_body(&mut self, body: Vec<u8>) {
+        self.body = body;
+    }
+
+    /// Attempts to create a `Request` from the provided TCP stream by reading the request line
+    /// and the header section.
     ///
     /// This method reads the first line of an HTTP request from the given TCP stream,
-    /// parses it, and constructs a `Request` instance if the input is valid.
+    /// parses it, reads the headers that follow up to the blank line, and constructs a
+    /// `Request` instance if the input is valid.
     ///
     /// # Arguments
     ///
@@ -53,8 +285,14 @@ impl Request {
     /// - The request line is too long (exceeds `MAX_REQUEST_LINE_LENGTH`)
     /// - The request line does not contain exactly three parts
     /// - The HTTP method is not recognized
-    /// - The request path does not start with a forward slash
+    /// - The request path does not start with a forward slash (except for `OPTIONS *` and
+    ///   absolute-form `http://host/path` targets, whose host replaces the `Host` header)
     /// - The HTTP version is not supported (only HTTP/1.0 and HTTP/1.1 are accepted)
+    /// - The path or a header value contains control characters
+    /// - A header line is malformed, too long, or there are too many headers
+    /// - Both `Content-Length` and `Transfer-Encoding` are present, or `Content-Length`
+    ///   values are invalid or conflict
+    /// - The body is larger than 1 MiB or ends before `Content-Length` bytes were read
     ///
     /// # Examples
     ///
@@ -83,31 +321,173 @@ pub fn from_stream(
                 ))
             })?;
 
-        let mut buf_reader = BufReader::new(stream);
+        Self::from_reader(stream)
+    }
+
+    /// Creates a new `Request` by reading from any byte source.
+    ///
+    /// This is the transport-independent counterpart of [`Request::from_stream`]: it
+    /// parses the request line, headers and body from an in-memory buffer, a file, or any
+    /// other `Read` implementation. No read timeout is applied.
+    ///
+    /// Lines may end with `\r\n` or, as sent by some clients and by hand with tools
+    /// such as `nc`, a bare `\n`.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The source positioned at the start of the request line.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Request)` - If the request is valid and successfully parsed.
+    /// * `Err(ServerError)` - If the request is malformed, cannot be read, or is invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::request::Request;
+    ///
+    /// let raw = b"GET /index.html HTTP/1.1\r\nHost: example.com\r\n\r\n";
+    /// let request = Request::from_reader(&raw[..]).unwrap();
+    /// assert_eq!(request.path(), "/index.html");
+    /// assert_eq!(request.host(), Some("example.com"));
+    /// ```
+    pub fn from_reader<R: Read>(
+        reader: R,
+    ) -> Result<Self, ServerError> {
+        Self::parse(&mut BufReader::new(reader))
+    }
+
+    /// Parses a request, including its body, from any buffered reader.
+    ///
+    /// The buffered data is handed to a [`RequestParser`] as it arrives, so a request
+    /// split across any number of reads is reassembled, and nothing past the end of the
+    /// request is consumed from the reader.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf_reader` - The reader positioned at the start of the request line.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Request)` - If the request is valid and successfully parsed.
+    /// * `Err(ServerError)` - If the request is malformed, cannot be read, or is invalid.
+    pub(crate) fn parse<R: BufRead>(
+        buf_reader: &mut R,
+    ) -> Result<Self, ServerError> {
+        Self::parse_with_limit(buf_reader, MAX_BODY_LENGTH)
+    }
+
+    /// Parses a request from any buffered reader, refusing bodies over a limit.
+    ///
+    /// An oversized body is refused as soon as the headers announcing it are read, so
+    /// none of it is consumed from the reader.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf_reader` - The reader positioned at the start of the request line.
+    /// * `max_body_length` - The largest body accepted, in bytes.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Request)` - If the request is valid and successfully parsed.
+    /// * `Err(ServerError)` - If the request is malformed, cannot be read, is invalid,
+    ///   or its body is too large. A read timeout firing before the request is complete
+    ///   is reported as `ServerError::Timeout`.
+    pub(crate) fn parse_with_limit<R: BufRead>(
+        buf_reader: &mut R,
+        max_body_length: u64,
+    ) -> Result<Self, ServerError> {
+        let mut parser = RequestParser::new(max_body_length);
+        while !parser.is_done() {
+            let data = match buf_reader.fill_buf() {
+                Ok([]) => break,
+                Ok(data) => data,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+                    continue
+                }
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock
+                            | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Err(ServerError::Timeout(format!(
+                        "Timed out reading request: {}",
+                        e
+                    )))
+                }
+                Err(e) => {
+                    return Err(ServerError::invalid_request(format!(
+                        "Failed to read request: {}",
+                        e
+                    )))
+                }
+            };
+            let consumed = parser.feed(data);
+            buf_reader.consume(consumed);
+        }
+        parser.finish()
+    }
+
+    /// Parses the request line and headers from any buffered reader.
+    ///
+    /// [`RequestParser`] buffers the request head and hands it to this function once
+    /// the blank line ending it has arrived, then reads the body itself. The returned
+    /// request has an empty body.
+    ///
+    /// # Arguments
+    ///
+    /// * `buf_reader` - The reader positioned at the start of the request line.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Request)` - If the request head is valid and successfully parsed.
+    /// * `Err(ServerError)` - If the request is malformed, cannot be read, or is invalid.
+    pub(crate) fn parse_head<R: BufRead>(
+        buf_reader: &mut R,
+    ) -> Result<Self, ServerError> {
         let mut request_line = String::new();
 
-        let _ =
-            buf_reader.read_line(&mut request_line).map_err(|e| {
-                ServerError::invalid_request(format!(
-                    "Failed to read request line: {}",
-                    e
-                ))
-            })?;
+        let _ = read_bounded_line(
+            buf_reader,
+            &mut request_line,
+            MAX_REQUEST_LINE_LENGTH,
+        )
+        .map_err(|e| {
+            ServerError::invalid_request(format!(
+                "Failed to read request line: {}",
+                e
+            ))
+        })?;
 
-        // Trim the trailing \r\n before checking the length
-        let trimmed_request_line = request_line.trim_end();
+        // Strip only the line terminator; any other whitespace is significant
+        let trimmed_request_line = request_line
+            .strip_suffix('\n')
+            .map(|line| line.strip_suffix('\r').unwrap_or(line))
+            .unwrap_or(&request_line);
 
         // Check if the request line exceeds the maximum allowed length
         if request_line.len() > MAX_REQUEST_LINE_LENGTH {
             return Err(ServerError::invalid_request(format!(
-                "Request line too long: {} characters (max {})",
-                request_line.len(),
+                "Request line too long: more than {} characters",
                 MAX_REQUEST_LINE_LENGTH
             )));
         }
 
+        // The three parts are separated by exactly one space each (RFC 7230 section
+        // 3.1.1); tolerating other whitespace invites request smuggling.
+        if trimmed_request_line
+            .chars()
+            .any(|c| c.is_whitespace() && c != ' ')
+        {
+            return Err(ServerError::invalid_request(
+                "Invalid request line: whitespace other than single spaces",
+            ));
+        }
         let parts: Vec<&str> =
-            trimmed_request_line.split_whitespace().collect();
+            trimmed_request_line.split(' ').collect();
 
         if parts.len() != REQUEST_PARTS {
             return Err(ServerError::invalid_request(format!(
@@ -125,8 +505,31 @@ pub fn from_stream(
             )));
         }
 
-        let path = parts[1].to_string();
-        if !path.starts_with('/') {
+        // Absolute-form targets carry the host themselves; keep it to replace any
+        // `Host` header, as required by RFC 7230 section 5.4.
+        let (authority, path) =
+            match Self::split_absolute_form(parts[1]) {
+                Some((authority, _))
+                    if !Self::is_valid_host(authority) =>
+                {
+                    return Err(ServerError::invalid_request(format!(
+                        "Invalid request target authority: {}",
+                        authority
+                    )));
+                }
+                Some((authority, path)) => {
+                    (Some(authority.to_string()), path)
+                }
+                None => (None, parts[1].to_string()),
+            };
+        if path.chars().any(|c| c.is_ascii_control()) {
+            return Err(ServerError::invalid_request(
+                "Invalid path: contains control characters",
+            ));
+        }
+        let is_asterisk_form =
+            path == "*" && method.eq_ignore_ascii_case("OPTIONS");
+        if !path.starts_with('/') && !is_asterisk_form {
             return Err(ServerError::invalid_request(
                 "Invalid path: must start with '/'",
             ));
@@ -140,13 +543,241 @@ pub fn from_stream(
             )));
         }
 
+        let mut headers = Self::read_headers(buf_reader)?;
+        Self::validate_message_framing(&headers)?;
+        if let Some(authority) = authority {
+            headers
+                .retain(|(name, _)| !name.eq_ignore_ascii_case("Host"));
+            headers.push(("Host".to_string(), authority));
+        }
+
         Ok(Request {
             method,
             path,
             version,
+            headers,
+            raw_line: trimmed_request_line.to_string(),
+            body: Vec::new(),
         })
     }
 
+    /// Returns the length of the body announced by the `Content-Length` header.
+    ///
+    /// The framing headers have already been validated by the parser, so the first
+    /// listed value is the length. A body over the limit is refused before any of it is
+    /// read: with `417 Expectation Failed` if the client sent `Expect: 100-continue` and
+    /// is waiting for permission to send it, and with `413 Content Too Large` otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_body_length` - The largest body accepted, in bytes.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(usize)` - The body length, or 0 if the request has no `Content-Length`.
+    /// * `Err(ServerError)` - If the body is larger than `max_body_length`.
+    pub(crate) fn body_length(
+        &self,
+        max_body_length: u64,
+    ) -> Result<usize, ServerError> {
+        let length = self
+            .header("Content-Length")
+            .and_then(|value| value.split(',').next())
+            .and_then(|value| value.trim().parse::<u64>().ok())
+            .unwrap_or(0);
+        if length > max_body_length {
+            let message = format!(
+                "Request body too large: {} bytes (max {})",
+                length, max_body_length
+            );
+            let expects_continue =
+                self.header("Expect").map_or(false, |value| {
+                    value.trim().eq_ignore_ascii_case("100-continue")
+                });
+            return Err(if expects_continue {
+                ServerError::ExpectationFailed(message)
+            } else {
+                ServerError::PayloadTooLarge(message)
+            });
+        }
+        Ok(length as usize)
+    }
+
+    /// Splits an absolute-form request target into its authority and path.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The request target (e.g., "http://example.com/index.html?lang=en").
+    ///
+    /// # Returns
+    ///
+    /// `Some((authority, path))` for `http` and `https` absolute-form targets, where the
+    /// path keeps any query and defaults to "/", or `None` for any other target form.
+    fn split_absolute_form(target: &str) -> Option<(&str, String)> {
+        let scheme_end = target.find("://")?;
+        let scheme = &target[..scheme_end];
+        if !scheme.eq_ignore_ascii_case("http")
+            && !scheme.eq_ignore_ascii_case("https")
+        {
+            return None;
+        }
+
+        let rest = &target[scheme_end + 3..];
+        let authority_end =
+            rest.find(|c| c == '/' || c == '?').unwrap_or(rest.len());
+        let (authority, path) = rest.split_at(authority_end);
+        let path = if path.starts_with('/') {
+            path.to_string()
+        } else {
+            format!("/{}", path)
+        };
+        Some((authority, path))
+    }
+
+    /// Reads the header section of a request, stopping at the blank line or at the end of the
+    /// stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - The buffered reader positioned just after the request line.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Vec<(String, String)>)` - The parsed headers, in the order they were received.
+    /// * `Err(ServerError)` - If a header line is malformed or a limit is exceeded.
+    fn read_headers<R: BufRead>(
+        reader: &mut R,
+    ) -> Result<Vec<(String, String)>, ServerError> {
+        let mut headers = Vec::new();
+
+        loop {
+            let mut line = String::new();
+            let bytes_read = read_bounded_line(
+                reader,
+                &mut line,
+                MAX_HEADER_LINE_LENGTH,
+            )
+            .map_err(|e| {
+                ServerError::invalid_request(format!(
+                    "Failed to read header line: {}",
+                    e
+                ))
+            })?;
+
+            // End of stream: the client sent no further headers
+            if bytes_read == 0 {
+                break;
+            }
+
+            if line.len() > MAX_HEADER_LINE_LENGTH {
+                return Err(ServerError::invalid_request(format!(
+                    "Header line too long: more than {} characters",
+                    MAX_HEADER_LINE_LENGTH
+                )));
+            }
+
+            let line = line.trim_end_matches(&['\r', '\n'][..]);
+            if line.is_empty() {
+                break;
+            }
+
+            if headers.len() >= MAX_HEADERS {
+                return Err(ServerError::invalid_request(format!(
+                    "Too many headers (max {})",
+                    MAX_HEADERS
+                )));
+            }
+
+            let (name, value) =
+                line.split_once(':').ok_or_else(|| {
+                    ServerError::invalid_request(format!(
+                        "Malformed header line: {}",
+                        line
+                    ))
+                })?;
+
+            // Field names must not be empty or contain whitespace, which also rejects
+            // obsolete line folding.
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return Err(ServerError::invalid_request(format!(
+                    "Invalid header name: {:?}",
+                    name
+                )));
+            }
+
+            let value = value.trim();
+            if !is_valid_header_value(value) {
+                return Err(ServerError::invalid_request(format!(
+                    "Invalid value for header {}",
+                    name
+                )));
+            }
+
+            headers.push((name.to_string(), value.to_string()));
+        }
+
+        Ok(headers)
+    }
+
+    /// Rejects requests whose body length is ambiguous.
+    ///
+    /// A request carrying both `Content-Length` and `Transfer-Encoding`, or several
+    /// `Content-Length` values that disagree, can be framed differently by intermediaries
+    /// and origin servers, which enables request smuggling (RFC 7230 section 3.3.3).
+    ///
+    /// # Arguments
+    ///
+    /// * `headers` - The parsed request headers.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the message framing is unambiguous.
+    /// * `Err(ServerError)` - If the framing headers conflict or are invalid.
+    fn validate_message_framing(
+        headers: &[(String, String)],
+    ) -> Result<(), ServerError> {
+        let has_transfer_encoding = headers.iter().any(|(name, _)| {
+            name.eq_ignore_ascii_case("Transfer-Encoding")
+        });
+
+        let mut content_length = None;
+        for (_, value) in headers.iter().filter(|(name, _)| {
+            name.eq_ignore_ascii_case("Content-Length")
+        }) {
+            if has_transfer_encoding {
+                return Err(ServerError::invalid_request(
+                    "Both Content-Length and Transfer-Encoding present",
+                ));
+            }
+
+            // A list of identical values is allowed, e.g. "42, 42"
+            for item in value.split(',').map(str::trim) {
+                let length = item
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|_| {
+                        item.bytes().all(|b| b.is_ascii_digit())
+                    })
+                    .ok_or_else(|| {
+                        ServerError::invalid_request(format!(
+                            "Invalid Content-Length: {}",
+                            value
+                        ))
+                    })?;
+                match content_length {
+                    Some(previous) if previous != length => {
+                        return Err(ServerError::invalid_request(
+                            "Conflicting Content-Length values",
+                        ));
+                    }
+                    _ => content_length = Some(length),
+                }
+            }
+        }
+
+        Ok(())
+    }
+
     /// Returns the HTTP method of the request.
     ///
     /// # Returns
@@ -156,6 +787,28 @@ pub fn method(&self) -> &str {
         &self.method
     }
 
+    /// Returns the HTTP method of the request as a typed `Method`.
+    ///
+    /// Parsed requests always have a known method, so this only returns `None` for a
+    /// `Request` built by hand with an unsupported method.
+    ///
+    /// # Returns
+    ///
+    /// The `Method`, matched case-insensitively (e.g., `Some(Method::Get)`).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::request::{Method, Request};
+    ///
+    /// let request = Request::from_reader(&b"HEAD / HTTP/1.1\r\n\r\n"[..])
+    ///     .unwrap();
+    /// assert_eq!(request.method_enum(), Some(Method::Head));
+    /// ```
+    pub fn method_enum(&self) -> Option<Method> {
+        self.method.parse().ok()
+    }
+
     /// Returns the requested path of the request.
     ///
     /// # Returns
@@ -165,6 +818,115 @@ pub fn path(&self) -> &str {
         &self.path
     }
 
+    /// Returns the request line exactly as received.
+    ///
+    /// Unlike [`Request::method`], [`Request::path`] and [`Request::version`], which
+    /// return normalized components (an absolute-form target is reduced to its path, for
+    /// example), this keeps the original bytes for logging and debugging. The trailing
+    /// line break is not included.
+    ///
+    /// # Returns
+    ///
+    /// A string slice containing the raw request line, or an empty string for requests
+    /// built in code.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::request::Request;
+    ///
+    /// let raw = b"GET http://example.com/a%20b?x=1 HTTP/1.1\r\n\r\n";
+    /// let request = Request::from_reader(&raw[..]).unwrap();
+    /// assert_eq!(request.raw_line(), "GET http://example.com/a%20b?x=1 HTTP/1.1");
+    /// assert_eq!(request.path(), "/a%20b?x=1");
+    /// ```
+    pub fn raw_line(&self) -> &str {
+        &self.raw_line
+    }
+
+    /// Returns the body of the request.
+    ///
+    /// # Returns
+    ///
+    /// The body bytes, empty if the request carried none.
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// Decodes an `application/x-www-form-urlencoded` body into name/value pairs.
+    ///
+    /// Pairs are separated by `&` and split at the first `=`; a pair without `=` has an
+    /// empty value. Names and values are percent-decoded, with `+` standing for a space,
+    /// and invalid UTF-8 is replaced with U+FFFD.
+    ///
+    /// # Returns
+    ///
+    /// The pairs in the order they appear, or an empty list if the `Content-Type` is not
+    /// `application/x-www-form-urlencoded`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::request::Request;
+    ///
+    /// let raw = b"POST /login HTTP/1.1\r\n\
+    ///     Content-Type: application/x-www-form-urlencoded\r\n\
+    ///     Content-Length: 25\r\n\r\n\
+    ///     name=Ada+L&note=caf%C3%A9";
+    /// let request = Request::from_reader(&raw[..]).unwrap();
+    /// assert_eq!(
+    ///     request.form_params(),
+    ///     vec![
+    ///         ("name".to_string(), "Ada L".to_string()),
+    ///         ("note".to_string(), "café".to_string()),
+    ///     ]
+    /// );
+    /// ```
+    pub fn form_params(&self) -> Vec<(String, String)> {
+        let is_form =
+            self.header("Content-Type").map_or(false, |value| {
+                value
+                    .split(';')
+                    .next()
+                    .unwrap_or("")
+                    .trim()
+                    .eq_ignore_ascii_case(
+                        "application/x-www-form-urlencoded",
+                    )
+            });
+        if !is_form {
+            return Vec::new();
+        }
+
+        self.body
+            .split(|&b| b == b'&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = match pair
+                    .iter()
+                    .position(|&b| b == b'=')
+                {
+                    Some(index) => (&pair[..index], &pair[index + 1..]),
+                    None => (pair, &pair[pair.len()..]),
+                };
+                (form_decode(name), form_decode(value))
+            })
+            .collect()
+    }
+
+    /// Returns the form of the request target.
+    ///
+    /// # Returns
+    ///
+    /// `RequestTarget::Asterisk` for `OPTIONS *` requests, `RequestTarget::Origin` otherwise.
+    pub fn target(&self) -> RequestTarget {
+        if self.path == "*" {
+            RequestTarget::Asterisk
+        } else {
+            RequestTarget::Origin
+        }
+    }
+
     /// Returns the HTTP version of the request.
     ///
     /// # Returns
@@ -174,6 +936,132 @@ pub fn version(&self) -> &str {
         &self.version
     }
 
+    /// Returns the HTTP version of the request as a typed `Version`.
+    ///
+    /// Parsed requests always have a supported version, so this only returns `None`
+    /// for a `Request` built by hand with another version.
+    ///
+    /// # Returns
+    ///
+    /// The `Version`, matched case-insensitively (e.g., `Some(Version::Http11)`).
+    pub fn version_enum(&self) -> Option<Version> {
+        self.version.parse().ok()
+    }
+
+    /// Returns the value of the first header matching the given name.
+    ///
+    /// Header names are compared case-insensitively.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the header to look up (e.g., "Content-Type").
+    ///
+    /// # Returns
+    ///
+    /// `Some(&str)` with the header value if present, `None` otherwise.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns the value of the `Host` header, if present and valid.
+    ///
+    /// The value is considered valid when it is non-empty and only contains characters
+    /// allowed in a host name, an IP literal and an optional port.
+    ///
+    /// # Returns
+    ///
+    /// `Some(&str)` with the host (including any port), `None` if the header is missing
+    /// or invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::request::Request;
+    ///
+    /// let request = Request {
+    ///     method: "GET".to_string(),
+    ///     path: "/".to_string(),
+    ///     version: "HTTP/1.1".to_string(),
+    ///     headers: vec![("Host".to_string(), "example.com:8080".to_string())],
+    ///     raw_line: String::new(),
+    ///     body: Vec::new(),
+    /// };
+    /// assert_eq!(request.host(), Some("example.com:8080"));
+    /// ```
+    pub fn host(&self) -> Option<&str> {
+        self.header("Host").filter(|host| Self::is_valid_host(host))
+    }
+
+    /// Checks whether the `Connection` header lists the given option.
+    ///
+    /// The header is a comma-separated list of tokens (e.g., `keep-alive, Upgrade`) and
+    /// may be repeated, so every value is split on commas and each token is trimmed and
+    /// compared case-insensitively.
+    ///
+    /// # Arguments
+    ///
+    /// * `option` - The connection option to look for (e.g., "close" or "keep-alive").
+    ///
+    /// # Returns
+    ///
+    /// `true` if any `Connection` header contains the option, `false` otherwise.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::request::Request;
+    ///
+    /// let request = Request {
+    ///     method: "GET".to_string(),
+    ///     path: "/".to_string(),
+    ///     version: "HTTP/1.1".to_string(),
+    ///     headers: vec![(
+    ///         "Connection".to_string(),
+    ///         "keep-alive, Upgrade".to_string(),
+    ///     )],
+    ///     raw_line: String::new(),
+    ///     body: Vec::new(),
+    /// };
+    /// assert!(request.has_connection_option("upgrade"));
+    /// assert!(!request.has_connection_option("close"));
+    /// ```
+    pub fn has_connection_option(&self, option: &str) -> bool {
+        self.headers
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case("Connection"))
+            .flat_map(|(_, value)| value.split(','))
+            .any(|token| token.trim().eq_ignore_ascii_case(option))
+    }
+
+    /// Returns whether the client wants the connection kept open after this request.
+    ///
+    /// HTTP/1.1 connections are persistent unless the client sends `Connection: close`;
+    /// HTTP/1.0 connections close after the response unless the client sends
+    /// `Connection: keep-alive`.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the connection should be kept alive, `false` if it should be closed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::request::Request;
+    ///
+    /// let raw = b"GET / HTTP/1.0\r\nConnection: keep-alive\r\n\r\n";
+    /// assert!(Request::from_reader(&raw[..]).unwrap().is_keep_alive());
+    /// ```
+    pub fn is_keep_alive(&self) -> bool {
+        if self.version_enum() == Some(Version::Http10) {
+            self.has_connection_option("keep-alive")
+        } else {
+            !self.has_connection_option("close")
+        }
+    }
+
     /// Checks if the given method is a valid HTTP method.
     ///
     /// # Arguments
@@ -184,37 +1072,310 @@ pub fn version(&self) -> &str {
     ///
     /// `true` if the method is valid, `false` otherwise.
     fn is_valid_method(method: &str) -> bool {
+        method.parse::<Method>().is_ok()
+    }
+
+    /// Checks if the given HTTP version is supported.
+    ///
+    /// # Arguments
+    ///
+    /// * `version` - A string slice containing the HTTP version to validate.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the version is supported, `false` otherwise.
+    fn is_valid_version(version: &str) -> bool {
+        version.parse::<Version>().is_ok()
+    }
+
+    /// Checks if the given `Host` header value is well formed.
+    ///
+    /// # Arguments
+    ///
+    /// * `host` - A string slice containing the `Host` header value.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the value is a plausible host with an optional port, `false` otherwise.
+    fn is_valid_host(host: &str) -> bool {
+        !host.is_empty()
+            && host.chars().all(|c| {
+                c.is_ascii_alphanumeric()
+                    || matches!(c, '-' | '.' | '_' | ':' | '[' | ']')
+            })
+    }
+}
+
+impl fmt::Display for Request {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.method, self.path, self.version)
+    }
+}
+
+/// The progress of a [`RequestParser`].
+#[derive(Debug)]
+enum ParserState {
+    /// Reading the request line and headers.
+    Head,
+    /// Reading the body, with the given number of bytes still expected.
+    Body(Box<Request>, usize),
+    /// The whole request has been read.
+    Complete(Box<Request>),
+    /// The request is invalid; no further input is accepted.
+    Failed(ServerError),
+}
+
+/// An incremental HTTP request parser.
+///
+/// Input is fed in pieces of any size, such as the data returned by successive reads
+/// from a socket, and the parser resumes exactly where the previous piece ended. The
+/// line length and header count limits are enforced as bytes arrive, so an oversized
+/// request is rejected before it is buffered, and the parser never consumes bytes
+/// beyond the end of the request, which belong to the next request on the connection.
+#[derive(Debug)]
+pub(crate) struct RequestParser {
+    /// The request line and headers received so far.
+    head: Vec<u8>,
+    /// The offset in `head` where the current line starts.
+    line_start: usize,
+    /// The number of complete lines in `head`, including the request line.
+    lines: usize,
+    /// The largest body accepted, in bytes.
+    max_body_length: u64,
+    /// What the parser expects next.
+    state: ParserState,
+}
+
+impl RequestParser {
+    /// Creates a parser expecting the start of a request.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_body_length` - The largest body accepted, in bytes.
+    pub(crate) fn new(max_body_length: u64) -> Self {
+        RequestParser {
+            head: Vec::with_capacity(1024),
+            line_start: 0,
+            lines: 0,
+            max_body_length,
+            state: ParserState::Head,
+        }
+    }
+
+    /// Returns whether the parser has finished, successfully or not.
+    ///
+    /// # Returns
+    ///
+    /// `true` once the whole request has been read or an error was found, after which
+    /// [`RequestParser::finish`] yields the result.
+    pub(crate) fn is_done(&self) -> bool {
         matches!(
-            method.to_ascii_uppercase().as_str(),
-            "GET"
-                | "POST"
-                | "PUT"
-                | "DELETE"
-                | "HEAD"
-                | "OPTIONS"
-                | "PATCH"
+            self.state,
+            ParserState::Complete(_) | ParserState::Failed(_)
         )
     }
 
-    /// Checks if the given HTTP version is supported.
-    ///
-    /// # Arguments
+    /// Feeds the next piece of input to the parser.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The bytes that follow the input fed so far.
+    ///
+    /// # Returns
+    ///
+    /// The number of bytes consumed. This is less than `data.len()` once the parser is
+    /// done; the remaining bytes are not part of this request.
+    pub(crate) fn feed(&mut self, data: &[u8]) -> usize {
+        let mut consumed = 0;
+        while consumed < data.len() && !self.is_done() {
+            let remaining = &data[consumed..];
+            consumed += match self.state {
+                ParserState::Head => self.feed_head(remaining),
+                ParserState::Body(
+                    ref mut request,
+                    ref mut expected,
+                ) => {
+                    let count = remaining.len().min(*expected);
+                    request.body.extend_from_slice(&remaining[..count]);
+                    *expected -= count;
+                    count
+                }
+                ParserState::Complete(_) | ParserState::Failed(_) => 0,
+            };
+
+            if matches!(self.state, ParserState::Body(_, 0)) {
+                if let ParserState::Body(request, _) =
+                    mem::replace(&mut self.state, ParserState::Head)
+                {
+                    self.state = ParserState::Complete(request);
+                }
+            }
+        }
+        consumed
+    }
+
+    /// Consumes bytes of the request head, up to the blank line that ends it.
+    ///
+    /// # Arguments
+    ///
+    /// * `data` - The input following the head received so far.
+    ///
+    /// # Returns
+    ///
+    /// The number of bytes consumed.
+    fn feed_head(&mut self, data: &[u8]) -> usize {
+        for (index, &byte) in data.iter().enumerate() {
+            self.head.push(byte);
+
+            let line_length = self.head.len() - self.line_start;
+            if self.lines == 0 && line_length > MAX_REQUEST_LINE_LENGTH
+            {
+                self.state =
+                    ParserState::Failed(ServerError::invalid_request(
+                        format!(
+                            "Request line too long: more than {} characters",
+                            MAX_REQUEST_LINE_LENGTH
+                        ),
+                    ));
+                return index + 1;
+            }
+            if self.lines > 0 && line_length > MAX_HEADER_LINE_LENGTH {
+                self.state = ParserState::Failed(
+                    ServerError::invalid_request(format!(
+                        "Header line too long: more than {} characters",
+                        MAX_HEADER_LINE_LENGTH
+                    )),
+                );
+                return index + 1;
+            }
+            if byte != b'\n' {
+                continue;
+            }
+
+            let line = &self.head[self.line_start..];
+            if self.lines > 0 && (line == b"\n" || line == b"\r\n") {
+                self.complete_head();
+                return index + 1;
+            }
+            self.lines += 1;
+            self.line_start = self.head.len();
+            if self.lines > MAX_HEADERS + 1 {
+                self.state = ParserState::Failed(
+                    ServerError::invalid_request(format!(
+                        "Too many headers (max {})",
+                        MAX_HEADERS
+                    )),
+                );
+                return index + 1;
+            }
+        }
+        data.len()
+    }
+
+    /// Parses the complete head and prepares to read the body it announces.
+    fn complete_head(&mut self) {
+        let head = mem::take(&mut self.head);
+        self.state =
+            match Request::parse_head(&mut io::Cursor::new(head))
+                .and_then(|request| {
+                    request
+                        .body_length(self.max_body_length)
+                        .map(|length| (request, length))
+                }) {
+                Ok((request, 0)) => {
+                    ParserState::Complete(Box::new(request))
+                }
+                Ok((request, length)) => {
+                    ParserState::Body(Box::new(request), length)
+                }
+                Err(e) => ParserState::Failed(e),
+            };
+    }
+
+    /// Ends parsing, at the end of the request or of the input.
     ///
-    /// * `version` - A string slice containing the HTTP version to validate.
+    /// A head cut short by the end of the input is parsed as received, as if the blank
+    /// line had been sent, but a body cut short is an error.
     ///
     /// # Returns
     ///
-    /// `true` if the version is supported, `false` otherwise.
-    fn is_valid_version(version: &str) -> bool {
-        version.eq_ignore_ascii_case("HTTP/1.0")
-            || version.eq_ignore_ascii_case("HTTP/1.1")
+    /// * `Ok(Request)` - If a valid request was read.
+    /// * `Err(ServerError)` - If the request is invalid or its body is incomplete.
+    pub(crate) fn finish(mut self) -> Result<Request, ServerError> {
+        if matches!(self.state, ParserState::Head) {
+            self.complete_head();
+        }
+        match self.state {
+            ParserState::Complete(request) => Ok(*request),
+            ParserState::Failed(e) => Err(e),
+            ParserState::Body(_, expected) => {
+                Err(ServerError::invalid_request(format!(
+                    "Failed to read request body: {} bytes missing",
+                    expected
+                )))
+            }
+            ParserState::Head => unreachable!("the head was completed"),
+        }
     }
 }
 
-impl fmt::Display for Request {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{} {} {}", self.method, self.path, self.version)
+/// Decodes one component of a form-encoded body.
+///
+/// `+` becomes a space and `%XX` escapes become the byte they encode; a `%` not followed
+/// by two hexadecimal digits is kept as is.
+///
+/// # Arguments
+///
+/// * `input` - The encoded name or value.
+///
+/// # Returns
+///
+/// The decoded text, with invalid UTF-8 replaced by U+FFFD.
+fn form_decode(input: &[u8]) -> String {
+    let hex = |b: u8| (b as char).to_digit(16);
+    let mut decoded = Vec::with_capacity(input.len());
+    let mut index = 0;
+    while index < input.len() {
+        match input[index] {
+            b'+' => decoded.push(b' '),
+            b'%' => match (
+                input.get(index + 1).copied().and_then(hex),
+                input.get(index + 2).copied().and_then(hex),
+            ) {
+                (Some(high), Some(low)) => {
+                    decoded.push((high * 16 + low) as u8);
+                    index += 2;
+                }
+                _ => decoded.push(b'%'),
+            },
+            b => decoded.push(b),
+        }
+        index += 1;
     }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Reads one line, buffering at most one byte more than the given limit.
+///
+/// Reading stops at the end of the line or once `limit + 1` bytes have been read, so a
+/// client sending an endless line cannot make the server buffer it. A result longer
+/// than `limit` tells the caller the line exceeded it.
+///
+/// # Arguments
+///
+/// * `reader` - The reader to read the line from.
+/// * `line` - The string the line is appended to, including its terminator.
+/// * `limit` - The maximum accepted line length.
+///
+/// # Returns
+///
+/// The number of bytes read, or an I/O error.
+fn read_bounded_line<R: BufRead>(
+    reader: &mut R,
+    line: &mut String,
+    limit: usize,
+) -> io::Result<usize> {
+    reader.take(limit as u64 + 1).read_line(line)
 }
 
 #[cfg(test)]
@@ -223,6 +1384,27 @@ mod tests {
     use std::io::Write;
     use std::net::TcpListener;
 
+    #[test]
+    fn test_request_built_in_memory() {
+        let mut request =
+            Request::new("GET", "/search?q=rust", "HTTP/1.0");
+        request.add_header("Connection", "keep-alive");
+        request.add_header("X-Custom", "one");
+        request.add_header("x-custom", "two");
+
+        assert_eq!(request.method_enum(), Some(Method::Get));
+        assert_eq!(request.version_enum(), Some(Version::Http10));
+        assert_eq!(request.path(), "/search?q=rust");
+        assert_eq!(request.header("X-CUSTOM"), Some("one"));
+        assert_eq!(request.headers.len(), 3);
+        assert!(request.is_keep_alive());
+        assert!(request.raw_line().is_empty());
+        assert!(request.body().is_empty());
+
+        request.set_body(b"payload".to_vec());
+        assert_eq!(request.body(), b"payload");
+    }
+
     #[test]
     fn test_valid_request() {
         let listener = TcpListener::bind("127.0.0.1:0").unwrap();
@@ -241,6 +1423,106 @@ fn test_valid_request() {
         assert_eq!(request.version(), "HTTP/1.1");
     }
 
+    #[test]
+    fn test_stalled_request_times_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let client = std::thread::spawn(move || {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            stream.write_all(b"GET /index.html HTTP/1.1\r\n").unwrap();
+            // Stall past the read timeout before closing
+            std::thread::sleep(Duration::from_millis(500));
+        });
+
+        let (stream, _) = listener.accept().unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_millis(100)))
+            .unwrap();
+        let error = Request::from_reader(&stream).unwrap_err();
+        assert!(matches!(error, ServerError::Timeout(_)), "{}", error);
+        assert_eq!(error.status_code(), 408);
+        client.join().unwrap();
+    }
+
+    #[test]
+    fn test_request_with_host_header() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let _ = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream
+                .write_all(
+                    b"GET / HTTP/1.1\r\nHost: example.com:8080\r\nAccept: */*\r\n\r\n",
+                )
+                .unwrap();
+        });
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let request = Request::from_stream(&stream).unwrap();
+
+        assert_eq!(request.headers.len(), 2);
+        assert_eq!(request.host(), Some("example.com:8080"));
+        assert_eq!(request.header("accept"), Some("*/*"));
+    }
+
+    #[test]
+    fn test_request_without_host_header() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let _ = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream
+                .write_all(b"GET / HTTP/1.1\r\nAccept: */*\r\n\r\n")
+                .unwrap();
+        });
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let request = Request::from_stream(&stream).unwrap();
+
+        assert_eq!(request.host(), None);
+    }
+
+    #[test]
+    fn test_invalid_host_header() {
+        let request = Request {
+            method: "GET".to_string(),
+            path: "/".to_string(),
+            version: "HTTP/1.1".to_string(),
+            headers: vec![(
+                "Host".to_string(),
+                "exa mple.com/".to_string(),
+            )],
+            raw_line: String::new(),
+            body: Vec::new(),
+        };
+
+        assert_eq!(request.host(), None);
+    }
+
+    #[test]
+    fn test_malformed_header() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let _ = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream
+                .write_all(b"GET / HTTP/1.1\r\nNoColonHere\r\n\r\n")
+                .unwrap();
+        });
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let result = Request::from_stream(&stream);
+
+        assert!(matches!(
+            result.unwrap_err(),
+            ServerError::InvalidRequest(_)
+        ));
+    }
+
     #[test]
     fn test_invalid_method() {
         let listener = TcpListener::bind("127.0.0.1:0").unwrap();
@@ -289,6 +1571,266 @@ fn test_max_length_request() {
         );
     }
 
+    #[test]
+    fn test_method_round_trip() {
+        let methods = [
+            (Method::Get, "GET"),
+            (Method::Post, "POST"),
+            (Method::Put, "PUT"),
+            (Method::Delete, "DELETE"),
+            (Method::Head, "HEAD"),
+            (Method::Options, "OPTIONS"),
+            (Method::Patch, "PATCH"),
+        ];
+        for (method, name) in methods {
+            assert_eq!(method.to_string(), name);
+            assert_eq!(name.parse::<Method>().unwrap(), method);
+            assert_eq!(
+                name.to_ascii_lowercase().parse::<Method>().unwrap(),
+                method
+            );
+
+            let raw = format!("{} / HTTP/1.1\r\n\r\n", name);
+            let request = Request::from_reader(raw.as_bytes()).unwrap();
+            assert_eq!(request.method_enum(), Some(method));
+        }
+
+        assert!(matches!(
+            "TRACE".parse::<Method>(),
+            Err(ServerError::InvalidRequest(_))
+        ));
+        let mut request =
+            Request::from_reader(&b"GET / HTTP/1.1\r\n\r\n"[..])
+                .unwrap();
+        request.method = "BREW".to_string();
+        assert_eq!(request.method_enum(), None);
+    }
+
+    #[test]
+    fn test_version_parsing() {
+        for (version, name) in [
+            (Version::Http10, "HTTP/1.0"),
+            (Version::Http11, "HTTP/1.1"),
+        ] {
+            assert_eq!(version.to_string(), name);
+            assert_eq!(name.parse::<Version>().unwrap(), version);
+
+            let raw = format!("GET / {}\r\n\r\n", name);
+            let request = Request::from_reader(raw.as_bytes()).unwrap();
+            assert_eq!(request.version_enum(), Some(version));
+        }
+        assert_eq!(
+            "http/1.1".parse::<Version>().unwrap(),
+            Version::Http11
+        );
+
+        for unsupported in
+            ["HTTP/0.9", "HTTP/2.0", "HTTP/1.1.0", "HTTP/1", ""]
+        {
+            assert!(matches!(
+                unsupported.parse::<Version>(),
+                Err(ServerError::InvalidRequest(_))
+            ));
+            let raw = format!("GET / {}\r\n\r\n", unsupported);
+            assert!(Request::from_reader(raw.as_bytes()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_parse_across_single_byte_reads() {
+        /// A reader that returns at most one byte per read.
+        struct Throttled<'a>(&'a [u8]);
+
+        impl Read for Throttled<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                match (self.0.split_first(), buf.first_mut()) {
+                    (Some((&byte, rest)), Some(slot)) => {
+                        *slot = byte;
+                        self.0 = rest;
+                        Ok(1)
+                    }
+                    _ => Ok(0),
+                }
+            }
+        }
+
+        let raw = b"POST /submit HTTP/1.1\r\nHost: example.com\r\nContent-Length: 5\r\n\r\nhelloGET /next HTTP/1.1\r\n\r\n";
+        let mut reader = BufReader::new(Throttled(raw));
+
+        let first = Request::parse(&mut reader).unwrap();
+        assert_eq!(first.method(), "POST");
+        assert_eq!(first.host(), Some("example.com"));
+        assert_eq!(first.body(), b"hello");
+
+        // Nothing belonging to the next request was consumed
+        let second = Request::parse(&mut reader).unwrap();
+        assert_eq!(second.path(), "/next");
+        assert!(second.body().is_empty());
+    }
+
+    #[test]
+    fn test_bare_lf_line_endings() {
+        let raw = b"POST /submit HTTP/1.1\nHost: example.com\nContent-Length: 5\n\nhelloGET /next HTTP/1.1\r\nAccept: */*\n\r\n";
+        let mut reader = BufReader::new(&raw[..]);
+
+        let first = Request::parse(&mut reader).unwrap();
+        assert_eq!(first.method(), "POST");
+        assert_eq!(first.path(), "/submit");
+        assert_eq!(first.version(), "HTTP/1.1");
+        assert_eq!(first.host(), Some("example.com"));
+        assert_eq!(first.body(), b"hello");
+
+        // Line endings may be mixed within a request
+        let second = Request::parse(&mut reader).unwrap();
+        assert_eq!(second.path(), "/next");
+        assert_eq!(second.header("Accept"), Some("*/*"));
+
+        // A carriage return alone does not end a line
+        assert!(Request::from_reader(
+            &b"GET / HTTP/1.1\rHost: example.com\n\n"[..]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_request_parser_resumes() {
+        let raw =
+            b"GET / HTTP/1.1\r\nContent-Length: 3\r\n\r\nabcextra";
+        let mut parser = RequestParser::new(MAX_BODY_LENGTH);
+        let mut consumed = 0;
+        for byte in raw.chunks(1) {
+            if parser.is_done() {
+                break;
+            }
+            consumed += parser.feed(byte);
+        }
+        assert_eq!(consumed, raw.len() - b"extra".len());
+        assert_eq!(parser.feed(b"more"), 0);
+        assert_eq!(parser.finish().unwrap().body(), b"abc");
+
+        // A body cut short by the end of the input is an error
+        let mut parser = RequestParser::new(MAX_BODY_LENGTH);
+        let _ = parser
+            .feed(b"GET / HTTP/1.1\r\nContent-Length: 3\r\n\r\na");
+        assert!(!parser.is_done());
+        assert!(parser.finish().is_err());
+
+        // Too many headers are rejected as they arrive
+        let mut parser = RequestParser::new(MAX_BODY_LENGTH);
+        let _ = parser.feed(b"GET / HTTP/1.1\r\n");
+        for _ in 0..=MAX_HEADERS {
+            let _ = parser.feed(b"X-Header: value\r\n");
+        }
+        assert!(parser.is_done());
+        assert!(matches!(
+            parser.finish(),
+            Err(ServerError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn test_request_body() {
+        let raw = b"POST /submit HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello GET";
+        let request = Request::from_reader(&raw[..]).unwrap();
+        assert_eq!(request.body(), b"hello");
+
+        // A body shorter than announced, or over the limit, is rejected
+        let raw =
+            b"POST /submit HTTP/1.1\r\nContent-Length: 10\r\n\r\nhello";
+        assert!(Request::from_reader(&raw[..]).is_err());
+        let raw = format!(
+            "POST /submit HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_LENGTH + 1
+        );
+        assert!(matches!(
+            Request::from_reader(raw.as_bytes()),
+            Err(ServerError::PayloadTooLarge(_))
+        ));
+    }
+
+    #[test]
+    fn test_form_params() {
+        let form = |content_type: &str, body: &str| {
+            let raw = format!(
+                "POST /form HTTP/1.1\r\nContent-Type: {}\r\nContent-Length: {}\r\n\r\n{}",
+                content_type,
+                body.len(),
+                body
+            );
+            Request::from_reader(raw.as_bytes()).unwrap().form_params()
+        };
+        let pairs = |list: &[(&str, &str)]| {
+            list.iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            form(
+                "application/x-www-form-urlencoded",
+                "q=a+b%26c&emoji=%F0%9F%98%80&x%3Dy=1"
+            ),
+            pairs(&[("q", "a b&c"), ("emoji", "😀"), ("x=y", "1")])
+        );
+        // Empty values, missing `=`, empty pairs and stray `%`
+        assert_eq!(
+            form(
+                "Application/X-WWW-Form-Urlencoded; charset=utf-8",
+                "a=&b&&c=100%&d=%zz"
+            ),
+            pairs(&[("a", ""), ("b", ""), ("c", "100%"), ("d", "%zz")])
+        );
+        assert!(form("application/json", "a=1").is_empty());
+    }
+
+    #[test]
+    fn test_request_line_requires_single_spaces() {
+        let parse = |line: &str| {
+            Request::from_reader(format!("{}\r\n\r\n", line).as_bytes())
+        };
+
+        assert!(parse("GET /index.html HTTP/1.1").is_ok());
+        for line in [
+            "GET  /index.html HTTP/1.1",
+            "GET /index.html  HTTP/1.1",
+            "GET\t/index.html HTTP/1.1",
+            "GET /index.html\tHTTP/1.1",
+            " GET /index.html HTTP/1.1",
+            "GET /index.html HTTP/1.1 ",
+            "GET /index.html HTTP/1.1\t",
+        ] {
+            assert!(
+                matches!(
+                    parse(line),
+                    Err(ServerError::InvalidRequest(_))
+                ),
+                "{:?} should be rejected",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn test_oversized_request_line_is_read_bounded() {
+        // A 10 MB request line is rejected after reading just past the limit
+        let mut data = b"GET /".to_vec();
+        data.resize(10 * 1024 * 1024, b'a');
+        let mut reader = io::Cursor::new(data);
+        let result = Request::parse(&mut reader);
+        assert!(matches!(result, Err(ServerError::InvalidRequest(_))));
+        assert_eq!(
+            reader.position(),
+            MAX_REQUEST_LINE_LENGTH as u64 + 1
+        );
+
+        // An endless line terminates too, as does an endless header line
+        let endless = b"GET /".chain(io::repeat(b'a'));
+        assert!(Request::from_reader(endless).is_err());
+        let endless =
+            b"GET / HTTP/1.1\r\nX-Long: ".chain(io::repeat(b'a'));
+        assert!(Request::from_reader(endless).is_err());
+    }
+
     #[test]
     fn test_oversized_request() {
         let listener = TcpListener::bind("127.0.0.1:0").unwrap();
@@ -341,6 +1883,82 @@ fn test_invalid_path() {
         ));
     }
 
+    #[test]
+    fn test_options_asterisk_form() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let _ = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(b"OPTIONS * HTTP/1.1\r\n\r\n").unwrap();
+        });
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let request = Request::from_stream(&stream).unwrap();
+
+        assert_eq!(request.method(), "OPTIONS");
+        assert_eq!(request.path(), "*");
+        assert_eq!(request.target(), RequestTarget::Asterisk);
+    }
+
+    #[test]
+    fn test_asterisk_form_requires_options() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let _ = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream.write_all(b"GET * HTTP/1.1\r\n\r\n").unwrap();
+        });
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let result = Request::from_stream(&stream);
+
+        assert!(matches!(
+            result.unwrap_err(),
+            ServerError::InvalidRequest(_)
+        ));
+    }
+
+    #[test]
+    fn test_absolute_form_target() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let _ = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            stream
+                .write_all(
+                    b"GET http://example.com:8080/path HTTP/1.1\r\nHost: other.com\r\n\r\n",
+                )
+                .unwrap();
+        });
+
+        let stream = TcpStream::connect(addr).unwrap();
+        let request = Request::from_stream(&stream).unwrap();
+
+        assert_eq!(request.path(), "/path");
+        assert_eq!(request.host(), Some("example.com:8080"));
+        assert_eq!(request.target(), RequestTarget::Origin);
+    }
+
+    #[test]
+    fn test_split_absolute_form() {
+        assert_eq!(
+            Request::split_absolute_form("http://example.com"),
+            Some(("example.com", "/".to_string()))
+        );
+        assert_eq!(
+            Request::split_absolute_form("HTTPS://example.com?q=1"),
+            Some(("example.com", "/?q=1".to_string()))
+        );
+        assert_eq!(Request::split_absolute_form("/index.html"), None);
+        assert_eq!(
+            Request::split_absolute_form("ftp://example.com/"),
+            None
+        );
+    }
+
     #[test]
     fn test_invalid_version() {
         let listener = TcpListener::bind("127.0.0.1:0").unwrap();
@@ -360,4 +1978,127 @@ fn test_invalid_version() {
             ServerError::InvalidRequest(_)
         ));
     }
+
+    #[test]
+    fn test_from_reader() {
+        let raw = b"POST /submit HTTP/1.0\r\nHost: example.com\r\nAccept: */*\r\n\r\n";
+        let request = Request::from_reader(&raw[..]).unwrap();
+
+        assert_eq!(request.method(), "POST");
+        assert_eq!(request.path(), "/submit");
+        assert_eq!(request.version(), "HTTP/1.0");
+        assert_eq!(request.header("accept"), Some("*/*"));
+
+        assert!(Request::from_reader(&b"GARBAGE\r\n\r\n"[..]).is_err());
+    }
+
+    #[test]
+    fn test_content_length_with_transfer_encoding_rejected() {
+        let raw = b"POST /submit HTTP/1.1\r\nHost: example.com\r\nContent-Length: 4\r\nTransfer-Encoding: chunked\r\n\r\n";
+        let result = Request::from_reader(&raw[..]);
+        assert!(matches!(result, Err(ServerError::InvalidRequest(_))));
+
+        let raw = b"POST /submit HTTP/1.1\r\nTransfer-Encoding: chunked\r\ncontent-length: 4\r\n\r\n";
+        let result = Request::from_reader(&raw[..]);
+        assert!(matches!(result, Err(ServerError::InvalidRequest(_))));
+    }
+
+    #[test]
+    fn test_conflicting_content_length_rejected() {
+        for raw in [
+            &b"POST / HTTP/1.1\r\nContent-Length: 4\r\nContent-Length: 5\r\n\r\n"[..],
+            &b"POST / HTTP/1.1\r\nContent-Length: 4, 5\r\n\r\n"[..],
+            &b"POST / HTTP/1.1\r\nContent-Length: +4\r\n\r\n"[..],
+            &b"POST / HTTP/1.1\r\nContent-Length: four\r\n\r\n"[..],
+        ] {
+            let result = Request::from_reader(raw);
+            assert!(matches!(result, Err(ServerError::InvalidRequest(_))));
+        }
+
+        // Repeated identical values are unambiguous
+        let raw = b"POST / HTTP/1.1\r\nContent-Length: 4\r\nContent-Length: 4, 4\r\n\r\nbody";
+        assert_eq!(
+            Request::from_reader(&raw[..]).unwrap().body(),
+            b"body"
+        );
+    }
+
+    #[test]
+    fn test_control_characters_rejected() {
+        for raw in [
+            &b"GET /a\x01b HTTP/1.1\r\n\r\n"[..],
+            &b"GET / HTTP/1.1\r\nX-Test: a\rInjected: yes\r\n\r\n"[..],
+            &b"GET / HTTP/1.1\r\nX-Test: nul\x00byte\r\n\r\n"[..],
+        ] {
+            let result = Request::from_reader(raw);
+            assert!(matches!(
+                result,
+                Err(ServerError::InvalidRequest(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_connection_options() {
+        let raw = b"GET / HTTP/1.1\r\nConnection: keep-alive, Upgrade\r\nUpgrade: websocket\r\nconnection: X-Custom\r\n\r\n";
+        let request = Request::from_reader(&raw[..]).unwrap();
+
+        assert!(request.has_connection_option("keep-alive"));
+        assert!(request.has_connection_option("KEEP-ALIVE"));
+        assert!(request.has_connection_option("upgrade"));
+        assert!(request.has_connection_option("x-custom"));
+        assert!(!request.has_connection_option("close"));
+        assert!(!request.has_connection_option("keep"));
+    }
+
+    #[test]
+    fn test_is_keep_alive() {
+        let cases = [
+            ("HTTP/1.1", None, true),
+            ("HTTP/1.1", Some("close"), false),
+            ("HTTP/1.0", None, false),
+            ("HTTP/1.0", Some("Keep-Alive"), true),
+            ("HTTP/1.1", Some("Upgrade, close"), false),
+        ];
+
+        for (version, connection, expected) in cases {
+            let request = Request {
+                method: "GET".to_string(),
+                path: "/".to_string(),
+                version: version.to_string(),
+                headers: connection
+                    .map(|v| ("Connection".to_string(), v.to_string()))
+                    .into_iter()
+                    .collect(),
+                raw_line: String::new(),
+                body: Vec::new(),
+            };
+            assert_eq!(
+                request.is_keep_alive(),
+                expected,
+                "{} with Connection: {:?}",
+                version,
+                connection
+            );
+        }
+    }
+
+    #[test]
+    fn test_raw_line() {
+        let raw = b"GET http://Example.com/docs/caf%C3%A9.html?lang=fr&q=a+b HTTP/1.1\r\nHost: ignored\r\n\r\n";
+        let request = Request::from_reader(&raw[..]).unwrap();
+
+        assert_eq!(
+            request.raw_line(),
+            "GET http://Example.com/docs/caf%C3%A9.html?lang=fr&q=a+b HTTP/1.1"
+        );
+        assert_eq!(request.method(), "GET");
+        assert_eq!(
+            request.path(),
+            "/docs/caf%C3%A9.html?lang=fr&q=a+b"
+        );
+        assert_eq!(request.version(), "HTTP/1.1");
+        assert_eq!(request.host(), Some("Example.com"));
+        assert_ne!(request.raw_line(), request.to_string());
+    }
 }
@@ -11,8 +11,19 @@
 //!
 //! - `Response`: Represents an HTTP response, containing status code, headers, and body.
 //! - `Response::new`: Creates a new `Response` instance.
+//! - `Response::try_new`: Creates a response, rejecting status text that could inject
+//!   extra lines (e.g. containing `\r\n`).
+//! - `Response::with_status`: Creates a response with the canonical reason phrase.
+//! - `Response::text` / `Response::html`: Create UTF-8 text or HTML responses.
+//! - `Response::from_file`: Creates a response serving a file, typed by its extension.
 //! - `Response::add_header`: Adds custom headers to the response.
+//! - `Response::try_add_header`: Adds a header, rejecting names and values that could inject
+//!   extra headers (e.g. containing `\r\n`).
+//! - `Response::set_header` / `Response::remove_header`: Replace or remove headers by name.
+//! - `Response::set_body`: Replaces the body, keeping any `Content-Length` header in sync.
+//! - `Response::attachment`: Marks the response as a download with a suggested file name.
 //! - `Response::send`: Sends the response over a writable stream (e.g., a network socket).
+//! - `Response::send_chunked`: Streams a body of unknown length using chunked transfer coding.
 //!
 //! This module integrates error handling via the `ServerError` type, ensuring that issues
 //! with sending the response or writing to the stream are properly captured and handled.
@@ -38,8 +49,12 @@
 //! HTTP/1.1 protocol.
 
 use crate::error::ServerError;
+use crate::files::{file_error, get_content_type};
+use log::warn;
 use serde::{Deserialize, Serialize};
-use std::io::Write;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::Path;
 
 /// Represents an HTTP response, including the status code, status text, headers, and body.
 #[derive(
@@ -74,11 +89,26 @@ impl Response {
     /// # Returns
     ///
     /// A new `Response` instance with the specified status code, status text, and body.
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if the status text contains control characters (see
+    /// [`Response::try_new`]). In release builds the canonical reason phrase for
+    /// `status_code` is used instead, with a warning, so it can never split the response.
     pub fn new(
         status_code: u16,
         status_text: &str,
         body: Vec<u8>,
     ) -> Self {
+        if !is_valid_header_value(status_text) {
+            let e = invalid_status_text(status_text);
+            if cfg!(debug_assertions) {
+                panic!("{}", e);
+            }
+            warn!("Replacing status text: {}", e);
+            return Self::with_status(status_code, body);
+        }
+
         Response {
             status_code,
             status_text: status_text.to_string(),
@@ -87,6 +117,476 @@ pub fn new(
         }
     }
 
+    /// Creates a new `Response` after validating its status text.
+    ///
+    /// The status text must not contain control characters other than horizontal tab.
+    /// In particular, `\r` and `\n` are rejected, which prevents injecting headers or a
+    /// whole response through the status line.
+    ///
+    /// # Arguments
+    ///
+    /// * `status_code` - The HTTP status code for the response.
+    /// * `status_text` - The status text corresponding to the status code.
+    /// * `body` - The body of the response, represented as a vector of bytes.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(Response)` - If the status text is valid.
+    /// * `Err(ServerError)` - If the status text is invalid.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::response::Response;
+    ///
+    /// assert!(Response::try_new(200, "Fine", Vec::new()).is_ok());
+    /// assert!(Response::try_new(200, "OK\r\nSet-Cookie: evil", Vec::new())
+    ///     .is_err());
+    /// ```
+    pub fn try_new(
+        status_code: u16,
+        status_text: &str,
+        body: Vec<u8>,
+    ) -> Result<Self, ServerError> {
+        if !is_valid_header_value(status_text) {
+            return Err(invalid_status_text(status_text));
+        }
+        Ok(Self::new(status_code, status_text, body))
+    }
+
+    /// Creates a new `Response` with the canonical reason phrase for its status code.
+    ///
+    /// Unknown status codes get the generic phrase of their class (e.g., "Client Error"
+    /// for an unknown 4xx code).
+    ///
+    /// # Arguments
+    ///
+    /// * `status_code` - The HTTP status code for the response.
+    /// * `body` - The body of the response, represented as a vector of bytes.
+    ///
+    /// # Returns
+    ///
+    /// A new `Response` instance with the standard status text.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::response::Response;
+    ///
+    /// let response = Response::with_status(404, Vec::new());
+    /// assert_eq!(response.status_text, "Not Found");
+    /// ```
+    pub fn with_status(status_code: u16, body: Vec<u8>) -> Self {
+        Response {
+            status_code,
+            status_text: reason_phrase(status_code).to_string(),
+            headers: Vec::new(),
+            body,
+        }
+    }
+
+    /// Creates a `204 No Content` response with an empty body.
+    ///
+    /// # Returns
+    ///
+    /// A new `Response` instance with status 204.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::response::Response;
+    ///
+    /// let response = Response::no_content();
+    /// assert_eq!(response.status_code, 204);
+    /// assert!(response.body.is_empty());
+    /// ```
+    pub fn no_content() -> Self {
+        Response::new(204, "No Content", Vec::new())
+    }
+
+    /// Creates a `304 Not Modified` response with an empty body.
+    ///
+    /// # Returns
+    ///
+    /// A new `Response` instance with status 304.
+    pub fn not_modified() -> Self {
+        Response::new(304, "Not Modified", Vec::new())
+    }
+
+    /// Creates a `404 Not Found` response with the given body.
+    ///
+    /// # Arguments
+    ///
+    /// * `body` - The body of the response, such as a custom error page.
+    ///
+    /// # Returns
+    ///
+    /// A new `Response` instance with status 404.
+    pub fn not_found(body: Vec<u8>) -> Self {
+        Response::new(404, "Not Found", body)
+    }
+
+    /// Creates a plain-text response.
+    ///
+    /// The body is sent as UTF-8 with `Content-Type: text/plain; charset=utf-8`, and the
+    /// status text is the canonical reason phrase for `status`.
+    ///
+    /// # Arguments
+    ///
+    /// * `status` - The HTTP status code.
+    /// * `body` - The text of the response body.
+    ///
+    /// # Returns
+    ///
+    /// A new `Response` instance with the text body.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::response::Response;
+    ///
+    /// let response = Response::text(200, "Hello, world!");
+    /// assert_eq!(response.status_text, "OK");
+    /// assert_eq!(response.body, b"Hello, world!");
+    /// ```
+    pub fn text(status: u16, body: impl Into<String>) -> Self {
+        Self::with_text_body(
+            status,
+            body.into(),
+            "text/plain; charset=utf-8",
+        )
+    }
+
+    /// Creates an HTML response.
+    ///
+    /// The body is sent as UTF-8 with `Content-Type: text/html; charset=utf-8`, and the
+    /// status text is the canonical reason phrase for `status`.
+    ///
+    /// # Arguments
+    ///
+    /// * `status` - The HTTP status code.
+    /// * `body` - The HTML document or fragment.
+    ///
+    /// # Returns
+    ///
+    /// A new `Response` instance with the HTML body.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::response::Response;
+    ///
+    /// let response = Response::html(404, "<h1>Not here</h1>");
+    /// assert_eq!(response.status_text, "Not Found");
+    /// ```
+    pub fn html(status: u16, body: impl Into<String>) -> Self {
+        Self::with_text_body(
+            status,
+            body.into(),
+            "text/html; charset=utf-8",
+        )
+    }
+
+    /// Creates a response serving the contents of a file.
+    ///
+    /// The whole file is read into the body, and the response gets a `Content-Type`
+    /// from the file extension, as the server uses for static files, and a matching
+    /// `Content-Length`.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file to serve.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing a `200 OK` response, or a `ServerError`: `NotFound` if the
+    /// file does not exist, `Forbidden` if it cannot be read because of its
+    /// permissions, and `Io` for any other failure.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::response::Response;
+    /// use http_handle::ServerError;
+    /// use std::path::Path;
+    ///
+    /// let result = Response::from_file(Path::new("missing.html"));
+    /// assert!(matches!(result, Err(ServerError::NotFound(_))));
+    /// ```
+    pub fn from_file(path: &Path) -> Result<Self, ServerError> {
+        let contents =
+            fs::read(path).map_err(|e| file_error(e, path))?;
+        let length = contents.len().to_string();
+        let mut response = Response::with_file_contents(path, contents);
+        response.add_header("Content-Length", &length);
+        Ok(response)
+    }
+
+    /// Creates a response serving contents already read from a file.
+    ///
+    /// The server uses this for bodies it reads itself, for example through its file
+    /// cache. No `Content-Length` is set, since the body may still be rewritten.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path of the file, which determines the `Content-Type`.
+    /// * `contents` - The contents of the file.
+    ///
+    /// # Returns
+    ///
+    /// A `200 OK` response with the contents as its body.
+    pub(crate) fn with_file_contents(
+        path: &Path,
+        contents: Vec<u8>,
+    ) -> Self {
+        let mut response = Response::new(200, "OK", contents);
+        response.add_header("Content-Type", get_content_type(path));
+        response
+    }
+
+    /// Creates a response with a UTF-8 body of the given content type.
+    fn with_text_body(
+        status: u16,
+        body: String,
+        content_type: &str,
+    ) -> Self {
+        let mut response = Response::new(
+            status,
+            reason_phrase(status),
+            body.into_bytes(),
+        );
+        response.add_header("Content-Type", content_type);
+        response
+    }
+
+    /// Creates a redirect response pointing the client to `location`.
+    ///
+    /// The response carries the given 3xx status, a `Location` header and a small HTML
+    /// body with a link to the target for clients that do not follow redirects
+    /// automatically.
+    ///
+    /// The location is percent-encoded so that it is a valid URI: spaces, non-ASCII
+    /// characters, control characters and other bytes not allowed in a URI are encoded,
+    /// while the delimiters `/`, `?`, `#` and the like are kept. It may be given
+    /// unencoded or already encoded, as a `%` followed by two hexadecimal digits is
+    /// taken to be an escape and left intact. A literal `%` that happens to precede two
+    /// hexadecimal digits must therefore be passed encoded, as `%25`.
+    ///
+    /// # Arguments
+    ///
+    /// * `status` - The redirect status code (e.g., 301, 302, 307 or 308).
+    /// * `location` - The URL or absolute path to redirect to.
+    ///
+    /// # Returns
+    ///
+    /// A new `Response` instance describing the redirect.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `status` is not in the 3xx range.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::response::Response;
+    ///
+    /// let response = Response::redirect(301, "/new");
+    /// assert_eq!(response.status_code, 301);
+    /// assert!(response
+    ///     .headers
+    ///     .contains(&("Location".to_string(), "/new".to_string())));
+    ///
+    /// let response = Response::redirect(302, "/café menu.html");
+    /// assert!(response.headers.contains(&(
+    ///     "Location".to_string(),
+    ///     "/caf%C3%A9%20menu.html".to_string()
+    /// )));
+    /// ```
+    pub fn redirect(status: u16, location: &str) -> Self {
+        assert!(
+            (300..400).contains(&status),
+            "redirect status must be 3xx, got {}",
+            status
+        );
+
+        let location = uri_encode(location);
+        let escaped = html_escape(&location);
+        let body = format!(
+            "<html><body>Redirecting to <a href=\"{0}\">{0}</a></body></html>",
+            escaped
+        );
+
+        let mut response = Response::new(
+            status,
+            reason_phrase(status),
+            body.into_bytes(),
+        );
+        response.add_header("Location", &location);
+        response.add_header("Content-Type", "text/html");
+        response
+    }
+
+    /// Creates a response delegating the body to a reverse proxy.
+    ///
+    /// The response carries an `X-Accel-Redirect` header and an empty body. nginx, which
+    /// this targets, intercepts such responses and serves the file at `internal_path`
+    /// from a location marked `internal` itself, so the application never reads the
+    /// bytes. The proxy sets the content headers from the file. Without such a proxy in
+    /// front, the client receives an empty response.
+    ///
+    /// The path is percent-encoded like the `Location` of [`Response::redirect`].
+    ///
+    /// # Arguments
+    ///
+    /// * `internal_path` - The URI of the file within the proxy's internal location,
+    ///   e.g. `/protected/report.pdf`.
+    ///
+    /// # Returns
+    ///
+    /// A new `Response` instance with status 200.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::response::Response;
+    ///
+    /// let response = Response::accel_redirect("/protected/report.pdf");
+    /// assert!(response.body.is_empty());
+    /// assert!(response.headers.contains(&(
+    ///     "X-Accel-Redirect".to_string(),
+    ///     "/protected/report.pdf".to_string()
+    /// )));
+    /// ```
+    pub fn accel_redirect(internal_path: &str) -> Self {
+        let mut response = Response::new(200, "OK", Vec::new());
+        response
+            .add_header("X-Accel-Redirect", &uri_encode(internal_path));
+        response
+    }
+
+    /// Replaces the body of the response.
+    ///
+    /// Any `Content-Length` header already set is updated to the length of the new
+    /// body, so the response framing stays correct. No header is added if none was set.
+    ///
+    /// # Arguments
+    ///
+    /// * `body` - The new body of the response.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::response::Response;
+    ///
+    /// let mut response = Response::new(200, "OK", b"short".to_vec());
+    /// response.add_header("Content-Length", "5");
+    /// response.set_body(b"a longer body".to_vec());
+    /// assert_eq!(response.headers[0].1, "13");
+    /// ```
+    pub fn set_body(&mut self, body: Vec<u8>) {
+        let length = body.len().to_string();
+        for (name, value) in &mut self.headers {
+            if name.eq_ignore_ascii_case("Content-Length") {
+                value.clone_from(&length);
+            }
+        }
+        self.body = body;
+    }
+
+    /// Marks the response as a download with the given file name.
+    ///
+    /// Sets `Content-Disposition: attachment` so that browsers save the body instead of
+    /// rendering it. ASCII file names are sent as a quoted `filename` parameter. Names
+    /// with non-ASCII characters additionally get an RFC 5987 `filename*` parameter with
+    /// the UTF-8 name percent-encoded, alongside an ASCII fallback for older clients.
+    ///
+    /// # Arguments
+    ///
+    /// * `filename` - The file name suggested to the client.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::response::Response;
+    ///
+    /// let mut response = Response::new(200, "OK", Vec::new());
+    /// response.attachment("report.csv");
+    /// assert_eq!(
+    ///     response.headers[0].1,
+    ///     "attachment; filename=\"report.csv\""
+    /// );
+    /// ```
+    pub fn attachment(&mut self, filename: &str) {
+        // Control characters cannot appear in a header value; drop them
+        let filename: String =
+            filename.chars().filter(|c| !c.is_control()).collect();
+
+        let fallback: String = filename
+            .chars()
+            .map(|c| match c {
+                '"' | '\\' => format!("\\{}", c),
+                c if c.is_ascii() => c.to_string(),
+                _ => "_".to_string(),
+            })
+            .collect();
+
+        let value = if filename.is_ascii() {
+            format!("attachment; filename=\"{}\"", fallback)
+        } else {
+            format!(
+                "attachment; filename=\"{}\"; filename*=UTF-8''{}",
+                fallback,
+                rfc5987_encode(&filename)
+            )
+        };
+        self.add_header("Content-Disposition", &value);
+    }
+
+    /// Adds a field to the `Vary` header of the response.
+    ///
+    /// `Vary` tells caches which request headers the response depends on, so they do
+    /// not serve one variant to a client that asked for another. The field is appended
+    /// to an existing `Vary` header, or a new one is added. Nothing changes if the field
+    /// is already listed (compared case-insensitively) or the header is `*`.
+    ///
+    /// # Arguments
+    ///
+    /// * `field` - The request header name the response varies on (e.g., "Accept-Encoding").
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::response::Response;
+    ///
+    /// let mut response = Response::new(200, "OK", Vec::new());
+    /// response.add_vary("Accept-Encoding");
+    /// response.add_vary("Accept-Language");
+    /// response.add_vary("accept-encoding");
+    /// assert_eq!(
+    ///     response.headers[0].1,
+    ///     "Accept-Encoding, Accept-Language"
+    /// );
+    /// ```
+    pub fn add_vary(&mut self, field: &str) {
+        let existing = self
+            .headers
+            .iter_mut()
+            .find(|(name, _)| name.eq_ignore_ascii_case("Vary"));
+        match existing {
+            Some((_, value)) => {
+                let listed = value.split(',').any(|listed| {
+                    let listed = listed.trim();
+                    listed == "*" || listed.eq_ignore_ascii_case(field)
+                });
+                if !listed {
+                    value.push_str(", ");
+                    value.push_str(field);
+                }
+            }
+            None => self.add_header("Vary", field),
+        }
+    }
+
     /// Adds a header to the response.
     ///
     /// This method allows you to add custom headers to the response, which will be included
@@ -96,14 +596,138 @@ pub fn new(
     ///
     /// * `name` - The name of the header (e.g., "Content-Type").
     /// * `value` - The value of the header (e.g., "text/html").
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if the name or value is invalid (see
+    /// [`Response::try_add_header`]). In release builds the invalid header is dropped
+    /// with a warning instead, so it can never split the response.
     pub fn add_header(&mut self, name: &str, value: &str) {
+        if let Err(e) = self.try_add_header(name, value) {
+            if cfg!(debug_assertions) {
+                panic!("{}", e);
+            }
+            warn!("Dropping header: {}", e);
+        }
+    }
+
+    /// Sets a header, replacing any existing header with the same name.
+    ///
+    /// Existing headers are matched case-insensitively and all of them are removed, so
+    /// the response ends up with exactly one header of that name. The new header is
+    /// validated like [`Response::add_header`]; if it is invalid, the existing headers
+    /// are kept.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the header (e.g., "Content-Type").
+    /// * `value` - The value of the header (e.g., "text/html").
+    ///
+    /// # Panics
+    ///
+    /// In debug builds, panics if the name or value is invalid (see
+    /// [`Response::try_add_header`]).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::response::Response;
+    ///
+    /// let mut response = Response::text(200, "{}");
+    /// response.set_header("content-type", "application/json");
+    /// assert_eq!(response.headers.len(), 1);
+    /// assert_eq!(response.headers[0].1, "application/json");
+    /// ```
+    pub fn set_header(&mut self, name: &str, value: &str) {
+        if is_valid_header_name(name) && is_valid_header_value(value) {
+            self.remove_header(name);
+        }
+        self.add_header(name, value);
+    }
+
+    /// Removes every header with the given name, compared case-insensitively.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the header to remove (e.g., "Server").
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::response::Response;
+    ///
+    /// let mut response = Response::new(200, "OK", Vec::new());
+    /// response.add_header("Set-Cookie", "a=1");
+    /// response.add_header("set-cookie", "b=2");
+    /// response.remove_header("SET-COOKIE");
+    /// assert!(response.headers.is_empty());
+    /// ```
+    pub fn remove_header(&mut self, name: &str) {
+        self.headers
+            .retain(|(header, _)| !header.eq_ignore_ascii_case(name));
+    }
+
+    /// Adds a header to the response after validating its name and value.
+    ///
+    /// The name must be a non-empty HTTP token and the value must not contain control
+    /// characters other than horizontal tab. In particular, `\r` and `\n` are rejected,
+    /// which prevents response splitting and header injection when the header is derived
+    /// from request data.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the header (e.g., "Content-Type").
+    /// * `value` - The value of the header (e.g., "text/html").
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the header was added.
+    /// * `Err(ServerError)` - If the name or value is invalid; the header is not added.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::response::Response;
+    ///
+    /// let mut response = Response::new(200, "OK", Vec::new());
+    /// assert!(response.try_add_header("X-Id", "42").is_ok());
+    /// assert!(response
+    ///     .try_add_header("X-Id", "42\r\nSet-Cookie: evil")
+    ///     .is_err());
+    /// assert_eq!(response.headers.len(), 1);
+    /// ```
+    pub fn try_add_header(
+        &mut self,
+        name: &str,
+        value: &str,
+    ) -> Result<(), ServerError> {
+        if !is_valid_header_name(name) {
+            return Err(ServerError::Custom(format!(
+                "Invalid header name: {:?}",
+                name
+            )));
+        }
+        if !is_valid_header_value(value) {
+            return Err(ServerError::Custom(format!(
+                "Invalid value for header {}: {:?}",
+                name, value
+            )));
+        }
+
         self.headers.push((name.to_string(), value.to_string()));
+        Ok(())
     }
 
     /// Sends the response over the provided `Write` stream.
     ///
     /// This method writes the HTTP status line, headers, and body to the stream, ensuring
-    /// the client receives the complete response.
+    /// the client receives the complete response. Informational (1xx), `204 No Content`
+    /// and `304 Not Modified` responses must not carry a body, so for these status codes
+    /// neither the body nor any `Content-Length` header is written, even if set.
+    ///
+    /// Output is buffered, so the status line and headers leave in a single write
+    /// together with a small body, instead of one system call per header on a raw
+    /// socket. The stream is flushed before returning.
     ///
     /// # Arguments
     ///
@@ -117,22 +741,329 @@ pub fn send<W: Write>(
         &self,
         stream: &mut W,
     ) -> Result<(), ServerError> {
+        let mut stream = BufWriter::new(stream);
         write!(
             stream,
             "HTTP/1.1 {} {}\r\n",
             self.status_code, self.status_text
         )?;
 
+        let allows_body = self.allows_body();
         for (name, value) in &self.headers {
+            if !allows_body
+                && name.eq_ignore_ascii_case("Content-Length")
+            {
+                continue;
+            }
             write!(stream, "{}: {}\r\n", name, value)?;
         }
 
         write!(stream, "\r\n")?;
-        stream.write_all(&self.body)?;
+        if allows_body {
+            stream.write_all(&self.body)?;
+        }
+        stream.flush()?;
+
+        Ok(())
+    }
+
+    /// Serializes the response into the exact bytes [`Response::send`] would write.
+    ///
+    /// This is useful for assertions in tests and for buffering or forwarding a
+    /// response without a stream.
+    ///
+    /// # Returns
+    ///
+    /// The status line, headers and body as a byte vector.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::response::Response;
+    ///
+    /// let response = Response::text(200, "Hi");
+    /// assert_eq!(
+    ///     response.into_bytes(),
+    ///     b"HTTP/1.1 200 OK\r\nContent-Type: text/plain; charset=utf-8\r\n\r\nHi"
+    /// );
+    /// ```
+    pub fn into_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.body.len() + 256);
+        // Writing into a `Vec` cannot fail
+        let _ = self.send(&mut bytes);
+        bytes
+    }
+
+    /// Sends the response over the provided `Write` stream using chunked transfer coding.
+    ///
+    /// This is intended for content whose length is not known up front. The status line
+    /// and headers are written with `Transfer-Encoding: chunked`, then each item yielded
+    /// by `chunks` is written as a separate chunk, followed by the terminating zero-length
+    /// chunk. Any `Content-Length` or `Transfer-Encoding` header set on the response is
+    /// ignored, as is `self.body`. Empty chunks are skipped, since a zero-length chunk
+    /// would end the body early.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` - A mutable reference to any stream that implements `Write`.
+    /// * `chunks` - The body data, produced piece by piece.
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(())` - If the response is successfully sent.
+    /// * `Err(ServerError)` - If an error occurs while sending the response.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::response::Response;
+    /// use std::io::Cursor;
+    ///
+    /// let response = Response::new(200, "OK", Vec::new());
+    /// let chunks = vec![b"Hello, ".to_vec(), b"world!".to_vec()];
+    ///
+    /// let mut stream = Cursor::new(Vec::new());
+    /// response.send_chunked(&mut stream, chunks).unwrap();
+    ///
+    /// let written = stream.into_inner();
+    /// assert!(written.ends_with(b"7\r\nHello, \r\n6\r\nworld!\r\n0\r\n\r\n"));
+    /// ```
+    pub fn send_chunked<W, I>(
+        &self,
+        stream: &mut W,
+        chunks: I,
+    ) -> Result<(), ServerError>
+    where
+        W: Write,
+        I: IntoIterator<Item = Vec<u8>>,
+    {
+        write!(
+            stream,
+            "HTTP/1.1 {} {}\r\n",
+            self.status_code, self.status_text
+        )?;
+
+        for (name, value) in &self.headers {
+            if name.eq_ignore_ascii_case("Content-Length")
+                || name.eq_ignore_ascii_case("Transfer-Encoding")
+            {
+                continue;
+            }
+            write!(stream, "{}: {}\r\n", name, value)?;
+        }
+        write!(stream, "Transfer-Encoding: chunked\r\n\r\n")?;
+
+        for chunk in chunks {
+            if chunk.is_empty() {
+                continue;
+            }
+            write!(stream, "{:x}\r\n", chunk.len())?;
+            stream.write_all(&chunk)?;
+            write!(stream, "\r\n")?;
+        }
+        write!(stream, "0\r\n\r\n")?;
         stream.flush()?;
 
         Ok(())
     }
+
+    /// Returns whether the status code permits a message body.
+    ///
+    /// # Returns
+    ///
+    /// `false` for 1xx, 204 and 304 responses, `true` otherwise.
+    pub(crate) fn allows_body(&self) -> bool {
+        !matches!(self.status_code, 100..=199 | 204 | 304)
+    }
+}
+
+/// Returns the canonical reason phrase for a status code.
+///
+/// # Arguments
+///
+/// * `status` - The HTTP status code.
+///
+/// # Returns
+///
+/// The reason phrase registered for the code, or a generic phrase for its class
+/// (e.g., "Redirect" for an unassigned 3xx code).
+pub(crate) fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        100 => "Continue",
+        101 => "Switching Protocols",
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        204 => "No Content",
+        206 => "Partial Content",
+        300 => "Multiple Choices",
+        301 => "Moved Permanently",
+        302 => "Found",
+        303 => "See Other",
+        304 => "Not Modified",
+        307 => "Temporary Redirect",
+        308 => "Permanent Redirect",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        406 => "Not Acceptable",
+        408 => "Request Timeout",
+        409 => "Conflict",
+        410 => "Gone",
+        411 => "Length Required",
+        412 => "Precondition Failed",
+        413 => "Content Too Large",
+        414 => "URI Too Long",
+        415 => "Unsupported Media Type",
+        416 => "Range Not Satisfiable",
+        417 => "Expectation Failed",
+        429 => "Too Many Requests",
+        431 => "Request Header Fields Too Large",
+        500 => "Internal Server Error",
+        501 => "Not Implemented",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        504 => "Gateway Timeout",
+        505 => "HTTP Version Not Supported",
+        _ => match status / 100 {
+            1 => "Informational",
+            2 => "Success",
+            3 => "Redirect",
+            4 => "Client Error",
+            _ => "Server Error",
+        },
+    }
+}
+
+/// Builds the error for status text that would corrupt the status line.
+///
+/// # Arguments
+///
+/// * `status_text` - The rejected status text.
+///
+/// # Returns
+///
+/// A `ServerError::Custom` describing the status text.
+fn invalid_status_text(status_text: &str) -> ServerError {
+    ServerError::Custom(format!(
+        "Invalid status text: {:?}",
+        status_text
+    ))
+}
+
+/// Checks whether a header name is a valid HTTP token (RFC 7230 section 3.2.6).
+///
+/// # Arguments
+///
+/// * `name` - The header name to check.
+///
+/// # Returns
+///
+/// `true` if the name is non-empty and only contains token characters.
+pub(crate) fn is_valid_header_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.bytes().all(|b| {
+            b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+        })
+}
+
+/// Checks whether a header value is free of control characters.
+///
+/// Horizontal tab is the only control character allowed in a field value.
+///
+/// # Arguments
+///
+/// * `value` - The header value to check.
+///
+/// # Returns
+///
+/// `true` if the value contains no `\r`, `\n`, NUL or other control characters.
+pub(crate) fn is_valid_header_value(value: &str) -> bool {
+    !value.chars().any(|c| c.is_ascii_control() && c != '\t')
+}
+
+/// Percent-encodes a value for an RFC 5987 extended header parameter.
+///
+/// # Arguments
+///
+/// * `value` - The UTF-8 value to encode.
+///
+/// # Returns
+///
+/// The value with every byte outside the RFC 5987 `attr-char` set percent-encoded.
+fn rfc5987_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len() * 3);
+    for byte in value.bytes() {
+        if byte.is_ascii_alphanumeric()
+            || b"!#$&+-.^_`|~".contains(&byte)
+        {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Percent-encodes the bytes of a URI reference that are not allowed in a URI.
+///
+/// Unreserved and reserved characters (RFC 3986 section 2) are kept, as are existing
+/// `%XX` escapes; a `%` not starting an escape is encoded as `%25`.
+///
+/// # Arguments
+///
+/// * `uri` - The URI reference, unencoded or already encoded.
+///
+/// # Returns
+///
+/// The URI reference with every other byte percent-encoded.
+fn uri_encode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let is_hex = |index: usize| {
+        bytes.get(index).map_or(false, u8::is_ascii_hexdigit)
+    };
+    let mut encoded = String::with_capacity(uri.len());
+    for (index, &byte) in bytes.iter().enumerate() {
+        let keep = match byte {
+            b'%' => is_hex(index + 1) && is_hex(index + 2),
+            _ => {
+                byte.is_ascii_alphanumeric()
+                    || b"-._~:/?#[]@!$&'()*+,;=".contains(&byte)
+            }
+        };
+        if keep {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+/// Escapes the characters that are significant in HTML text and attribute values.
+///
+/// # Arguments
+///
+/// * `input` - The text to escape.
+///
+/// # Returns
+///
+/// A `String` safe to embed in an HTML document.
+fn html_escape(input: &str) -> String {
+    let mut escaped = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
 }
 
 #[cfg(test)]
@@ -237,4 +1168,513 @@ fn flush(&mut self) -> io::Result<()> {
 
         assert!(result.is_err());
     }
+
+    /// Test case for the `Response::redirect` method.
+    #[test]
+    fn test_response_redirect() {
+        let response = Response::redirect(301, "/new");
+
+        assert_eq!(response.status_code, 301);
+        assert_eq!(response.status_text, "Moved Permanently");
+        assert!(response
+            .headers
+            .contains(&("Location".to_string(), "/new".to_string())));
+        assert_eq!(
+            response.body,
+            b"<html><body>Redirecting to <a href=\"/new\">/new</a></body></html>"
+        );
+    }
+
+    /// Test case for `Response::redirect` escaping the location in the HTML body.
+    #[test]
+    fn test_response_redirect_escapes_body() {
+        let response = Response::redirect(302, "/a?b=1&c='2'");
+
+        assert_eq!(response.status_text, "Found");
+        let body = String::from_utf8(response.body).unwrap();
+        assert!(body.contains("/a?b=1&amp;c=&#39;2&#39;"));
+    }
+
+    /// Test case for `Response::from_file` with an existing and a missing file.
+    #[test]
+    fn test_response_from_file() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join("style.css");
+        fs::write(&path, "body { margin: 0 }").unwrap();
+
+        let response = Response::from_file(&path).unwrap();
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.body, b"body { margin: 0 }");
+        assert_eq!(
+            response.headers,
+            vec![
+                (
+                    "Content-Type".to_string(),
+                    "text/css; charset=utf-8".to_string()
+                ),
+                ("Content-Length".to_string(), "18".to_string()),
+            ]
+        );
+
+        let missing = temp_dir.path().join("missing.css");
+        assert!(matches!(
+            Response::from_file(&missing),
+            Err(ServerError::NotFound(_))
+        ));
+    }
+
+    /// Test case for `Response::accel_redirect` delegating the body to the proxy.
+    #[test]
+    fn test_response_accel_redirect() {
+        let response =
+            Response::accel_redirect("/internal/my report.pdf");
+        assert_eq!(response.status_code, 200);
+        assert!(response.body.is_empty());
+        assert_eq!(
+            response.headers,
+            vec![(
+                "X-Accel-Redirect".to_string(),
+                "/internal/my%20report.pdf".to_string()
+            )]
+        );
+    }
+
+    /// Test case for `Response::redirect` percent-encoding the location.
+    #[test]
+    fn test_response_redirect_encodes_location() {
+        let location = |target: &str| {
+            Response::redirect(301, target)
+                .headers
+                .into_iter()
+                .find(|(name, _)| name == "Location")
+                .map(|(_, value)| value)
+                .unwrap()
+        };
+
+        assert_eq!(location("/my file.html"), "/my%20file.html");
+        assert_eq!(
+            location("/naïve/ünïcode"),
+            "/na%C3%AFve/%C3%BCn%C3%AFcode"
+        );
+        assert_eq!(
+            location("https://example.com/a b?q=x y#top"),
+            "https://example.com/a%20b?q=x%20y#top"
+        );
+        assert_eq!(
+            location("/say\"hi\"<now>"),
+            "/say%22hi%22%3Cnow%3E"
+        );
+
+        // Existing escapes are kept; a stray percent sign is encoded
+        assert_eq!(location("/my%20file.html"), "/my%20file.html");
+        assert_eq!(location("/100%"), "/100%25");
+        assert_eq!(location("/50%off"), "/50%25off");
+
+        // Control characters cannot split the response
+        assert_eq!(
+            location("/a\r\nSet-Cookie: x"),
+            "/a%0D%0ASet-Cookie:%20x"
+        );
+    }
+
+    /// Test case for `Response::redirect` rejecting a non-3xx status.
+    #[test]
+    #[should_panic(expected = "redirect status must be 3xx")]
+    fn test_response_redirect_invalid_status() {
+        let _ = Response::redirect(200, "/new");
+    }
+
+    /// Test case for the status shortcut constructors.
+    #[test]
+    fn test_status_shortcuts() {
+        let no_content = Response::no_content();
+        assert_eq!(no_content.status_code, 204);
+        assert_eq!(no_content.status_text, "No Content");
+
+        let not_modified = Response::not_modified();
+        assert_eq!(not_modified.status_code, 304);
+        assert_eq!(not_modified.status_text, "Not Modified");
+
+        let not_found = Response::not_found(b"missing".to_vec());
+        assert_eq!(not_found.status_code, 404);
+        assert_eq!(not_found.body, b"missing");
+    }
+
+    /// Test case ensuring no body bytes are written for 204 and 304 responses.
+    #[test]
+    fn test_send_omits_body_for_no_content_and_not_modified() {
+        for mut response in
+            [Response::no_content(), Response::not_modified()]
+        {
+            response.body = b"ignored".to_vec();
+
+            let mut mock_stream = Cursor::new(Vec::new());
+            response.send(&mut mock_stream).unwrap();
+
+            let written = mock_stream.into_inner();
+            assert!(written.ends_with(b"\r\n\r\n"));
+            assert!(!written.windows(7).any(|w| w == b"ignored"));
+            assert!(!String::from_utf8_lossy(&written)
+                .contains("Content-Length"));
+        }
+    }
+
+    /// Test case ensuring 1xx, 204 and 304 responses end at the blank line, dropping a
+    /// manually set body and `Content-Length` header.
+    #[test]
+    fn test_send_suppresses_body_and_content_length() {
+        for (status_code, status_text) in [
+            (100, "Continue"),
+            (204, "No Content"),
+            (304, "Not Modified"),
+        ] {
+            let mut response = Response::new(
+                status_code,
+                status_text,
+                b"body".to_vec(),
+            );
+            response.add_header("Content-Length", "4");
+            response.add_header("ETag", "\"abc\"");
+
+            let mut mock_stream = Cursor::new(Vec::new());
+            response.send(&mut mock_stream).unwrap();
+
+            let expected = format!(
+                "HTTP/1.1 {} {}\r\nETag: \"abc\"\r\n\r\n",
+                status_code, status_text
+            );
+            assert_eq!(mock_stream.into_inner(), expected.as_bytes());
+        }
+    }
+
+    /// Decodes a chunked message body, returning the reassembled data.
+    fn decode_chunked(mut data: &[u8]) -> Vec<u8> {
+        let mut body = Vec::new();
+        loop {
+            let line_end =
+                data.windows(2).position(|w| w == b"\r\n").unwrap();
+            let size = usize::from_str_radix(
+                std::str::from_utf8(&data[..line_end]).unwrap(),
+                16,
+            )
+            .unwrap();
+            data = &data[line_end + 2..];
+            if size == 0 {
+                assert_eq!(data, b"\r\n");
+                return body;
+            }
+            body.extend_from_slice(&data[..size]);
+            assert_eq!(&data[size..size + 2], b"\r\n");
+            data = &data[size + 2..];
+        }
+    }
+
+    /// Test case for `Response::send_chunked`, decoding the framed output.
+    #[test]
+    fn test_response_send_chunked() {
+        let mut response = Response::new(200, "OK", Vec::new());
+        response.add_header("Content-Type", "text/plain");
+        response.add_header("Content-Length", "999");
+
+        let chunks = vec![
+            b"Hello".to_vec(),
+            Vec::new(),
+            b", chunked ".to_vec(),
+            vec![b'x'; 300],
+        ];
+        let mut mock_stream = Cursor::new(Vec::new());
+        response.send_chunked(&mut mock_stream, chunks).unwrap();
+
+        let written = mock_stream.into_inner();
+        let head_end =
+            written.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+        let head = String::from_utf8_lossy(&written[..head_end]);
+        assert!(head.contains("Transfer-Encoding: chunked"));
+        assert!(!head.contains("Content-Length"));
+
+        let body = decode_chunked(&written[head_end + 4..]);
+        let mut expected = b"Hello, chunked ".to_vec();
+        expected.extend(vec![b'x'; 300]);
+        assert_eq!(body, expected);
+    }
+
+    /// Test case for `Response::try_add_header` rejecting injected CRLF.
+    #[test]
+    fn test_try_add_header_rejects_crlf() {
+        let mut response = Response::new(200, "OK", Vec::new());
+
+        assert!(response
+            .try_add_header(
+                "Location",
+                "/a\r\nSet-Cookie: session=evil"
+            )
+            .is_err());
+        assert!(response
+            .try_add_header("X-Test", "line\nbreak")
+            .is_err());
+        assert!(response
+            .try_add_header("X-Test", "nul\0byte")
+            .is_err());
+        assert!(response
+            .try_add_header("X-Test\r\nSet-Cookie", "evil")
+            .is_err());
+        assert!(response.try_add_header("Bad Name", "value").is_err());
+        assert!(response.try_add_header("", "value").is_err());
+        assert!(response.headers.is_empty());
+
+        response.try_add_header("X-Test", "tab\tallowed").unwrap();
+        assert_eq!(response.headers.len(), 1);
+    }
+
+    /// Test case for rejecting status text that would inject extra lines.
+    #[test]
+    fn test_try_new_rejects_crlf_in_status_text() {
+        let result = Response::try_new(
+            200,
+            "OK\r\nSet-Cookie: session=evil",
+            Vec::new(),
+        );
+        assert!(matches!(result, Err(ServerError::Custom(_))));
+        assert!(Response::try_new(200, "OK\n", Vec::new()).is_err());
+        assert!(Response::try_new(200, "O\0K", Vec::new()).is_err());
+
+        let response =
+            Response::try_new(299, "Custom\tPhrase", Vec::new())
+                .unwrap();
+        assert_eq!(response.status_text, "Custom\tPhrase");
+    }
+
+    /// Test case for `Response::new` refusing malicious status text in debug builds.
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "Invalid status text")]
+    fn test_new_panics_on_crlf_in_status_text() {
+        let _ = Response::new(
+            200,
+            "OK\r\nContent-Length: 0\r\n\r\nHTTP/1.1 200 OK",
+            Vec::new(),
+        );
+    }
+
+    /// Test case for the canonical reason phrase of `Response::with_status`.
+    #[test]
+    fn test_with_status() {
+        let response = Response::with_status(503, b"busy".to_vec());
+        assert_eq!(response.status_text, "Service Unavailable");
+        assert_eq!(response.body, b"busy");
+        assert_eq!(
+            Response::with_status(499, Vec::new()).status_text,
+            "Client Error"
+        );
+    }
+
+    /// Test case for case-insensitive header removal.
+    #[test]
+    fn test_remove_header() {
+        let mut response = Response::new(200, "OK", Vec::new());
+        response.add_header("Server", "http-handle");
+        response.add_header("X-Keep", "yes");
+        response.add_header("server", "duplicate");
+        response.remove_header("SERVER");
+        assert_eq!(
+            response.headers,
+            vec![("X-Keep".to_string(), "yes".to_string())]
+        );
+
+        // Removing a header that is not present is a no-op
+        response.remove_header("Server");
+        assert_eq!(response.headers.len(), 1);
+    }
+
+    /// Test case for `Response::set_header` replacing instead of duplicating.
+    #[test]
+    fn test_set_header() {
+        let mut response = Response::text(200, "{}");
+        response.add_header("content-type", "text/html");
+        response.set_header("Content-Type", "application/json");
+        response.set_header("Content-Type", "application/json");
+        let content_types: Vec<_> = response
+            .headers
+            .iter()
+            .filter(|(name, _)| {
+                name.eq_ignore_ascii_case("Content-Type")
+            })
+            .collect();
+        assert_eq!(content_types.len(), 1);
+        assert_eq!(content_types[0].1, "application/json");
+
+        // Setting a header that is not present inserts it
+        response.set_header("X-Id", "42");
+        assert_eq!(response.headers.len(), 2);
+    }
+
+    /// Test case for `Response::set_header` panicking on invalid input in debug builds.
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "Invalid value for header Location")]
+    fn test_set_header_panics_on_crlf_in_debug() {
+        let mut response = Response::new(302, "Found", Vec::new());
+        response.add_header("Location", "/safe");
+        response.set_header("Location", "/a\r\nSet-Cookie: evil");
+    }
+
+    /// Test case for `Response::add_header` panicking on invalid input in debug builds.
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "Invalid value for header X-Test")]
+    fn test_add_header_panics_on_crlf_in_debug() {
+        let mut response = Response::new(200, "OK", Vec::new());
+        response.add_header("X-Test", "value\r\nInjected: yes");
+    }
+
+    /// Test case for `Response::attachment` with an ASCII file name.
+    #[test]
+    fn test_attachment_ascii_filename() {
+        let mut response = Response::new(200, "OK", Vec::new());
+        response.attachment("quarterly \"final\" report.csv");
+
+        assert_eq!(
+            response.headers,
+            vec![(
+                "Content-Disposition".to_string(),
+                "attachment; filename=\"quarterly \\\"final\\\" report.csv\""
+                    .to_string()
+            )]
+        );
+    }
+
+    /// Test case for `Response::attachment` with a UTF-8 file name.
+    #[test]
+    fn test_attachment_utf8_filename() {
+        let mut response = Response::new(200, "OK", Vec::new());
+        response.attachment("résumé €.pdf");
+
+        assert_eq!(
+            response.headers[0].1,
+            "attachment; filename=\"r_sum_ _.pdf\"; filename*=UTF-8''r%C3%A9sum%C3%A9%20%E2%82%AC.pdf"
+        );
+    }
+
+    /// Test case for `Response::text` and `Response::html`.
+    #[test]
+    fn test_text_and_html_responses() {
+        let response = Response::text(201, String::from("created"));
+        assert_eq!(response.status_code, 201);
+        assert_eq!(response.status_text, "Created");
+        assert_eq!(response.body, b"created");
+        assert_eq!(
+            response.headers,
+            vec![(
+                "Content-Type".to_string(),
+                "text/plain; charset=utf-8".to_string()
+            )]
+        );
+
+        let response = Response::html(404, "<p>¿Dónde?</p>");
+        assert_eq!(response.status_code, 404);
+        assert_eq!(response.status_text, "Not Found");
+        assert_eq!(response.body, "<p>¿Dónde?</p>".as_bytes());
+        assert_eq!(
+            response.headers[0].1,
+            "text/html; charset=utf-8".to_string()
+        );
+    }
+
+    /// Test case for `Response::set_body` keeping `Content-Length` in sync.
+    #[test]
+    fn test_set_body_updates_content_length() {
+        let mut response = Response::new(200, "OK", Vec::new());
+        response.set_body(b"first".to_vec());
+        response.add_header("content-length", "5");
+
+        response.set_body(b"replacement body".to_vec());
+        assert_eq!(response.body, b"replacement body");
+        assert_eq!(
+            response.headers,
+            vec![("content-length".to_string(), "16".to_string())]
+        );
+
+        // Without a Content-Length header, none is added
+        let mut response = Response::new(200, "OK", Vec::new());
+        response.set_body(b"body".to_vec());
+        assert!(response.headers.is_empty());
+    }
+
+    /// Test case for `Response::add_vary` merging fields into one header.
+    #[test]
+    fn test_add_vary() {
+        let mut response = Response::new(200, "OK", Vec::new());
+        response.add_vary("Accept-Encoding");
+        response.add_vary("ACCEPT-ENCODING");
+        response.add_vary("Accept-Language");
+        assert_eq!(
+            response.headers,
+            vec![(
+                "Vary".to_string(),
+                "Accept-Encoding, Accept-Language".to_string()
+            )]
+        );
+
+        // A wildcard already covers every field
+        let mut response = Response::new(200, "OK", Vec::new());
+        response.add_header("vary", "*");
+        response.add_vary("Accept-Encoding");
+        assert_eq!(response.headers[0].1, "*");
+    }
+
+    /// Test case for `Response::into_bytes` matching `Response::send`.
+    #[test]
+    fn test_into_bytes_matches_send() {
+        let mut response =
+            Response::new(200, "OK", b"Hello, World!".to_vec());
+        response.add_header("Content-Type", "text/plain");
+        response.add_header("Content-Length", "13");
+
+        let mut cursor = Cursor::new(Vec::new());
+        response.send(&mut cursor).unwrap();
+        assert_eq!(response.into_bytes(), cursor.into_inner());
+
+        // Body suppression applies too
+        let response = Response::not_modified();
+        let mut cursor = Cursor::new(Vec::new());
+        response.send(&mut cursor).unwrap();
+        assert_eq!(response.into_bytes(), cursor.into_inner());
+    }
+
+    /// Test case for `Response::send` coalescing the response into few writes.
+    #[test]
+    fn test_send_is_buffered() {
+        /// A writer recording each write call separately.
+        #[derive(Default)]
+        struct RecordingWriter {
+            writes: Vec<Vec<u8>>,
+        }
+
+        impl Write for RecordingWriter {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.writes.push(buf.to_vec());
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let mut response = Response::new(200, "OK", b"Hello".to_vec());
+        for i in 0..20 {
+            response.add_header(&format!("X-Header-{}", i), "value");
+        }
+
+        let mut writer = RecordingWriter::default();
+        response.send(&mut writer).unwrap();
+        assert_eq!(writer.writes.len(), 1);
+        assert_eq!(writer.writes[0], response.into_bytes());
+
+        // A large body bypasses the buffer but the output is unchanged
+        let response = Response::new(200, "OK", vec![b'x'; 64 * 1024]);
+        let mut writer = RecordingWriter::default();
+        response.send(&mut writer).unwrap();
+        assert_eq!(writer.writes.concat(), response.into_bytes());
+    }
 }
@@ -12,38 +12,339 @@
 //! - `Server`: Represents the HTTP server and its configuration (address and document root).
 //! - `handle_connection`: Manages a single client connection, processing HTTP requests and sending responses.
 //! - `generate_response`: Generates an appropriate HTTP response based on the requested file or directory.
-//! - `get_content_type`: Determines the content type of files based on their extension.
 //!
 //! This module also includes error handling using `ServerError`, which covers
 //! I/O errors, invalid requests, file not found errors, and forbidden access.
 //!
+//! Server events (startup, accepted connections, handled requests and errors) are
+//! reported through the [`log`] facade, so any compatible logger such as `env_logger`
+//! can capture and filter them.
+//! Each handled request produces an access log line, either short text or a JSON
+//! object for log aggregators (see [`LogFormat`]).
+//!
 //! # Features
 //!
 //! - Handles HTTP GET requests and serves static files.
-//! - Supports serving an `index.html` for directories.
-//! - Returns a `404 Not Found` response for missing files.
+//! - Serves an index file, `index.html` unless configured otherwise, for the root and
+//!   other directories.
+//! - Redirects directory requests without a trailing slash to the slashed path.
+//! - Returns a `404 Not Found` response for missing files, or hands such requests to a
+//!   fallback handler.
+//! - Completes WebSocket handshakes at a configured path, handing the upgraded
+//!   connection to a handler.
+//! - Optionally injects a live reload script into HTML pages during development,
+//!   reloading them when files change with the `watch` feature.
+//! - Optionally keeps served files in an in-memory cache, reporting hits and misses
+//!   in the access log and an `X-Cache` header.
+//! - Optionally serves language variants such as `index.fr.html`, chosen with
+//!   `Accept-Language` and labelled with `Content-Language`.
+//! - Optionally answers a missing `/favicon.ico` with a default icon or `204 No Content`.
+//! - Supports single and multipart byte-range requests with `ETag`-based `If-Range` validation,
+//!   advertised with `Accept-Ranges: bytes` on file responses and `none` on the others.
+//! - Keeps HTTP/1.1 connections alive, up to a configurable number of requests each.
+//! - Answers `If-None-Match` with `304 Not Modified`, for `GET` and `HEAD` alike.
+//! - Sends `Last-Modified`, answering `If-Modified-Since` with `304 Not Modified` and a
+//!   failed `If-Match` or `If-Unmodified-Since` with `412 Precondition Failed`.
+//! - Optionally sends weak entity tags, which never satisfy `If-Range`.
+//! - With the `gzip` or `brotli` feature, compresses textual files with the encoding the
+//!   client prefers, marking such responses with `Vary: Accept-Encoding`.
 //! - Provides security against directory traversal attacks by restricting access
 //!   to the document root.
+//! - Blocks hidden files (e.g. `.env`, `.git/`) with `403 Forbidden`, except under
+//!   `.well-known/` for ACME challenges.
 //! - Serves appropriate content types based on file extensions (e.g., `.html`, `.css`, `.js`).
+//! - Optionally caps simultaneous connections, queueing or rejecting (`503`) extra ones.
+//! - With the `tls` feature, serves HTTPS with rustls, from PEM files or a prebuilt
+//!   configuration, advertising `http/1.1` through ALPN.
+//! - On Unix, listens on a Unix domain socket instead of a TCP port when created with
+//!   [`Server::new_unix`].
+//! - Shuts down gracefully through a [`ShutdownHandle`], finishing in-flight requests;
+//!   with the `signals` feature, `SIGTERM` and `SIGINT` can trigger the shutdown.
 //!
 
+use crate::cache::{CacheStatus, FileCache};
+#[cfg(any(feature = "gzip", feature = "brotli"))]
+use crate::compression;
+use crate::date::{format_http_date, format_rfc3339, parse_http_date};
 use crate::error::ServerError;
-use crate::request::Request;
-use crate::response::Response;
+use crate::files::{file_error, get_content_type};
+use crate::livereload::{self, ReloadHandle, LIVE_RELOAD_PATH};
+use crate::middleware::{Middleware, Next};
+use crate::negotiation::{negotiate_encoding, negotiate_language};
+use crate::request::{
+    Request, Version, MAX_BODY_LENGTH, TIMEOUT_SECONDS,
+};
+use crate::response::{
+    is_valid_header_name, is_valid_header_value, reason_phrase,
+    Response,
+};
+#[cfg(feature = "tls")]
+use crate::tls::{self, TlsStream};
+#[cfg(feature = "watch")]
+use crate::watch::{self, FileWatcher};
+use crate::websocket::{self, UpgradedStream};
+use log::{debug, error, info, warn};
 use serde::{Deserialize, Serialize};
-use std::fs;
-use std::io;
-use std::net::{TcpListener, TcpStream};
+use socket2::{Domain, Protocol, SockRef, Socket, Type};
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::fs::{self, File};
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::iter;
+use std::net::{
+    IpAddr, Ipv4Addr, Ipv6Addr, Shutdown, SocketAddr, TcpListener,
+    TcpStream, ToSocketAddrs,
+};
+#[cfg(target_os = "linux")]
+use std::os::unix::io::{AsRawFd, RawFd};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::panic::{self, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, PoisonError};
 use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-/// Represents the Http Handle and its configuration.
+/// Default number of requests served on one persistent connection.
+const DEFAULT_MAX_REQUESTS_PER_CONNECTION: usize = 100;
+
+/// Default capacity of the buffer requests are read through, in bytes.
+const DEFAULT_READ_BUFFER_SIZE: usize = 8 * 1024;
+
+/// Default length of the pending connection queue.
+const DEFAULT_LISTEN_BACKLOG: i32 = 128;
+
+/// The methods served by the static file handler.
+const ALLOWED_METHODS: &str = "GET, HEAD, OPTIONS";
+
+/// Headers added to every response once security headers are enabled.
+const SECURITY_HEADERS: [(&str, &str); 3] = [
+    ("X-Content-Type-Options", "nosniff"),
+    ("X-Frame-Options", "DENY"),
+    ("Referrer-Policy", "no-referrer"),
+];
+
+/// Default `Cache-Control` value for static file responses.
+const DEFAULT_CACHE_CONTROL: &str = "public, max-age=3600";
+
+/// Maximum number of ranges honoured in a single `Range` header.
+const MAX_RANGES: usize = 16;
+
+/// Hidden top-level directories that are served even when dotfiles are blocked.
+const DOTFILE_ALLOWLIST: &[&str] = &[".well-known"];
+
+/// The most connections over the limit answered with a 503 at the same time.
+const MAX_REJECTING_CONNECTIONS: usize = 8;
+
+/// How often idle persistent connections check whether the server is shutting down.
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Selects how the server answers requests using a method it does not handle.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+pub enum UnsupportedMethodPolicy {
+    /// Respond with `405 Method Not Allowed` and an `Allow` header listing the
+    /// supported methods.
+    #[default]
+    MethodNotAllowed,
+    /// Respond with `501 Not Implemented`.
+    NotImplemented,
+}
+
+/// Selects the format of the access log line written for each request.
 #[derive(
-    Clone, Debug, PartialEq, Eq, Hash, Default, Serialize, Deserialize,
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Default,
+    Serialize,
+    Deserialize,
 )]
+pub enum LogFormat {
+    /// A short human-readable line, e.g. `GET /index.html 200 in 1.2ms`.
+    #[default]
+    Text,
+    /// One JSON object per request, for log aggregators.
+    ///
+    /// The object has the keys `timestamp` (RFC 3339, UTC), `method`, `path`,
+    /// `status`, `bytes` (the body bytes sent), `duration_ms`, `remote_addr` and
+    /// `user_agent`; the last two are `null` when unknown.
+    Json,
+}
+
+/// Selects what happens to new connections once the connection limit is reached.
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    PartialEq,
+    Eq,
+    Hash,
+    Default,
+    Serialize,
+    Deserialize,
+)]
+pub enum ConnectionLimitPolicy {
+    /// Stop accepting until a connection finishes; new clients wait in the listen
+    /// backlog.
+    #[default]
+    Queue,
+    /// Accept the connection, respond with `503 Service Unavailable` and close it.
+    ///
+    /// At most a few connections are answered at once; during a flood, the others are
+    /// closed without a response.
+    Reject,
+}
+
+/// A callback, or other state, shared by every connection of a server.
+pub(crate) struct Handler<F: ?Sized>(pub(crate) Arc<F>);
+
+/// A handler building the response for a request.
+type RequestHandler =
+    Handler<dyn Fn(&Request) -> Response + Send + Sync>;
+
+/// A hook observing or modifying each request before it is handled.
+type RequestInterceptor = Handler<dyn Fn(&mut Request) + Send + Sync>;
+
+/// A hook observing or modifying each response before it is sent.
+type ResponseInterceptor =
+    Handler<dyn Fn(&Request, &mut Response) + Send + Sync>;
+
+/// A handler taking over connections upgraded to the WebSocket protocol.
+type WebSocketHandler =
+    Handler<dyn Fn(&Request, UpgradedStream) + Send + Sync>;
+
+impl<F: ?Sized> Clone for Handler<F> {
+    fn clone(&self) -> Self {
+        Handler(Arc::clone(&self.0))
+    }
+}
+
+impl<F: ?Sized> fmt::Debug for Handler<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Handler(..)")
+    }
+}
+
+// Handlers compare and hash by identity, as closures and caches cannot be inspected
+impl<F: ?Sized> PartialEq for Handler<F> {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl<F: ?Sized> Eq for Handler<F> {}
+
+impl<F: ?Sized> Hash for Handler<F> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        (Arc::as_ptr(&self.0) as *const () as usize).hash(state);
+    }
+}
+
+/// Represents the Http Handle and its configuration.
+///
+/// A server deserialized from a partial configuration, such as only its `address`
+/// and `document_root`, takes the defaults of [`Server::new`] for every other setting.
+/// Handlers, middleware, interceptors and the file cache are not serialized. A TLS
+/// configuration cannot be serialized either, and attempting to is an error rather
+/// than a server that would silently fall back to plain HTTP.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
 pub struct Server {
     address: String,
+    #[cfg(unix)]
+    unix_socket: Option<PathBuf>,
     document_root: PathBuf,
+    fallback_roots: Vec<PathBuf>,
+    require_host: bool,
+    virtual_hosts: BTreeMap<String, PathBuf>,
+    redirect_directories: bool,
+    index_files: Vec<String>,
+    unsupported_method_policy: UnsupportedMethodPolicy,
+    server_timing: bool,
+    tcp_nodelay: bool,
+    listen_backlog: i32,
+    reuse_port: bool,
+    sendfile: bool,
+    max_connections: Option<usize>,
+    connection_limit_policy: ConnectionLimitPolicy,
+    attachment_extensions: BTreeSet<String>,
+    default_favicon: Option<Option<Vec<u8>>>,
+    health_check: Option<String>,
+    accel_redirect: Option<String>,
+    languages: Vec<String>,
+    cache_status_header: bool,
+    block_dotfiles: bool,
+    cache_control: String,
+    cache_control_overrides: BTreeMap<String, String>,
+    pub(crate) max_requests_per_connection: usize,
+    pub(crate) max_body_size: u64,
+    max_path_length: Option<usize>,
+    pub(crate) read_timeout: Duration,
+    pub(crate) read_buffer_size: usize,
+    access_log_format: LogFormat,
+    server_header: Option<String>,
+    security_headers: bool,
+    content_security_policy: Option<String>,
+    weak_etags: bool,
+    debug_errors: bool,
+    #[cfg(feature = "gzip")]
+    gzip: bool,
+    #[cfg(feature = "brotli")]
+    brotli: bool,
+    #[cfg(any(feature = "gzip", feature = "brotli"))]
+    compression_min_size: usize,
+    #[cfg(any(feature = "gzip", feature = "brotli"))]
+    compression_level: Option<u32>,
+    #[cfg(any(feature = "gzip", feature = "brotli"))]
+    compressible_types: Option<Vec<String>>,
+    header_rules: Vec<(String, String, String)>,
+    #[serde(skip)]
+    fallback: Option<RequestHandler>,
+    #[serde(skip)]
+    request_interceptor: Option<RequestInterceptor>,
+    #[serde(skip)]
+    response_interceptor: Option<ResponseInterceptor>,
+    #[serde(skip)]
+    middleware: Vec<Handler<dyn Middleware>>,
+    #[serde(skip)]
+    file_cache: Option<Handler<FileCache>>,
+    #[serde(skip)]
+    websockets: Vec<(String, WebSocketHandler)>,
+    #[serde(skip)]
+    live_reload: Option<ReloadHandle>,
+    #[cfg(feature = "watch")]
+    file_watch: bool,
+    #[cfg(feature = "tls")]
+    #[serde(
+        skip_deserializing,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "tls::serialize_config"
+    )]
+    tls: Option<Handler<rustls::ServerConfig>>,
+}
+
+impl Default for Server {
+    /// Creates a server with the defaults of [`Server::new`], an empty address and
+    /// an empty document root.
+    fn default() -> Self {
+        Server::new("", "")
+    }
 }
 
 impl Server {
@@ -51,7 +352,8 @@ impl Server {
     ///
     /// # Arguments
     ///
-    /// * `address` - A string slice that holds the IP address and port (e.g., "127.0.0.1:8080").
+    /// * `address` - A string slice that holds the IP address and port (e.g., "127.0.0.1:8080"
+    ///   or "[::1]:8080" for IPv6).
     /// * `document_root` - A string slice that holds the path to the document root directory.
     ///
     /// # Returns
@@ -60,329 +362,8216 @@ impl Server {
     pub fn new(address: &str, document_root: &str) -> Self {
         Server {
             address: address.to_string(),
+            #[cfg(unix)]
+            unix_socket: None,
             document_root: PathBuf::from(document_root),
+            fallback_roots: Vec::new(),
+            require_host: false,
+            virtual_hosts: BTreeMap::new(),
+            redirect_directories: true,
+            index_files: vec!["index.html".to_string()],
+            unsupported_method_policy: Default::default(),
+            server_timing: false,
+            tcp_nodelay: true,
+            listen_backlog: DEFAULT_LISTEN_BACKLOG,
+            reuse_port: false,
+            sendfile: true,
+            max_connections: None,
+            connection_limit_policy: Default::default(),
+            attachment_extensions: BTreeSet::new(),
+            default_favicon: None,
+            health_check: None,
+            accel_redirect: None,
+            languages: Vec::new(),
+            cache_status_header: false,
+            block_dotfiles: true,
+            cache_control: DEFAULT_CACHE_CONTROL.to_string(),
+            cache_control_overrides: BTreeMap::new(),
+            max_requests_per_connection:
+                DEFAULT_MAX_REQUESTS_PER_CONNECTION,
+            max_body_size: MAX_BODY_LENGTH,
+            max_path_length: None,
+            read_timeout: Duration::from_secs(TIMEOUT_SECONDS),
+            read_buffer_size: DEFAULT_READ_BUFFER_SIZE,
+            access_log_format: LogFormat::Text,
+            server_header: Some(default_server_header()),
+            security_headers: false,
+            content_security_policy: None,
+            weak_etags: false,
+            debug_errors: false,
+            #[cfg(feature = "gzip")]
+            gzip: true,
+            #[cfg(feature = "brotli")]
+            brotli: true,
+            #[cfg(any(feature = "gzip", feature = "brotli"))]
+            compression_min_size: 0,
+            #[cfg(any(feature = "gzip", feature = "brotli"))]
+            compression_level: None,
+            #[cfg(any(feature = "gzip", feature = "brotli"))]
+            compressible_types: None,
+            header_rules: Vec::new(),
+            fallback: None,
+            request_interceptor: None,
+            response_interceptor: None,
+            middleware: Vec::new(),
+            file_cache: None,
+            websockets: Vec::new(),
+            live_reload: None,
+            #[cfg(feature = "watch")]
+            file_watch: false,
+            #[cfg(feature = "tls")]
+            tls: None,
         }
     }
 
-    /// Starts the server and begins listening for incoming connections.
+    /// Creates a new `Server` listening on a Unix domain socket.
+    ///
+    /// The server is configured like one created with [`Server::new`], but
+    /// [`Server::start`] binds a `UnixListener` at `socket_path` instead of a TCP
+    /// port. This suits local tooling and running behind a reverse proxy on the same
+    /// host. Binding fails if a file already exists at `socket_path`; the socket file
+    /// is removed when the server shuts down. TCP socket options such as `TCP_NODELAY`
+    /// do not apply.
+    ///
+    /// Only available on Unix platforms.
+    ///
+    /// # Arguments
+    ///
+    /// * `socket_path` - The path of the socket file to create.
+    /// * `document_root` - A string slice that holds the path to the document root directory.
     ///
     /// # Returns
     ///
-    /// A `Result` indicating success or an I/O error.
-    pub fn start(&self) -> io::Result<()> {
-        let listener = TcpListener::bind(&self.address)?;
-        println!("❯ Server is now running at http://{}", self.address);
-        println!("  Document root: {}", self.document_root.display());
-        println!("  Press Ctrl+C to stop the server.");
-
-        for stream in listener.incoming() {
-            match stream {
-                Ok(stream) => {
-                    let document_root = self.document_root.clone();
-                    let _ = thread::spawn(move || {
-                        if let Err(e) =
-                            handle_connection(stream, &document_root)
-                        {
-                            eprintln!(
-                                "Error handling connection: {}",
-                                e
-                            );
-                        }
-                    });
-                }
-                Err(e) => eprintln!("Connection error: {}", e),
-            }
+    /// A new `Server` instance.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use http_handle::Server;
+    ///
+    /// let server = Server::new_unix("/run/http-handle.sock", "./public");
+    /// server.start().unwrap();
+    /// ```
+    #[cfg(unix)]
+    pub fn new_unix(socket_path: &str, document_root: &str) -> Self {
+        Server {
+            unix_socket: Some(PathBuf::from(socket_path)),
+            ..Server::new(socket_path, document_root)
         }
-
-        Ok(())
     }
-}
-
-/// Handles a single client connection.
-///
-/// # Arguments
-///
-/// * `stream` - A `TcpStream` representing the client connection.
-/// * `document_root` - A `PathBuf` representing the server's document root.
-///
-/// # Returns
-///
-/// A `Result` indicating success or a `ServerError`.
-fn handle_connection(
-    mut stream: TcpStream,
-    document_root: &Path,
-) -> Result<(), ServerError> {
-    let request = Request::from_stream(&stream)?;
-    let response = generate_response(&request, document_root)?;
-    response.send(&mut stream)?;
-    Ok(())
-}
-
-/// Generates an HTTP response based on the requested file.
-///
-/// # Arguments
-///
-/// * `request` - A `Request` instance representing the client's request.
-/// * `document_root` - A `Path` representing the server's document root.
-///
-/// # Returns
-///
-/// A `Result` containing the `Response` or a `ServerError`.
-fn generate_response(
-    request: &Request,
-    document_root: &Path,
-) -> Result<Response, ServerError> {
-    let mut path = PathBuf::from(document_root);
-    let request_path = request.path().trim_start_matches('/');
 
-    if request_path.is_empty() {
-        // If the request is for the root, append "index.html"
-        path.push("index.html");
-    } else {
-        for component in request_path.split('/') {
-            if component == ".." {
-                let _ = path.pop();
-            } else {
-                path.push(component);
-            }
-        }
+    /// Creates a new `Server` serving HTTPS with a certificate and key from PEM files.
+    ///
+    /// The server is configured like one created with [`Server::new`], and every
+    /// connection accepted by [`Server::start`] or [`Server::serve`] is served over TLS
+    /// with rustls, without client authentication. Use [`Server::with_tls_config`] for
+    /// other TLS settings. TLS does not apply to the asynchronous server or to Unix
+    /// domain sockets.
+    ///
+    /// Only available with the `tls` feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - A string slice that holds the IP address and port (e.g., "127.0.0.1:8443").
+    /// * `document_root` - A string slice that holds the path to the document root directory.
+    /// * `cert_path` - The PEM file holding the certificate chain, leaf certificate first.
+    /// * `key_path` - The PEM file holding the private key of the leaf certificate.
+    ///
+    /// # Returns
+    ///
+    /// A new `Server` instance advertising `http/1.1` through ALPN, or a `ServerError`
+    /// if a file cannot be read or the certificate and key are rejected.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use http_handle::Server;
+    ///
+    /// let server = Server::new_tls(
+    ///     "127.0.0.1:8443",
+    ///     "./public",
+    ///     "cert.pem",
+    ///     "key.pem",
+    /// )
+    /// .unwrap();
+    /// server.start().unwrap();
+    /// ```
+    #[cfg(feature = "tls")]
+    pub fn new_tls(
+        address: &str,
+        document_root: &str,
+        cert_path: &str,
+        key_path: &str,
+    ) -> Result<Self, ServerError> {
+        let config = tls::load_config(
+            Path::new(cert_path),
+            Path::new(key_path),
+        )?;
+        Ok(Server::with_tls_config(
+            address,
+            document_root,
+            Arc::new(config),
+        ))
     }
 
-    if !path.starts_with(document_root) {
-        return Err(ServerError::forbidden("Access denied"));
+    /// Creates a new `Server` serving HTTPS with a prebuilt rustls configuration.
+    ///
+    /// This is [`Server::new_tls`] for configurations the PEM files cannot express,
+    /// such as certificate resolvers selecting by SNI, client authentication or
+    /// custom protocol versions. The configuration is shared by every connection.
+    ///
+    /// A configuration without ALPN protocols is given `http/1.1`, so clients that
+    /// negotiate the protocol know what the server speaks; one listing protocols is
+    /// used as is.
+    ///
+    /// Only available with the `tls` feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - A string slice that holds the IP address and port (e.g., "127.0.0.1:8443").
+    /// * `document_root` - A string slice that holds the path to the document root directory.
+    /// * `config` - The rustls server configuration.
+    ///
+    /// # Returns
+    ///
+    /// A new `Server` instance.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use http_handle::Server;
+    /// use std::sync::Arc;
+    ///
+    /// # fn config() -> rustls::ServerConfig { unimplemented!() }
+    /// let config: rustls::ServerConfig = config();
+    /// let server =
+    ///     Server::with_tls_config("127.0.0.1:8443", "./public", Arc::new(config));
+    /// server.start().unwrap();
+    /// ```
+    #[cfg(feature = "tls")]
+    pub fn with_tls_config(
+        address: &str,
+        document_root: &str,
+        config: Arc<rustls::ServerConfig>,
+    ) -> Self {
+        let mut config = config;
+        if config.alpn_protocols.is_empty() {
+            Arc::make_mut(&mut config).alpn_protocols =
+                vec![tls::HTTP_1_1.to_vec()];
+        }
+        Server {
+            tls: Some(Handler(config)),
+            ..Server::new(address, document_root)
+        }
     }
 
-    if path.is_file() {
-        let contents = fs::read(&path)?;
-        let content_type = get_content_type(&path);
-        let mut response = Response::new(200, "OK", contents);
-        response.add_header("Content-Type", content_type);
-        Ok(response)
-    } else if path.is_dir() {
-        // If it's a directory, try to serve index.html from that directory
-        path.push("index.html");
-        if path.is_file() {
-            let contents = fs::read(&path)?;
-            let content_type = get_content_type(&path);
-            let mut response = Response::new(200, "OK", contents);
-            response.add_header("Content-Type", content_type);
-            Ok(response)
-        } else {
-            generate_404_response(document_root)
+    /// Advertises an additional protocol through ALPN during TLS handshakes.
+    ///
+    /// The server picks the first protocol in its list that the client also offers,
+    /// and an added protocol goes first. Adding a protocol already listed moves it
+    /// first. This does nothing for a server without TLS.
+    ///
+    /// **Warning:** connections are always served as HTTP/1.1, whatever protocol is
+    /// negotiated. Never add `h2` or `h3`: clients that support them would select
+    /// them and then fail to talk to the server. Only add protocols that a custom
+    /// client expects and that are spoken over HTTP/1.1.
+    ///
+    /// Only available with the `tls` feature.
+    ///
+    /// # Arguments
+    ///
+    /// * `protocol` - The ALPN protocol identifier, e.g. `x-custom`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use http_handle::Server;
+    ///
+    /// let mut server =
+    ///     Server::new_tls("127.0.0.1:8443", "./public", "cert.pem", "key.pem")
+    ///         .unwrap();
+    /// server.add_alpn_protocol("x-custom");
+    /// ```
+    #[cfg(feature = "tls")]
+    pub fn add_alpn_protocol(&mut self, protocol: &str) {
+        if let Some(config) = &mut self.tls {
+            let protocols =
+                &mut Arc::make_mut(&mut config.0).alpn_protocols;
+            protocols.retain(|listed| listed != protocol.as_bytes());
+            protocols.insert(0, protocol.as_bytes().to_vec());
         }
-    } else {
-        generate_404_response(document_root)
     }
-}
-
-/// Generates a 404 Not Found response.
-///
-/// # Arguments
-///
-/// * `document_root` - A `Path` representing the server's document root.
-///
-/// # Returns
-///
-/// A `Result` containing the `Response` or a `ServerError`.
-fn generate_404_response(
-    document_root: &Path,
-) -> Result<Response, ServerError> {
-    let not_found_path = document_root.join("404/index.html");
-    let contents = if not_found_path.is_file() {
-        fs::read(not_found_path)?
-    } else {
-        b"404 Not Found".to_vec()
-    };
-    let mut response = Response::new(404, "NOT FOUND", contents);
-    response.add_header("Content-Type", "text/html");
-    Ok(response)
-}
 
-/// Determines the content type based on the file extension.
-///
-/// # Arguments
-///
-/// * `path` - A `Path` representing the file path.
-///
-/// # Returns
-///
-/// A string slice representing the content type.
-fn get_content_type(path: &Path) -> &'static str {
-    match path.extension().and_then(std::ffi::OsStr::to_str) {
-        Some("html") => "text/html",
-        Some("css") => "text/css",
-        Some("js") => "application/javascript",
-        Some("json") => "application/json",
-        Some("png") => "image/png",
-        Some("jpg") | Some("jpeg") => "image/jpeg",
-        Some("gif") => "image/gif",
-        Some("svg") => "image/svg+xml",
-        _ => "application/octet-stream",
+    /// Sets whether whole-file responses are transmitted with zero-copy `sendfile(2)`.
+    ///
+    /// When enabled (the default), a `GET` for a whole static file is sent straight from
+    /// the file to the socket instead of being read into memory first, which reduces
+    /// memory use and copying for large files. This uses `sendfile(2)` on Linux and a
+    /// streamed buffered copy on other platforms. Range requests are always served from
+    /// memory. See `benches/sendfile_benchmark.rs` to measure the difference.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - `true` to stream whole files directly to the socket.
+    pub fn set_sendfile(&mut self, enabled: bool) {
+        self.sendfile = enabled;
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::fs::File;
-    use std::io::Write;
-    use tempfile::TempDir;
+    /// Sets whether `SO_REUSEPORT` is enabled on the listening socket.
+    ///
+    /// `SO_REUSEADDR` is always set so that a restarted server can rebind while old
+    /// connections linger in `TIME_WAIT`. `SO_REUSEPORT` goes further and lets several
+    /// sockets listen on the same port at once, with the kernel distributing connections
+    /// between them. It is disabled by default.
+    ///
+    /// # Security
+    ///
+    /// With `SO_REUSEPORT`, any other process running as the same user can bind the same
+    /// port and receive a share of the incoming connections. Only enable it on hosts
+    /// where that is acceptable. The option is only available on Unix platforms that
+    /// support it and is ignored, with a warning, elsewhere.
+    ///
+    /// # Arguments
+    ///
+    /// * `reuse_port` - `true` to set `SO_REUSEPORT` when binding.
+    pub fn set_reuse_port(&mut self, reuse_port: bool) {
+        self.reuse_port = reuse_port;
+    }
 
-    fn setup_test_directory() -> TempDir {
-        let temp_dir = TempDir::new().unwrap();
-        let root_path = temp_dir.path();
+    /// Sets the maximum number of connections served at the same time.
+    ///
+    /// Connections are unlimited by default. Once the limit is reached, new connections
+    /// are handled according to the [`ConnectionLimitPolicy`] set with
+    /// [`Server::set_connection_limit_policy`].
+    ///
+    /// # Arguments
+    ///
+    /// * `max_connections` - The maximum number of simultaneous connections.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_connections` is zero.
+    pub fn set_max_connections(&mut self, max_connections: usize) {
+        assert!(
+            max_connections > 0,
+            "max_connections must be at least 1"
+        );
+        self.max_connections = Some(max_connections);
+    }
 
-        // Create index.html
-        let mut index_file =
-            File::create(root_path.join("index.html")).unwrap();
-        index_file
-            .write_all(b"<html><body>Hello, World!</body></html>")
-            .unwrap();
+    /// Sets how many requests are served on one persistent connection.
+    ///
+    /// Once a connection has served this many requests, the last response carries
+    /// `Connection: close` and the connection is closed, which bounds how long a single
+    /// client can hold on to it. The default is 100.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_requests` - The maximum number of requests per connection.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_requests` is zero.
+    pub fn set_max_requests_per_connection(
+        &mut self,
+        max_requests: usize,
+    ) {
+        assert!(max_requests > 0, "max_requests must be at least 1");
+        self.max_requests_per_connection = max_requests;
+    }
 
-        // Create 404/index.html
-        fs::create_dir(root_path.join("404")).unwrap();
-        let mut not_found_file =
-            File::create(root_path.join("404/index.html")).unwrap();
-        not_found_file
-            .write_all(b"<html><body>404 Not Found</body></html>")
-            .unwrap();
+    /// Sets the largest request body the server reads, in bytes.
+    ///
+    /// A request announcing a larger `Content-Length` is refused before its body is
+    /// read, and the connection is closed. Clients that sent `Expect: 100-continue`
+    /// and are still waiting to send the body get `417 Expectation Failed`; others get
+    /// `413 Content Too Large`. The default is 1 MiB.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_body_size` - The maximum body size in bytes.
+    pub fn set_max_body_size(&mut self, max_body_size: u64) {
+        self.max_body_size = max_body_size;
+    }
 
-        // Create a subdirectory with its own index.html
-        fs::create_dir(root_path.join("subdir")).unwrap();
-        let mut subdir_index_file =
-            File::create(root_path.join("subdir/index.html")).unwrap();
-        subdir_index_file
-            .write_all(b"<html><body>Subdirectory Index</body></html>")
-            .unwrap();
+    /// Sets the longest request path the server accepts, in bytes.
+    ///
+    /// The path is measured as the file system would see it: without the query string
+    /// and with `%XX` escapes decoded. Requests whose path is longer are answered with
+    /// `414 URI Too Long`, whatever their method, even when the request line itself is
+    /// within its 8 KiB limit. This keeps pathological paths away from the file
+    /// system, which has its own limits on path length. By default only the request
+    /// line limit applies.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_path_length` - The maximum path length in bytes.
+    pub fn set_max_path_length(&mut self, max_path_length: usize) {
+        self.max_path_length = Some(max_path_length);
+    }
 
-        temp_dir
+    /// Sets how long the server waits for a client to send data.
+    ///
+    /// A connection on which no request has started within this time, whether newly
+    /// accepted or idle between keep-alive requests, is closed quietly. A client that
+    /// stalls this long in the middle of a request is answered with
+    /// `408 Request Timeout` and the connection is closed. The default is 30 seconds.
+    ///
+    /// # Arguments
+    ///
+    /// * `timeout` - The read timeout.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `timeout` is zero.
+    pub fn set_read_timeout(&mut self, timeout: Duration) {
+        assert!(!timeout.is_zero(), "timeout must be non-zero");
+        self.read_timeout = timeout;
     }
 
-    #[test]
-    fn test_server_creation() {
-        let server = Server::new("127.0.0.1:8080", "/var/www");
-        assert_eq!(server.address, "127.0.0.1:8080");
-        assert_eq!(server.document_root, PathBuf::from("/var/www"));
+    /// Sets the capacity of the buffer each connection reads requests through.
+    ///
+    /// Every open connection holds a buffer of this size for its whole lifetime, so a
+    /// larger one costs memory per connection, while a smaller one takes more `read`
+    /// system calls to receive a large header block or body. Requests are parsed the
+    /// same whatever the size, across as many buffer fills as they need. The default
+    /// is 8 KiB.
+    ///
+    /// # Arguments
+    ///
+    /// * `size` - The buffer capacity in bytes.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `size` is zero.
+    pub fn set_read_buffer_size(&mut self, size: usize) {
+        assert!(size > 0, "size must be at least 1");
+        self.read_buffer_size = size;
+    }
+
+    /// Sets the format of the access log line written for each request.
+    ///
+    /// Access log lines are emitted through the `log` facade at the `info` level, like
+    /// the other server events. With [`LogFormat::Json`], the message is a single JSON
+    /// object, ready to be forwarded to a log aggregator.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - The format of the access log lines.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::server::LogFormat;
+    /// use http_handle::Server;
+    ///
+    /// let mut server = Server::new("127.0.0.1:8080", "./public");
+    /// server.set_access_log_format(LogFormat::Json);
+    /// ```
+    pub fn set_access_log_format(&mut self, format: LogFormat) {
+        self.access_log_format = format;
+    }
+
+    /// Writes the access log line for a served request.
+    ///
+    /// # Arguments
+    ///
+    /// * `entry` - The details of the request and its response.
+    pub(crate) fn log_access(&self, entry: &AccessLogEntry<'_>) {
+        info!("{}", entry.format(self.access_log_format));
+    }
+
+    /// Sets what happens to new connections once the connection limit is reached.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - Whether to queue or reject connections over the limit.
+    pub fn set_connection_limit_policy(
+        &mut self,
+        policy: ConnectionLimitPolicy,
+    ) {
+        self.connection_limit_policy = policy;
+    }
+
+    /// Sets whether `TCP_NODELAY` is enabled on accepted connections.
+    ///
+    /// Disabling Nagle's algorithm avoids delaying small responses, such as HTML pages
+    /// and headers, and is enabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `nodelay` - `true` to set `TCP_NODELAY` on each accepted stream.
+    pub fn set_tcp_nodelay(&mut self, nodelay: bool) {
+        self.tcp_nodelay = nodelay;
+    }
+
+    /// Sets the listen backlog used when binding the server socket.
+    ///
+    /// The backlog bounds the number of connections the kernel queues before they are
+    /// accepted; a larger value helps absorb bursts of connections. The default is 128.
+    ///
+    /// The value is only a hint: Linux silently caps it at `net.core.somaxconn`, macOS
+    /// and the BSDs at `kern.ipc.somaxconn`, and Windows may choose its own limit.
+    ///
+    /// # Arguments
+    ///
+    /// * `backlog` - The requested length of the pending connection queue.
+    pub fn set_listen_backlog(&mut self, backlog: i32) {
+        self.listen_backlog = backlog;
+    }
+
+    /// Sets how requests using a method other than `GET`, `HEAD` or `OPTIONS` are answered.
+    ///
+    /// By default such requests receive `405 Method Not Allowed` with an `Allow`
+    /// header. Strict deployments may prefer `501 Not Implemented` instead.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - The `UnsupportedMethodPolicy` to apply.
+    pub fn set_unsupported_method_policy(
+        &mut self,
+        policy: UnsupportedMethodPolicy,
+    ) {
+        self.unsupported_method_policy = policy;
+    }
+
+    /// Sets whether responses carry a `Server-Timing` header.
+    ///
+    /// When enabled, each response reports the time spent handling the request, from
+    /// the start of the connection up to the response being generated, as
+    /// `Server-Timing: total;dur=<milliseconds>`. This is intended for debugging slow
+    /// requests and is disabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - `true` to add the `Server-Timing` header.
+    pub fn set_server_timing(&mut self, enabled: bool) {
+        self.server_timing = enabled;
+    }
+
+    /// Sets whether hidden files and directories are blocked.
+    ///
+    /// When enabled (the default), requests for any path with a component starting with
+    /// a dot, such as `/.git/config` or `/.env`, are answered with `403 Forbidden`.
+    /// Paths under `/.well-known/` remain accessible so that ACME HTTP-01 challenges
+    /// (`/.well-known/acme-challenge/<token>`) can be validated.
+    ///
+    /// # Arguments
+    ///
+    /// * `block` - `true` to refuse hidden files, `false` to serve them like any other.
+    pub fn set_block_dotfiles(&mut self, block: bool) {
+        self.block_dotfiles = block;
+    }
+
+    /// Serves files with the given extension as downloads.
+    ///
+    /// Responses for matching files carry `Content-Disposition: attachment` with the
+    /// file name, so browsers save them instead of rendering them. Extensions are
+    /// matched case-insensitively, without the leading dot.
+    ///
+    /// # Arguments
+    ///
+    /// * `extension` - The file extension to force as a download (e.g., "csv").
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::Server;
+    ///
+    /// let mut server = Server::new("127.0.0.1:8080", "./public");
+    /// server.add_attachment_extension("csv");
+    /// server.add_attachment_extension("zip");
+    /// ```
+    pub fn add_attachment_extension(&mut self, extension: &str) {
+        let _ = self.attachment_extensions.insert(
+            extension.trim_start_matches('.').to_ascii_lowercase(),
+        );
+    }
+
+    /// Sets what answers `/favicon.ico` when no document root contains it.
+    ///
+    /// Browsers request `/favicon.ico` on their own, which fills the logs with
+    /// `404 Not Found` for sites without one. With `Some(icon)`, the bytes are served as
+    /// `image/x-icon`; with `None`, the request is answered with `204 No Content`. A
+    /// real `favicon.ico` file is always served when it exists. Without this setting,
+    /// a missing icon is a `404 Not Found` like any other file.
+    ///
+    /// # Arguments
+    ///
+    /// * `icon` - The icon to serve, or `None` to suppress the request.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::Server;
+    ///
+    /// let mut server = Server::new("127.0.0.1:8080", "./public");
+    /// server.set_default_favicon(None);
+    /// ```
+    pub fn set_default_favicon(&mut self, icon: Option<Vec<u8>>) {
+        self.default_favicon = Some(icon);
+    }
+
+    /// Enables a built-in health check endpoint, for liveness probes.
+    ///
+    /// `GET` and `HEAD` requests for exactly `path`, with any query string, are answered
+    /// with `200 OK` and the body `ok`, before middleware and static file resolution, so
+    /// probes keep working whatever authentication or other middleware is in place. The
+    /// health check is disabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The request path of the endpoint, e.g. `/healthz`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::Server;
+    ///
+    /// let mut server = Server::new("127.0.0.1:8080", "./public");
+    /// server.enable_health_check("/healthz");
+    /// ```
+    pub fn enable_health_check(&mut self, path: &str) {
+        self.health_check = Some(path.to_string());
+    }
+
+    /// Answers a request for the health check endpoint, if it is one.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The request to check.
+    ///
+    /// # Returns
+    ///
+    /// The health check response, or `None` if the request is for something else.
+    pub(crate) fn health_check_response(
+        &self,
+        request: &Request,
+    ) -> Option<Response> {
+        let path = self.health_check.as_deref()?;
+        let is_head = request.method().eq_ignore_ascii_case("HEAD");
+        let request_path =
+            request.path().split('?').next().unwrap_or_default();
+        if request_path != path
+            || !(is_head
+                || request.method().eq_ignore_ascii_case("GET"))
+        {
+            return None;
+        }
+        let mut response = Response::text(200, "ok");
+        response.add_header("Cache-Control", "no-store");
+        if is_head {
+            response.add_header("Content-Length", "2");
+            response.body.clear();
+        }
+        Some(response)
+    }
+
+    /// Sets whether static files are handed to the reverse proxy to send.
+    ///
+    /// With `Some(prefix)`, a request resolving to a static file is answered with
+    /// [`Response::accel_redirect`] to `prefix` followed by the file's path relative to
+    /// its document root, and nginx serves the bytes from an `internal` location
+    /// mapped to that root. The proxy then handles validators, ranges and
+    /// compression; the server's own file headers are not applied. `None`, the
+    /// default, serves files directly.
+    ///
+    /// # Arguments
+    ///
+    /// * `prefix` - The URI prefix of the proxy's internal location, or `None`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::Server;
+    ///
+    /// // Paired with nginx's `location /internal/ { internal; alias /srv/public/; }`
+    /// let mut server = Server::new("127.0.0.1:8080", "/srv/public");
+    /// server.set_accel_redirect(Some("/internal"));
+    /// ```
+    pub fn set_accel_redirect(&mut self, prefix: Option<&str>) {
+        self.accel_redirect = prefix
+            .map(|prefix| prefix.trim_end_matches('/').to_string());
+    }
+
+    /// Enables serving language variants of static files.
+    ///
+    /// Variants are named by inserting one of `languages` before the extension, such
+    /// as `index.en.html` and `index.fr.html` for `index.html`. Only the listed tags
+    /// count, so names such as `app.min.js` are left alone. A request for the untagged
+    /// name is answered with the variant [`negotiate_language`] picks from its
+    /// `Accept-Language` header. When none matches, the untagged file is
+    /// served if it exists, and otherwise the variant whose tag sorts first. A file
+    /// with a language tag in its name is sent with a matching `Content-Language`
+    /// header, and responses chosen among variants carry `Vary: Accept-Language`.
+    /// An empty list disables negotiation, which is the default.
+    ///
+    /// # Arguments
+    ///
+    /// * `languages` - The language tags the site's file names use, such as `pt-BR`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::Server;
+    ///
+    /// let mut server = Server::new("127.0.0.1:8080", "./public");
+    /// server.enable_language_negotiation(&["en", "fr", "pt-BR"]);
+    /// ```
+    pub fn enable_language_negotiation(&mut self, languages: &[&str]) {
+        self.languages =
+            languages.iter().map(|tag| tag.to_string()).collect();
+        self.languages.sort();
+        self.languages.dedup();
+    }
+
+    /// Enables an in-memory cache of static file contents.
+    ///
+    /// Files are kept in memory after being served, up to `max_bytes` in total, and
+    /// served from there while their size and modification time are unchanged on
+    /// disk. When a file does not fit, the files cached longest are evicted first;
+    /// files larger than `max_bytes` are never cached. Cached files are always read
+    /// into memory rather than sent with `sendfile`. Each access log line records
+    /// whether the file was a cache hit or miss.
+    ///
+    /// # Arguments
+    ///
+    /// * `max_bytes` - The largest total size of the cached files, in bytes.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::Server;
+    ///
+    /// let mut server = Server::new("127.0.0.1:8080", "./public");
+    /// server.enable_file_cache(64 * 1024 * 1024);
+    /// ```
+    pub fn enable_file_cache(&mut self, max_bytes: usize) {
+        self.file_cache =
+            Some(Handler(Arc::new(FileCache::new(max_bytes))));
+    }
+
+    /// Sets whether file responses report the cache outcome in an `X-Cache` header.
+    ///
+    /// With the file cache enabled, responses serving a file then carry
+    /// `X-Cache: HIT` or `X-Cache: MISS`, which helps check the cache during
+    /// development. Disabled by default.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - Whether the `X-Cache` header is sent.
+    pub fn set_cache_status_header(&mut self, enabled: bool) {
+        self.cache_status_header = enabled;
+    }
+
+    /// Reads the contents of a static file, through the file cache if enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file to read.
+    /// * `metadata` - The current metadata of the file.
+    ///
+    /// # Returns
+    ///
+    /// The contents and, with the cache enabled, whether they came from it.
+    fn read_file(
+        &self,
+        path: &Path,
+        metadata: &fs::Metadata,
+    ) -> io::Result<(Vec<u8>, Option<CacheStatus>)> {
+        match &self.file_cache {
+            Some(cache) => cache
+                .0
+                .read(path, metadata)
+                .map(|(contents, status)| (contents, Some(status))),
+            None => fs::read(path).map(|contents| (contents, None)),
+        }
+    }
+
+    /// Takes the cache outcome recorded on a file response.
+    ///
+    /// The `X-Cache` header set while serving the file is removed unless it is
+    /// configured to be sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `response` - The response being sent.
+    ///
+    /// # Returns
+    ///
+    /// `HIT` or `MISS` for a file served with the cache enabled, `None` otherwise.
+    pub(crate) fn take_cache_status(
+        &self,
+        response: &mut Response,
+    ) -> Option<&'static str> {
+        let status = self.file_cache.as_ref().and_then(|_| {
+            let (_, value) =
+                response.headers.iter().find(|(name, _)| {
+                    name.eq_ignore_ascii_case("X-Cache")
+                })?;
+            [CacheStatus::Hit, CacheStatus::Miss]
+                .iter()
+                .map(|status| status.as_str())
+                .find(|status| value == status)
+        });
+        if status.is_some() && !self.cache_status_header {
+            response.remove_header("X-Cache");
+        }
+        status
+    }
+
+    /// Sets the `Cache-Control` header sent with static file responses.
+    ///
+    /// The default is `public, max-age=3600`. Pass an empty string to send no
+    /// `Cache-Control` header. Per-extension values set with
+    /// [`Server::set_cache_control_for_extension`] take precedence.
+    ///
+    /// # Arguments
+    ///
+    /// * `header_value` - The `Cache-Control` value (e.g., "public, max-age=600").
+    pub fn set_cache_control(&mut self, header_value: &str) {
+        self.cache_control = header_value.to_string();
+    }
+
+    /// Overrides the `Cache-Control` header for files with the given extension.
+    ///
+    /// Extensions are matched case-insensitively, without the leading dot. An empty
+    /// value sends no `Cache-Control` header for those files.
+    ///
+    /// # Arguments
+    ///
+    /// * `extension` - The file extension (e.g., "html").
+    /// * `header_value` - The `Cache-Control` value for matching files.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::Server;
+    ///
+    /// let mut server = Server::new("127.0.0.1:8080", "./public");
+    /// server.set_cache_control("public, max-age=31536000, immutable");
+    /// server.set_cache_control_for_extension("html", "no-cache");
+    /// ```
+    pub fn set_cache_control_for_extension(
+        &mut self,
+        extension: &str,
+        header_value: &str,
+    ) {
+        let _ = self.cache_control_overrides.insert(
+            extension.trim_start_matches('.').to_ascii_lowercase(),
+            header_value.to_string(),
+        );
+    }
+
+    /// Adds a header to static file responses whose path starts with a prefix.
+    ///
+    /// Rules are applied in the order they were added, after the content type and the
+    /// other file headers have been set. Every matching rule applies; a rule replaces
+    /// any header of the same name set before it, so a later, more specific rule can
+    /// override both the server defaults and an earlier rule. The prefix is compared
+    /// literally against the request path, without the query string.
+    ///
+    /// # Arguments
+    ///
+    /// * `path_prefix` - The request path prefix to match (e.g., "/assets/").
+    /// * `name` - The header name.
+    /// * `value` - The header value.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `name` is not a valid header name or `value` contains control
+    /// characters.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::Server;
+    ///
+    /// let mut server = Server::new("127.0.0.1:8080", "./public");
+    /// server.add_header_rule(
+    ///     "/assets/",
+    ///     "Cache-Control",
+    ///     "public, max-age=31536000, immutable",
+    /// );
+    /// server.add_header_rule("/api/", "Cache-Control", "no-store");
+    /// ```
+    pub fn add_header_rule(
+        &mut self,
+        path_prefix: &str,
+        name: &str,
+        value: &str,
+    ) {
+        assert!(
+            is_valid_header_name(name) && is_valid_header_value(value),
+            "invalid header rule: {:?}: {:?}",
+            name,
+            value
+        );
+        self.header_rules.push((
+            path_prefix.to_string(),
+            name.to_string(),
+            value.to_string(),
+        ));
+    }
+
+    /// Sets the `Server` header sent with every response.
+    ///
+    /// By default responses carry `Server: http-handle/<version>`. Pass `None` to omit
+    /// the header, for example to avoid disclosing the server software.
+    ///
+    /// # Arguments
+    ///
+    /// * `server_header` - The header value, or `None` to send no `Server` header.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::Server;
+    ///
+    /// let mut server = Server::new("127.0.0.1:8080", "./public");
+    /// server.set_server_header(Some("example/1.0".to_string()));
+    /// server.set_server_header(None);
+    /// ```
+    pub fn set_server_header(&mut self, server_header: Option<String>) {
+        self.server_header = server_header;
+    }
+
+    /// Enables a preset of common security headers on every response.
+    ///
+    /// Responses then carry `X-Content-Type-Options: nosniff`, `X-Frame-Options: DENY`
+    /// and `Referrer-Policy: no-referrer`. A header already set on a response is kept,
+    /// so a handler can relax any of them individually.
+    pub fn enable_security_headers(&mut self) {
+        self.security_headers = true;
+    }
+
+    /// Sets the `Content-Security-Policy` header sent with every response.
+    ///
+    /// As with the other security headers, a policy already set on a response takes
+    /// precedence.
+    ///
+    /// # Arguments
+    ///
+    /// * `policy` - The policy (e.g., "default-src 'self'").
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::Server;
+    ///
+    /// let mut server = Server::new("127.0.0.1:8080", "./public");
+    /// server.enable_security_headers();
+    /// server.set_csp("default-src 'self'");
+    /// ```
+    pub fn set_csp(&mut self, policy: &str) {
+        self.content_security_policy = Some(policy.to_string());
+    }
+
+    /// Sets whether file responses carry weak entity tags.
+    ///
+    /// Weak tags (`W/"..."`) suit content that is semantically equivalent but not
+    /// byte-identical across revisions. Following RFC 7232, a weak tag still matches
+    /// `If-None-Match` for a `304 Not Modified`, but never satisfies `If-Range`, so a
+    /// range request conditioned on it is answered with the full `200` representation.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - `true` to send weak entity tags, `false` (the default) for strong ones.
+    pub fn set_weak_etags(&mut self, enabled: bool) {
+        self.weak_etags = enabled;
+    }
+
+    /// Sets whether error responses explain the error as JSON.
+    ///
+    /// By default, a request the server fails to handle is answered with a terse
+    /// plain-text body naming only the status, e.g. `400 Bad Request`. With debug
+    /// errors enabled, the body is a JSON object with the `status`, the error `kind`
+    /// (such as `invalid_request` or `payload_too_large`) and a `message`, which helps
+    /// when developing clients. The message only details errors in the request itself;
+    /// for file system and internal errors it is the reason phrase, so paths on the
+    /// server are never disclosed. Keep this disabled in production.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - `true` to send JSON error details, `false` (the default) for terse
+    ///   bodies.
+    pub fn set_debug_errors(&mut self, enabled: bool) {
+        self.debug_errors = enabled;
+    }
+
+    /// Generates the response for an error, in the configured level of detail.
+    ///
+    /// # Arguments
+    ///
+    /// * `error` - The `ServerError` raised while handling the request.
+    ///
+    /// # Returns
+    ///
+    /// A `Response` with the status code mapped from the error.
+    pub(crate) fn error_response(
+        &self,
+        error: &ServerError,
+    ) -> Response {
+        if self.debug_errors {
+            generate_debug_error_response(error)
+        } else {
+            generate_error_response(error)
+        }
+    }
+
+    /// Sets whether textual file responses are compressed with gzip.
+    ///
+    /// When enabled (the default with the `gzip` feature), responses for textual files
+    /// such as HTML, CSS, JavaScript and JSON are sent with `Content-Encoding: gzip` to
+    /// clients whose `Accept-Encoding` allows it. These responses always carry
+    /// `Vary: Accept-Encoding`, so caches keep the compressed and identity variants
+    /// apart, and their `ETag` is made weak since the bytes differ from the file.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - `true` to compress textual responses.
+    #[cfg(feature = "gzip")]
+    pub fn set_gzip(&mut self, enabled: bool) {
+        self.gzip = enabled;
+    }
+
+    /// Sets whether textual file responses are compressed with Brotli.
+    ///
+    /// When enabled (the default with the `brotli` feature), clients whose
+    /// `Accept-Encoding` allows it get responses for textual files with
+    /// `Content-Encoding: br`. When gzip is also enabled, the encoding with the highest
+    /// quality value in `Accept-Encoding` is used, and Brotli wins ties since it
+    /// compresses text better.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - `true` to compress textual responses with Brotli.
+    #[cfg(feature = "brotli")]
+    pub fn set_brotli(&mut self, enabled: bool) {
+        self.brotli = enabled;
+    }
+
+    /// Sets the smallest file compressed and the compression level.
+    ///
+    /// Files smaller than `min_size` bytes are always sent unencoded, since compressing
+    /// them saves little and costs a round of CPU work on every request; their
+    /// responses do not vary on `Accept-Encoding`. By default every compressible file
+    /// is compressed, at level 6 for gzip and quality 5 for Brotli.
+    ///
+    /// # Arguments
+    ///
+    /// * `min_size` - The smallest file size compressed, in bytes.
+    /// * `level` - The compression level used by every enabled codec, from 0 (fastest)
+    ///   up to 9 with the `gzip` feature, or 11 with only the `brotli` feature.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `level` is outside the range accepted by an enabled codec.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::Server;
+    ///
+    /// let mut server = Server::new("127.0.0.1:8080", "./public");
+    /// server.set_compression(1024, 4);
+    /// ```
+    #[cfg(any(feature = "gzip", feature = "brotli"))]
+    pub fn set_compression(&mut self, min_size: usize, level: u32) {
+        assert!(
+            level <= compression::MAX_LEVEL,
+            "level must be at most {}",
+            compression::MAX_LEVEL
+        );
+        self.compression_min_size = min_size;
+        self.compression_level = Some(level);
+    }
+
+    /// Sets the content types eligible for compression.
+    ///
+    /// This replaces the default list of textual types: `text/*`,
+    /// `application/javascript`, `application/json`, `application/xml` and
+    /// `image/svg+xml`. Media types are matched case-insensitively, ignoring
+    /// parameters such as `charset`, and `type/*` matches a whole top-level type.
+    ///
+    /// # Arguments
+    ///
+    /// * `media_types` - The media types to compress, e.g. `["text/html", "text/css"]`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::Server;
+    ///
+    /// let mut server = Server::new("127.0.0.1:8080", "./public");
+    /// server.set_compressible_types(&["text/*", "application/json"]);
+    /// ```
+    #[cfg(any(feature = "gzip", feature = "brotli"))]
+    pub fn set_compressible_types(&mut self, media_types: &[&str]) {
+        self.compressible_types = Some(
+            media_types.iter().map(|t| t.trim().to_string()).collect(),
+        );
+    }
+
+    /// Lists the enabled content codings, most preferred first.
+    ///
+    /// # Returns
+    ///
+    /// The codings responses may be compressed with.
+    #[cfg(any(feature = "gzip", feature = "brotli"))]
+    fn encodings(&self) -> Vec<&'static str> {
+        let mut encodings = Vec::new();
+        #[cfg(feature = "brotli")]
+        if self.brotli {
+            encodings.push("br");
+        }
+        #[cfg(feature = "gzip")]
+        if self.gzip {
+            encodings.push("gzip");
+        }
+        encodings
+    }
+
+    /// Checks whether responses for a file are subject to compression.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file being served.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the response depends on the client's `Accept-Encoding`.
+    fn compresses(&self, path: &Path) -> bool {
+        #[cfg(any(feature = "gzip", feature = "brotli"))]
+        {
+            let content_type = get_content_type(path);
+            !self.encodings().is_empty()
+                && !self.injects_live_reload(path)
+                && match &self.compressible_types {
+                    Some(types) => {
+                        compression::is_listed(content_type, types)
+                    }
+                    None => compression::is_compressible(content_type),
+                }
+                && (self.compression_min_size == 0
+                    || fs::metadata(path).map_or(false, |metadata| {
+                        metadata.len()
+                            >= self.compression_min_size as u64
+                    }))
+        }
+        #[cfg(not(any(feature = "gzip", feature = "brotli")))]
+        {
+            let _ = path;
+            false
+        }
+    }
+
+    /// Lists every file the server would serve from its document roots.
+    ///
+    /// The document root and any roots added with [`Server::add_document_root`] are
+    /// walked recursively, skipping hidden files and directories when dotfiles are
+    /// blocked. Symbolic links are followed like the server follows them, but each
+    /// directory is visited only once, so symlink loops are harmless. The server does
+    /// not need to be running, which makes this suitable for building a sitemap or
+    /// warming a cache.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the sorted file paths, relative to their document root and
+    /// listed once even when several roots contain them, or a `ServerError` if a
+    /// directory cannot be read.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use http_handle::Server;
+    ///
+    /// let server = Server::new("127.0.0.1:8080", "./public");
+    /// for path in server.list_files().unwrap() {
+    ///     println!("/{}", path.display());
+    /// }
+    /// ```
+    pub fn list_files(&self) -> Result<Vec<PathBuf>, ServerError> {
+        let mut files = Vec::new();
+        for document_root in
+            iter::once(&self.document_root).chain(&self.fallback_roots)
+        {
+            let mut visited = BTreeSet::new();
+            collect_files(
+                document_root,
+                document_root,
+                self.block_dotfiles,
+                &mut visited,
+                &mut files,
+            )?;
+        }
+        files.sort();
+        files.dedup();
+        Ok(files)
+    }
+
+    /// Adds the headers configured for every response.
+    ///
+    /// These are the `Server` header and, when configured, the security headers.
+    /// Final responses other than `304 Not Modified` also get `Accept-Ranges: none`,
+    /// since only file responses, which set the header themselves, serve ranges.
+    /// Headers already set on the response are left untouched.
+    ///
+    /// # Arguments
+    ///
+    /// * `response` - The response about to be sent.
+    pub(crate) fn apply_response_headers(
+        &self,
+        response: &mut Response,
+    ) {
+        let security_headers = if self.security_headers {
+            &SECURITY_HEADERS[..]
+        } else {
+            &[]
+        };
+        let configured = self
+            .server_header
+            .as_deref()
+            .map(|value| ("Server", value))
+            .into_iter()
+            .chain(security_headers.iter().copied())
+            .chain(
+                self.content_security_policy
+                    .as_deref()
+                    .map(|policy| ("Content-Security-Policy", policy)),
+            );
+
+        for (name, value) in configured {
+            if !has_header(response, name) {
+                response.add_header(name, value);
+            }
+        }
+        if response.status_code >= 200
+            && response.status_code != 304
+            && !has_header(response, "Accept-Ranges")
+        {
+            response.add_header("Accept-Ranges", "none");
+        }
+    }
+
+    /// Adds the headers configured for static files to a file response.
+    ///
+    /// This sets `Cache-Control`, `Content-Disposition: attachment` when `path` has a
+    /// download extension, and then the headers of the matching header rules.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The request being answered.
+    /// * `path` - The file being served.
+    /// * `response` - The response for the file.
+    fn apply_file_headers(
+        &self,
+        request: &Request,
+        path: &Path,
+        response: &mut Response,
+    ) {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+
+        let cache_control = extension
+            .as_ref()
+            .and_then(|ext| self.cache_control_overrides.get(ext))
+            .unwrap_or(&self.cache_control);
+        if !cache_control.is_empty() {
+            response.add_header("Cache-Control", cache_control);
+        }
+
+        let is_download = extension.as_ref().map_or(false, |ext| {
+            self.attachment_extensions.contains(ext)
+        });
+        if is_download {
+            if let Some(name) =
+                path.file_name().and_then(|n| n.to_str())
+            {
+                response.attachment(name);
+            }
+        }
+
+        if !self.languages.is_empty() {
+            match language_tag(path, &self.languages) {
+                Some(tag) => {
+                    response.add_header("Content-Language", tag);
+                    response.add_vary("Accept-Language");
+                }
+                None if !language_variants(path, &self.languages)
+                    .is_empty() =>
+                {
+                    response.add_vary("Accept-Language");
+                }
+                None => {}
+            }
+        }
+
+        let request_path =
+            request.path().split('?').next().unwrap_or_default();
+        for (prefix, name, value) in &self.header_rules {
+            if request_path.starts_with(prefix.as_str()) {
+                response.set_header(name, value);
+            }
+        }
+    }
+
+    /// Sets whether directory requests without a trailing slash are redirected.
+    ///
+    /// When enabled (the default), a request for `/docs` where `docs` is a directory is
+    /// answered with `301 Moved Permanently` and `Location: /docs/`, so relative links in
+    /// the directory's index resolve correctly. When disabled, the index is served directly.
+    ///
+    /// # Arguments
+    ///
+    /// * `enabled` - `true` to redirect directory requests to their slashed path.
+    pub fn set_redirect_directories(&mut self, enabled: bool) {
+        self.redirect_directories = enabled;
+    }
+
+    /// Sets the file names served for requests to a directory, including the root.
+    ///
+    /// The names are tried in order, and the first one present in the directory is
+    /// served with the content type of its extension. A directory containing none of
+    /// them is answered with `404 Not Found`. The default is `index.html`.
+    ///
+    /// # Arguments
+    ///
+    /// * `names` - The index file names, most preferred first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::Server;
+    ///
+    /// let mut server = Server::new("127.0.0.1:8080", "./public");
+    /// server.set_index_files(&["index.html", "index.htm"]);
+    /// ```
+    pub fn set_index_files(&mut self, names: &[&str]) {
+        self.index_files =
+            names.iter().map(|name| name.to_string()).collect();
+    }
+
+    /// Adds a document root searched after the ones already configured.
+    ///
+    /// Requests are resolved against each document root in turn, starting with the one
+    /// given to [`Server::new`], and the first root containing the requested file serves
+    /// it, like the layers of an overlay file system. Traversal protection and dotfile
+    /// blocking apply to each root on its own, and `404 Not Found` pages come from the
+    /// first root. Virtual hosts are served from their own root only.
+    ///
+    /// # Arguments
+    ///
+    /// * `document_root` - The document root to fall back to.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::Server;
+    /// use std::path::PathBuf;
+    ///
+    /// // Files in ./overrides take precedence over those of the base theme
+    /// let mut server = Server::new("127.0.0.1:8080", "./overrides");
+    /// server.add_document_root(PathBuf::from("./theme"));
+    /// ```
+    pub fn add_document_root(&mut self, document_root: PathBuf) {
+        self.fallback_roots.push(document_root);
+    }
+
+    /// Sets a handler answering the requests no static file matches.
+    ///
+    /// The handler is called instead of sending `404 Not Found` when no document root
+    /// contains the requested file, and instead of applying the
+    /// [`UnsupportedMethodPolicy`] to methods other than `GET`, `HEAD` and `OPTIONS`.
+    /// Its response is sent as is, apart from the server-wide headers. Hidden files and
+    /// paths escaping the document root are still refused before it is called.
+    ///
+    /// # Arguments
+    ///
+    /// * `handler` - The function building the response for a request.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::request::Request;
+    /// use http_handle::response::Response;
+    /// use http_handle::Server;
+    ///
+    /// let mut server = Server::new("127.0.0.1:8080", "./public");
+    /// server.set_fallback(|request: &Request| {
+    ///     Response::text(200, format!("No file at {}", request.path()))
+    /// });
+    /// ```
+    pub fn set_fallback<F>(&mut self, handler: F)
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        self.fallback = Some(Handler(Arc::new(handler)));
+    }
+
+    /// Sets the handler taking over WebSocket connections opened at a path.
+    ///
+    /// Each path has its own handler; setting one for a path already configured
+    /// replaces it. A WebSocket upgrade request for `path` is answered with
+    /// `101 Switching Protocols` and the computed `Sec-WebSocket-Accept`, or with
+    /// `400 Bad Request` if its key or version is invalid. After a successful
+    /// handshake, `handler` is called on the connection's thread with the request and
+    /// the raw connection; it speaks the WebSocket protocol itself, framing included,
+    /// and the connection is closed when it returns. Other requests for `path` are
+    /// served as usual. Only the synchronous server performs upgrades.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The request path of the WebSocket endpoint, e.g. `/live`.
+    /// * `handler` - The function taking over each upgraded connection.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::request::Request;
+    /// use http_handle::websocket::UpgradedStream;
+    /// use http_handle::Server;
+    /// use std::io::Write;
+    ///
+    /// let mut server = Server::new("127.0.0.1:8080", "./public");
+    /// server.set_websocket_handler(
+    ///     "/live",
+    ///     |_: &Request, mut stream: UpgradedStream| {
+    ///         // An unmasked text frame saying "hi"
+    ///         let _ = stream.write_all(&[0x81, 0x02, b'h', b'i']);
+    ///     },
+    /// );
+    /// ```
+    pub fn set_websocket_handler<F>(&mut self, path: &str, handler: F)
+    where
+        F: Fn(&Request, UpgradedStream) + Send + Sync + 'static,
+    {
+        let handler: WebSocketHandler = Handler(Arc::new(handler));
+        match self.websockets.iter_mut().find(|(p, _)| p == path) {
+            Some((_, existing)) => *existing = handler,
+            None => self.websockets.push((path.to_string(), handler)),
+        }
+    }
+
+    /// Enables reloading browsers when the site changes, for development.
+    ///
+    /// A script is injected before the closing `</body>` tag of every HTML page, or at
+    /// the end of pages without one, and `Content-Length` is adjusted to match. Other
+    /// responses are left untouched, and HTML is no longer compressed so that it can be
+    /// rewritten. The script connects to a WebSocket at
+    /// [`LIVE_RELOAD_PATH`](crate::livereload::LIVE_RELOAD_PATH) and reloads the page
+    /// when told to through the handle returned by [`Server::live_reload_handle`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::Server;
+    ///
+    /// let mut server = Server::new("127.0.0.1:8080", "./public");
+    /// server.enable_live_reload();
+    /// assert!(server.live_reload_handle().is_some());
+    /// ```
+    pub fn enable_live_reload(&mut self) {
+        if self.live_reload.is_some() {
+            return;
+        }
+        let handle = ReloadHandle::default();
+        let endpoint = handle.clone();
+        self.set_websocket_handler(
+            LIVE_RELOAD_PATH,
+            move |_: &Request, stream: UpgradedStream| {
+                endpoint.serve(stream)
+            },
+        );
+        self.live_reload = Some(handle);
+    }
+
+    /// Returns the handle telling browsers to reload, if live reload is enabled.
+    ///
+    /// # Returns
+    ///
+    /// A handle sharing the server's reload state, or `None` without
+    /// [`Server::enable_live_reload`].
+    pub fn live_reload_handle(&self) -> Option<ReloadHandle> {
+        self.live_reload.clone()
+    }
+
+    /// Reloads browsers automatically when files under the document roots change.
+    ///
+    /// This enables live reload (see [`Server::enable_live_reload`]) and, while the
+    /// server runs, watches the document root and any roots added with
+    /// [`Server::add_document_root`], recursively. Changes are debounced: a reload is
+    /// sent once no further change has been seen for 100 milliseconds, so a burst of
+    /// writes, such as a site generator rewriting its output, reloads browsers once,
+    /// after the last write. Starting the server fails with an I/O error if a root
+    /// cannot be watched.
+    ///
+    /// Only available with the `watch` feature.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use http_handle::Server;
+    ///
+    /// let mut server = Server::new("127.0.0.1:8080", "./public");
+    /// server.enable_file_watch();
+    /// server.start().unwrap();
+    /// ```
+    #[cfg(feature = "watch")]
+    pub fn enable_file_watch(&mut self) {
+        self.enable_live_reload();
+        self.file_watch = true;
+    }
+
+    /// Starts watching the document roots, if enabled.
+    ///
+    /// # Returns
+    ///
+    /// The running watcher, stopped when dropped, or an I/O error if a root cannot be
+    /// watched.
+    #[cfg(feature = "watch")]
+    fn watch_files(&self) -> io::Result<Option<FileWatcher>> {
+        match (&self.live_reload, self.file_watch) {
+            (Some(reload), true) => {
+                info!("Watching the document roots for changes");
+                let roots = iter::once(&self.document_root)
+                    .chain(&self.fallback_roots)
+                    .map(PathBuf::as_path);
+                FileWatcher::start(
+                    roots,
+                    reload.clone(),
+                    watch::DEBOUNCE,
+                )
+                .map(Some)
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Checks whether responses for a file get the live reload script injected.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file being served.
+    ///
+    /// # Returns
+    ///
+    /// `true` if live reload is enabled and the file is HTML.
+    fn injects_live_reload(&self, path: &Path) -> bool {
+        self.live_reload.is_some()
+            && get_content_type(path).starts_with("text/html")
+    }
+
+    /// Injects the live reload script into an HTML response, if enabled.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The request being answered.
+    /// * `response` - The response being sent.
+    pub(crate) fn inject_live_reload(
+        &self,
+        request: &Request,
+        response: &mut Response,
+    ) {
+        if self.live_reload.is_some() {
+            livereload::inject_script(request, response);
+        }
+    }
+
+    /// Returns the WebSocket handler for a request, if it asks to upgrade at its path.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The request being handled.
+    ///
+    /// # Returns
+    ///
+    /// The handler, if the request is an upgrade request for the WebSocket endpoint.
+    pub(crate) fn websocket_handler(
+        &self,
+        request: &Request,
+    ) -> Option<&WebSocketHandler> {
+        if !websocket::is_upgrade_request(request) {
+            return None;
+        }
+        let request_path =
+            request.path().split('?').next().unwrap_or_default();
+        self.websockets
+            .iter()
+            .find(|(path, _)| path == request_path)
+            .map(|(_, handler)| handler)
+    }
+
+    /// Sets a hook called with every request before it is handled.
+    ///
+    /// The interceptor runs on each successfully parsed request, before any other
+    /// processing, and may modify it, for instance to add a header that later steps
+    /// or the response interceptor rely on. Requests that cannot be parsed are answered
+    /// without calling it.
+    ///
+    /// # Arguments
+    ///
+    /// * `interceptor` - The function called with each request.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::request::Request;
+    /// use http_handle::Server;
+    ///
+    /// let mut server = Server::new("127.0.0.1:8080", "./public");
+    /// server.set_request_interceptor(|request: &mut Request| {
+    ///     if request.header("X-Request-Id").is_none() {
+    ///         request.add_header("X-Request-Id", "generated");
+    ///     }
+    /// });
+    /// ```
+    pub fn set_request_interceptor<F>(&mut self, interceptor: F)
+    where
+        F: Fn(&mut Request) + Send + Sync + 'static,
+    {
+        self.request_interceptor = Some(Handler(Arc::new(interceptor)));
+    }
+
+    /// Sets a hook called with every response before it is sent.
+    ///
+    /// The interceptor receives the request and the complete response, including the
+    /// server-wide headers, and may modify the response. `Content-Length` is added
+    /// after it runs, so it may also replace the body. While it is set, file bodies are
+    /// built in memory rather than sent with `sendfile`, so that the interceptor sees
+    /// them.
+    ///
+    /// # Arguments
+    ///
+    /// * `interceptor` - The function called with each request and its response.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::request::Request;
+    /// use http_handle::response::Response;
+    /// use http_handle::Server;
+    ///
+    /// let mut server = Server::new("127.0.0.1:8080", "./public");
+    /// server.set_response_interceptor(
+    ///     |request: &Request, response: &mut Response| {
+    ///         if let Some(id) = request.header("X-Request-Id") {
+    ///             response.add_header("X-Request-Id", id);
+    ///         }
+    ///     },
+    /// );
+    /// ```
+    pub fn set_response_interceptor<F>(&mut self, interceptor: F)
+    where
+        F: Fn(&Request, &mut Response) + Send + Sync + 'static,
+    {
+        self.response_interceptor =
+            Some(Handler(Arc::new(interceptor)));
+    }
+
+    /// Adds a middleware wrapping the handling of every request.
+    ///
+    /// Middleware run in the order they are added, the first one added being the
+    /// outermost: it sees each request first and its response last. The static file
+    /// handler, including the [fallback](Server::set_fallback), is the end of the
+    /// chain. A middleware short-circuits the chain by returning a response without
+    /// calling [`Next::run`], e.g. to answer `401 Unauthorized`. Server-wide headers
+    /// and the response interceptor apply to every response, including those built by
+    /// middleware. While any middleware is added, file bodies are built in memory
+    /// rather than sent with `sendfile`.
+    ///
+    /// # Arguments
+    ///
+    /// * `middleware` - The middleware to add at the innermost position.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::middleware::{Middleware, Next};
+    /// use http_handle::request::Request;
+    /// use http_handle::response::Response;
+    /// use http_handle::Server;
+    ///
+    /// #[derive(Debug)]
+    /// struct PoweredBy;
+    ///
+    /// impl Middleware for PoweredBy {
+    ///     fn handle(&self, request: &Request, next: Next<'_>) -> Response {
+    ///         let mut response = next.run(request);
+    ///         response.add_header("X-Powered-By", "http-handle");
+    ///         response
+    ///     }
+    /// }
+    ///
+    /// let mut server = Server::new("127.0.0.1:8080", "./public");
+    /// server.add_middleware(Box::new(PoweredBy));
+    /// ```
+    pub fn add_middleware(&mut self, middleware: Box<dyn Middleware>) {
+        self.middleware.push(Handler(Arc::from(middleware)));
+    }
+
+    /// Answers a request through the middleware chain and the static file handler.
+    ///
+    /// Errors from the static file handler are turned into error responses, so
+    /// middleware always see a response.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The request to answer.
+    ///
+    /// # Returns
+    ///
+    /// The response to send.
+    pub(crate) fn dispatch(&self, request: &Request) -> Response {
+        let endpoint = |request: &Request| {
+            generate_response(request, self).unwrap_or_else(|e| {
+                warn!("Request {} failed: {}", request, e);
+                self.error_response(&e)
+            })
+        };
+        Next::new(&self.middleware, &endpoint).run(request)
+    }
+
+    /// Calls the request interceptor, if one is set.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The request about to be handled.
+    pub(crate) fn intercept_request(&self, request: &mut Request) {
+        if let Some(interceptor) = &self.request_interceptor {
+            (interceptor.0)(request);
+        }
+    }
+
+    /// Calls the response interceptor, if one is set.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The request being answered.
+    /// * `response` - The response about to be sent.
+    pub(crate) fn intercept_response(
+        &self,
+        request: &Request,
+        response: &mut Response,
+    ) {
+        if let Some(interceptor) = &self.response_interceptor {
+            (interceptor.0)(request, response);
+        }
+    }
+
+    /// Adds a virtual host served from its own document root.
+    ///
+    /// Requests whose `Host` header matches `hostname` are resolved against
+    /// `document_root` instead of the default document root. Hostnames are matched
+    /// case-insensitively and any port in the `Host` header is ignored. Requests for
+    /// unknown hosts fall back to the default document root.
+    ///
+    /// # Arguments
+    ///
+    /// * `hostname` - The host name to match (e.g., "a.example.com").
+    /// * `document_root` - The document root directory for this host.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use http_handle::Server;
+    /// use std::path::PathBuf;
+    ///
+    /// let mut server = Server::new("127.0.0.1:8080", "./public");
+    /// server.add_virtual_host("a.example.com", PathBuf::from("./sites/a"));
+    /// server.add_virtual_host("b.example.com", PathBuf::from("./sites/b"));
+    /// ```
+    pub fn add_virtual_host(
+        &mut self,
+        hostname: &str,
+        document_root: PathBuf,
+    ) {
+        let _ = self
+            .virtual_hosts
+            .insert(hostname.to_ascii_lowercase(), document_root);
+    }
+
+    /// Returns the document roots that should serve the given request.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The request whose `Host` header selects the virtual host.
+    ///
+    /// # Returns
+    ///
+    /// The matching virtual host's document root, or the default document root followed
+    /// by the fallback roots, in the order they are searched.
+    fn document_roots_for(&self, request: &Request) -> Vec<&Path> {
+        let virtual_host = request
+            .host()
+            .map(|host| strip_port(host).to_ascii_lowercase())
+            .and_then(|host| self.virtual_hosts.get(&host));
+        match virtual_host {
+            Some(document_root) => vec![document_root.as_path()],
+            None => iter::once(&self.document_root)
+                .chain(&self.fallback_roots)
+                .map(PathBuf::as_path)
+                .collect(),
+        }
+    }
+
+    /// Sets whether HTTP/1.1 requests must carry a valid `Host` header.
+    ///
+    /// When enabled, HTTP/1.1 requests without a valid `Host` header are answered with
+    /// `400 Bad Request`, as required by RFC 7230. HTTP/1.0 requests are not affected.
+    ///
+    /// # Arguments
+    ///
+    /// * `require_host` - `true` to reject HTTP/1.1 requests missing a `Host` header.
+    pub fn set_require_host(&mut self, require_host: bool) {
+        self.require_host = require_host;
+    }
+
+    /// Binds the listening socket using the configured address and backlog.
+    ///
+    /// # Returns
+    ///
+    /// The bound `TcpListener`, or an I/O error if the address cannot be resolved or bound.
+    pub(crate) fn bind(&self) -> io::Result<TcpListener> {
+        let address =
+            self.address.to_socket_addrs()?.next().ok_or_else(
+                || {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!(
+                            "Could not resolve address: {}",
+                            self.address
+                        ),
+                    )
+                },
+            )?;
+
+        let socket = self.new_socket(Domain::for_address(address))?;
+        socket.bind(&address.into())?;
+        socket.listen(self.listen_backlog)?;
+        Ok(socket.into())
+    }
+
+    /// Creates a TCP socket with the configured address reuse options.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - The address family of the socket.
+    ///
+    /// # Returns
+    ///
+    /// The new `Socket`, or an I/O error if it cannot be created or configured.
+    fn new_socket(&self, domain: Domain) -> io::Result<Socket> {
+        let socket =
+            Socket::new(domain, Type::STREAM, Some(Protocol::TCP))?;
+        // Windows' SO_REUSEADDR allows stealing bound ports, so keep the default there
+        #[cfg(not(windows))]
+        socket.set_reuse_address(true)?;
+
+        if self.reuse_port {
+            #[cfg(all(
+                unix,
+                not(any(
+                    target_os = "solaris",
+                    target_os = "illumos"
+                ))
+            ))]
+            socket.set_reuse_port(true)?;
+            #[cfg(not(all(
+                unix,
+                not(any(
+                    target_os = "solaris",
+                    target_os = "illumos"
+                ))
+            )))]
+            warn!("SO_REUSEPORT is not supported on this platform");
+        }
+        Ok(socket)
+    }
+
+    /// Applies the per-connection socket options to an accepted stream.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` - A reference to the newly accepted client connection, which may be a
+    ///   blocking or an asynchronous socket.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success or an I/O error.
+    pub(crate) fn configure_stream(
+        &self,
+        stream: SockRef<'_>,
+    ) -> io::Result<()> {
+        stream.set_nodelay(self.tcp_nodelay)
+    }
+
+    /// Serves a directory with the default settings, blocking until the server stops.
+    ///
+    /// This is the quickest way to get a server running: it creates a [`Server`] for
+    /// `address` and `document_root`, installs `handler` with
+    /// [`Server::set_fallback`], binds the address and serves connections. With the
+    /// `signals` feature on Unix, `SIGTERM` and `SIGINT` shut the server down
+    /// gracefully and this function returns; otherwise it runs until the process exits.
+    /// Build a [`Server`] instead to change any other setting.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The address to listen on (e.g., "127.0.0.1:8080").
+    /// * `document_root` - The directory to serve files from.
+    /// * `handler` - An optional handler for requests no file matches. Without one,
+    ///   the type still has to be named, e.g. `None::<fn(&Request) -> Response>`.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success or an I/O error, for example if the address
+    /// cannot be bound.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use http_handle::request::Request;
+    /// use http_handle::response::Response;
+    /// use http_handle::Server;
+    ///
+    /// // Static files from ./public, and a JSON answer for everything else
+    /// Server::serve_blocking(
+    ///     "127.0.0.1:8080",
+    ///     "./public",
+    ///     Some(|request: &Request| {
+    ///         let mut response = Response::text(404, "{\"error\":\"not found\"}");
+    ///         response.set_header("Content-Type", "application/json");
+    ///         response
+    ///     }),
+    /// )
+    /// .unwrap();
+    /// ```
+    pub fn serve_blocking<F>(
+        address: &str,
+        document_root: &str,
+        handler: Option<F>,
+    ) -> io::Result<()>
+    where
+        F: Fn(&Request) -> Response + Send + Sync + 'static,
+    {
+        let mut server = Server::new(address, document_root);
+        if let Some(handler) = handler {
+            server.set_fallback(handler);
+        }
+        let listener = server.bind()?;
+        let shutdown = ShutdownHandle::new();
+        #[cfg(all(unix, feature = "signals"))]
+        shutdown.stop_on_signals()?;
+        server.serve_with_shutdown(listener, &shutdown)
+    }
+
+    /// Starts the server and begins listening for incoming connections.
+    ///
+    /// Startup, connection and request events are emitted through the `log` facade;
+    /// install a logger (e.g., `env_logger`) to see them.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success or an I/O error.
+    pub fn start(&self) -> io::Result<()> {
+        self.start_with_shutdown(&ShutdownHandle::new())
+    }
+
+    /// Starts the server and serves connections until `shutdown` is stopped.
+    ///
+    /// See [`Server::serve_with_shutdown`] for how the shutdown proceeds.
+    ///
+    /// # Arguments
+    ///
+    /// * `shutdown` - The handle used to stop the server.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success or an I/O error.
+    pub fn start_with_shutdown(
+        &self,
+        shutdown: &ShutdownHandle,
+    ) -> io::Result<()> {
+        #[cfg(unix)]
+        if let Some(path) = &self.unix_socket {
+            let listener = UnixListener::bind(path)?;
+            return self
+                .serve_unix_with_shutdown(listener, path, shutdown);
+        }
+        let listener = self.bind()?;
+        self.serve_with_shutdown(listener, shutdown)
+    }
+
+    /// Binds a dual-stack listener accepting both IPv4 and IPv6 clients on `port`.
+    ///
+    /// The socket is bound to the IPv6 unspecified address `[::]` with `IPV6_V6ONLY`
+    /// disabled, so IPv4 clients connect through IPv4-mapped addresses. Address reuse
+    /// is configured as for [`Server::start`], including [`Server::set_reuse_port`].
+    /// Pass the returned listener to [`Server::serve`] to start accepting connections.
+    ///
+    /// # Arguments
+    ///
+    /// * `port` - The port to listen on (0 picks an ephemeral port).
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(TcpListener)` - The bound dual-stack listener.
+    /// * `Err(ServerError)` - If IPv6 or dual-stack sockets are not supported on this
+    ///   platform, or the port cannot be bound.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use http_handle::Server;
+    ///
+    /// let server = Server::new("[::]:8080", "./public");
+    /// let listener = server.bind_dual_stack(8080).unwrap();
+    /// server.serve(listener).unwrap();
+    /// ```
+    pub fn bind_dual_stack(
+        &self,
+        port: u16,
+    ) -> Result<TcpListener, ServerError> {
+        let socket = self.new_socket(Domain::IPV6).map_err(|e| {
+            ServerError::Custom(format!(
+                "IPv6 sockets are not supported on this platform: {}",
+                e
+            ))
+        })?;
+        socket.set_only_v6(false).map_err(|e| {
+            ServerError::Custom(format!(
+                "Dual-stack sockets are not supported on this platform: {}",
+                e
+            ))
+        })?;
+
+        let address =
+            SocketAddr::new(IpAddr::V6(Ipv6Addr::UNSPECIFIED), port);
+        socket.bind(&address.into())?;
+        socket.listen(self.listen_backlog)?;
+        Ok(socket.into())
+    }
+
+    /// Serves connections accepted from an already bound listener.
+    ///
+    /// This is what [`Server::start`] runs after binding; it is useful when the
+    /// listener is created separately, such as with [`Server::bind_dual_stack`].
+    ///
+    /// # Arguments
+    ///
+    /// * `listener` - The bound listener to accept connections from.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success or an I/O error.
+    pub fn serve(&self, listener: TcpListener) -> io::Result<()> {
+        self.serve_with_shutdown(listener, &ShutdownHandle::new())
+    }
+
+    /// Serves connections accepted from a listener until `shutdown` is stopped.
+    ///
+    /// Once [`ShutdownHandle::stop`] is called, the listener is closed so no new
+    /// connections are accepted, and requests already being handled run to completion,
+    /// with `Connection: close` on their responses. Idle persistent connections are
+    /// closed within a fraction of a second. This method returns when every connection
+    /// has finished.
+    ///
+    /// # Arguments
+    ///
+    /// * `listener` - The bound listener to accept connections from.
+    /// * `shutdown` - The handle used to stop the server.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success or an I/O error.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use http_handle::{Server, ShutdownHandle};
+    /// use std::net::TcpListener;
+    /// use std::thread;
+    ///
+    /// let server = Server::new("127.0.0.1:8080", "./public");
+    /// let shutdown = ShutdownHandle::new();
+    /// let stopper = shutdown.clone();
+    /// let _ = thread::spawn(move || {
+    ///     // ... later, from anywhere:
+    ///     stopper.stop();
+    /// });
+    ///
+    /// let listener = TcpListener::bind("127.0.0.1:8080").unwrap();
+    /// server.serve_with_shutdown(listener, &shutdown).unwrap();
+    /// ```
+    pub fn serve_with_shutdown(
+        &self,
+        listener: TcpListener,
+        shutdown: &ShutdownHandle,
+    ) -> io::Result<()> {
+        #[cfg(feature = "tls")]
+        let scheme = if self.tls.is_some() { "https" } else { "http" };
+        #[cfg(not(feature = "tls"))]
+        let scheme = "http";
+        info!(
+            "Server is now running at {}://{}",
+            scheme,
+            listener.local_addr()?
+        );
+        info!("Document root: {}", self.document_root.display());
+
+        shutdown.register_listener(ListenerAddress::Tcp(
+            listener.local_addr()?,
+        ));
+        #[cfg(feature = "watch")]
+        let _watcher = self.watch_files()?;
+        #[cfg(feature = "tls")]
+        if let Some(config) = &self.tls {
+            let incoming = listener.incoming().map(|stream| {
+                stream.and_then(|stream| {
+                    TlsStream::new(stream, Arc::clone(&config.0))
+                })
+            });
+            self.accept_connections(incoming, shutdown);
+        } else {
+            self.accept_connections(listener.incoming(), shutdown);
+        }
+        #[cfg(not(feature = "tls"))]
+        self.accept_connections(listener.incoming(), shutdown);
+
+        drop(listener);
+        info!("Shutting down, waiting for open connections to finish");
+        shutdown.wait_for_connections();
+        info!("Server stopped");
+        Ok(())
+    }
+
+    /// Serves connections accepted from a Unix domain socket until `shutdown` is
+    /// stopped, then removes the socket file.
+    ///
+    /// # Arguments
+    ///
+    /// * `listener` - The bound listener to accept connections from.
+    /// * `path` - The path the listener is bound to.
+    /// * `shutdown` - The handle used to stop the server.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` indicating success or an I/O error.
+    #[cfg(unix)]
+    fn serve_unix_with_shutdown(
+        &self,
+        listener: UnixListener,
+        path: &Path,
+        shutdown: &ShutdownHandle,
+    ) -> io::Result<()> {
+        info!("Server is now running at unix:{}", path.display());
+        info!("Document root: {}", self.document_root.display());
+
+        shutdown.register_listener(ListenerAddress::Unix(
+            path.to_path_buf(),
+        ));
+        #[cfg(feature = "watch")]
+        let _watcher = self.watch_files()?;
+        self.accept_connections(listener.incoming(), shutdown);
+
+        drop(listener);
+        if let Err(e) = fs::remove_file(path) {
+            warn!(
+                "Failed to remove socket file {}: {}",
+                path.display(),
+                e
+            );
+        }
+        info!("Shutting down, waiting for open connections to finish");
+        shutdown.wait_for_connections();
+        info!("Server stopped");
+        Ok(())
+    }
+
+    /// Accepts connections and serves each on its own thread until `shutdown` is
+    /// stopped.
+    ///
+    /// # Arguments
+    ///
+    /// * `incoming` - The connections accepted by a listener.
+    /// * `shutdown` - The handle used to stop the server.
+    fn accept_connections<S, I>(
+        &self,
+        incoming: I,
+        shutdown: &ShutdownHandle,
+    ) where
+        S: ClientStream,
+        I: Iterator<Item = io::Result<S>>,
+    {
+        let limiter = self
+            .max_connections
+            .map(|max| Arc::new(ConnectionLimiter::new(max)));
+        let rejecting =
+            Arc::new(ConnectionLimiter::new(MAX_REJECTING_CONNECTIONS));
+        let server = Arc::new(self.clone());
+
+        for stream in incoming {
+            if shutdown.is_stopped() {
+                break;
+            }
+            match stream {
+                Ok(stream) => {
+                    let slot = match &limiter {
+                        Some(limiter) => {
+                            let wait = self.connection_limit_policy
+                                == ConnectionLimitPolicy::Queue;
+                            match ConnectionSlot::acquire(limiter, wait)
+                            {
+                                Some(slot) => Some(slot),
+                                None => {
+                                    warn!("Connection limit reached, rejecting connection");
+                                    // A TLS handshake or a slow client must not
+                                    // hold up the accept loop, and a flood must not
+                                    // spawn a thread per connection
+                                    let slot =
+                                        match ConnectionSlot::acquire(
+                                            &rejecting, false,
+                                        ) {
+                                            Some(slot) => slot,
+                                            None => continue,
+                                        };
+                                    let server = Arc::clone(&server);
+                                    let _ = thread::spawn(move || {
+                                        reject_connection(
+                                            stream, &server,
+                                        );
+                                        drop(slot);
+                                    });
+                                    continue;
+                                }
+                            }
+                        }
+                        None => None,
+                    };
+                    match stream.remote_addr() {
+                        Some(peer) => {
+                            debug!("Connection accepted from {}", peer)
+                        }
+                        None => debug!("Connection accepted"),
+                    }
+                    if let Err(e) = stream.configure(self) {
+                        warn!("Failed to configure connection: {}", e);
+                    }
+                    let server = Arc::clone(&server);
+                    let connection = shutdown.track_connection();
+                    let _ = thread::spawn(move || {
+                        isolate_connection(stream, |stream| {
+                            handle_connection(
+                                stream,
+                                &server,
+                                &connection.0,
+                            )
+                        });
+                        drop(slot);
+                        drop(connection);
+                    });
+                }
+                Err(e) => warn!("Connection error: {}", e),
+            }
+        }
+    }
+}
+
+/// A handle for stopping a running server gracefully.
+///
+/// Clones share the same state, so one clone can be passed to
+/// [`Server::serve_with_shutdown`] while another is kept to call
+/// [`ShutdownHandle::stop`] from any thread. Once stopped, a handle stays stopped;
+/// create a new one to run the server again.
+#[derive(Clone, Debug, Default)]
+pub struct ShutdownHandle {
+    state: Arc<ShutdownState>,
+}
+
+/// The address a listener accepts connections on.
+#[derive(Clone, Debug)]
+enum ListenerAddress {
+    /// A TCP socket address.
+    Tcp(SocketAddr),
+    /// The path of a Unix domain socket.
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl ListenerAddress {
+    /// Connects to the listener, waking up an accept loop blocked in `accept`.
+    fn wake(&self) {
+        let result = match self {
+            ListenerAddress::Tcp(address) => {
+                let address = match address.ip() {
+                    IpAddr::V4(ip) if ip.is_unspecified() => {
+                        SocketAddr::new(
+                            Ipv4Addr::LOCALHOST.into(),
+                            address.port(),
+                        )
+                    }
+                    IpAddr::V6(ip) if ip.is_unspecified() => {
+                        SocketAddr::new(
+                            Ipv6Addr::LOCALHOST.into(),
+                            address.port(),
+                        )
+                    }
+                    _ => *address,
+                };
+                TcpStream::connect_timeout(
+                    &address,
+                    Duration::from_secs(1),
+                )
+                .map(drop)
+            }
+            #[cfg(unix)]
+            ListenerAddress::Unix(path) => {
+                UnixStream::connect(path).map(drop)
+            }
+        };
+        if let Err(e) = result {
+            debug!("Failed to wake up listener {:?}: {}", self, e);
+        }
+    }
+}
+
+/// The state shared by the clones of a [`ShutdownHandle`].
+#[derive(Debug, Default)]
+struct ShutdownState {
+    /// Whether the shutdown has been requested.
+    stopped: AtomicBool,
+    /// The addresses of the listeners to wake up when stopping.
+    listeners: Mutex<Vec<ListenerAddress>>,
+    /// The number of connections still being served.
+    active: Mutex<usize>,
+    /// Signalled whenever a connection finishes.
+    finished: Condvar,
+}
+
+impl ShutdownHandle {
+    /// Creates a handle that has not been stopped.
+    ///
+    /// # Returns
+    ///
+    /// A new `ShutdownHandle`.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests a graceful shutdown of the servers using this handle.
+    ///
+    /// This returns immediately; the serving call returns once in-flight requests have
+    /// completed. Calling it more than once has no further effect.
+    pub fn stop(&self) {
+        if self.state.stopped.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        let listeners = self
+            .state
+            .listeners
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone();
+        for address in listeners {
+            address.wake();
+        }
+    }
+
+    /// Returns whether a shutdown has been requested.
+    ///
+    /// # Returns
+    ///
+    /// `true` once [`ShutdownHandle::stop`] has been called.
+    pub fn is_stopped(&self) -> bool {
+        self.state.stopped.load(Ordering::SeqCst)
+    }
+
+    /// Stops the server when the process receives `SIGTERM` or `SIGINT`.
+    ///
+    /// This installs process-wide signal handlers and starts a thread that calls
+    /// [`ShutdownHandle::stop`] on the first signal, so a container runtime or a
+    /// terminal can drain the server before it exits. Signal handling belongs to the
+    /// application rather than a library, so this is opt-in and has limitations:
+    ///
+    /// - The handlers replace the default action of terminating the process, and stay
+    ///   installed for the lifetime of the process. Call this only once.
+    /// - A second signal, received while draining, exits the process immediately with
+    ///   status 1 instead of waiting for open connections.
+    /// - Only servers using this handle are stopped; the application is responsible
+    ///   for exiting once the serving call returns.
+    ///
+    /// Only available on Unix with the `signals` feature.
+    ///
+    /// # Returns
+    ///
+    /// An I/O error if the handlers cannot be installed.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use http_handle::{Server, ShutdownHandle};
+    ///
+    /// let server = Server::new("0.0.0.0:8080", "./public");
+    /// let shutdown = ShutdownHandle::new();
+    /// shutdown.stop_on_signals().unwrap();
+    /// server.start_with_shutdown(&shutdown).unwrap();
+    /// ```
+    #[cfg(all(unix, feature = "signals"))]
+    pub fn stop_on_signals(&self) -> io::Result<()> {
+        use signal_hook::consts::{SIGINT, SIGTERM};
+        use signal_hook::flag;
+        use signal_hook::iterator::Signals;
+
+        let signals = [SIGTERM, SIGINT];
+        let received = Arc::new(AtomicBool::new(false));
+        for &signal in &signals {
+            // Checked before `received` is set, so only a second signal exits
+            let _ = flag::register_conditional_shutdown(
+                signal,
+                1,
+                Arc::clone(&received),
+            )?;
+            let _ = flag::register(signal, Arc::clone(&received))?;
+        }
+
+        let mut signals = Signals::new(signals)?;
+        let handle = self.clone();
+        let _ = thread::Builder::new()
+            .name("http-handle-signals".to_string())
+            .spawn(move || {
+                if let Some(signal) = signals.forever().next() {
+                    info!("Received signal {}, shutting down", signal);
+                    handle.stop();
+                }
+            })?;
+        Ok(())
+    }
+
+    /// Records the address of a listener to wake up when stopping.
+    ///
+    /// # Arguments
+    ///
+    /// * `address` - The local address of the listener.
+    fn register_listener(&self, address: ListenerAddress) {
+        self.state
+            .listeners
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(address);
+    }
+
+    /// Counts a connection as being served until the returned guard is dropped.
+    ///
+    /// # Returns
+    ///
+    /// A guard holding a clone of this handle.
+    fn track_connection(&self) -> TrackedConnection {
+        *self
+            .state
+            .active
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner) += 1;
+        TrackedConnection(self.clone())
+    }
+
+    /// Blocks until every tracked connection has finished.
+    fn wait_for_connections(&self) {
+        let mut active = self
+            .state
+            .active
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        while *active > 0 {
+            active = self
+                .state
+                .finished
+                .wait(active)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+}
+
+/// A connection counted by its [`ShutdownHandle`], released when dropped.
+#[derive(Debug)]
+struct TrackedConnection(ShutdownHandle);
+
+impl Drop for TrackedConnection {
+    fn drop(&mut self) {
+        let state = &self.0.state;
+        *state.active.lock().unwrap_or_else(PoisonError::into_inner) -=
+            1;
+        state.finished.notify_all();
+    }
+}
+
+/// Counts the connections being served, to enforce the connection limit.
+#[derive(Debug)]
+struct ConnectionLimiter {
+    max: usize,
+    active: Mutex<usize>,
+    released: Condvar,
+}
+
+impl ConnectionLimiter {
+    /// Creates a limiter allowing `max` simultaneous connections.
+    fn new(max: usize) -> Self {
+        ConnectionLimiter {
+            max,
+            active: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+}
+
+/// A claimed connection slot, released when dropped.
+#[derive(Debug)]
+struct ConnectionSlot(Arc<ConnectionLimiter>);
+
+impl ConnectionSlot {
+    /// Claims a connection slot from the limiter.
+    ///
+    /// # Arguments
+    ///
+    /// * `limiter` - The limiter to claim a slot from.
+    /// * `wait` - Whether to block until a slot is released when none is free.
+    ///
+    /// # Returns
+    ///
+    /// The claimed slot, or `None` if none is free and `wait` is `false`.
+    fn acquire(
+        limiter: &Arc<ConnectionLimiter>,
+        wait: bool,
+    ) -> Option<Self> {
+        let mut active = limiter
+            .active
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        while *active >= limiter.max {
+            if !wait {
+                return None;
+            }
+            active = limiter
+                .released
+                .wait(active)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        *active += 1;
+        Some(ConnectionSlot(Arc::clone(limiter)))
+    }
+}
+
+impl Drop for ConnectionSlot {
+    fn drop(&mut self) {
+        let mut active = self
+            .0
+            .active
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *active -= 1;
+        self.0.released.notify_one();
+    }
+}
+
+/// A connected client socket that requests can be served over.
+///
+/// This abstracts over TCP connections, TLS connections with the `tls` feature and,
+/// on Unix, Unix domain socket connections, so the same request handling serves all
+/// of them.
+pub(crate) trait ClientStream:
+    Read + Write + Send + Sized + 'static
+{
+    /// Creates a new handle to the same socket.
+    fn try_clone(&self) -> io::Result<Self>;
+
+    /// Sets the timeout of blocking reads, or removes it with `None`.
+    fn set_read_timeout(
+        &self,
+        timeout: Option<Duration>,
+    ) -> io::Result<()>;
+
+    /// Sets the timeout of blocking writes, or removes it with `None`.
+    fn set_write_timeout(
+        &self,
+        timeout: Option<Duration>,
+    ) -> io::Result<()>;
+
+    /// Shuts down the read side, the write side or both sides of the socket.
+    fn shutdown(&self, how: Shutdown) -> io::Result<()>;
+
+    /// Applies the server's socket options to a newly accepted connection.
+    fn configure(&self, server: &Server) -> io::Result<()>;
+
+    /// Returns the address of the remote end, if it has one.
+    fn remote_addr(&self) -> Option<String>;
+
+    /// Returns the descriptor `sendfile(2)` writes to, or `None` if data must be
+    /// written through the stream, as with TLS.
+    #[cfg(target_os = "linux")]
+    fn raw_fd(&self) -> Option<RawFd>;
+}
+
+impl ClientStream for TcpStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        TcpStream::try_clone(self)
+    }
+
+    fn set_read_timeout(
+        &self,
+        timeout: Option<Duration>,
+    ) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+
+    fn set_write_timeout(
+        &self,
+        timeout: Option<Duration>,
+    ) -> io::Result<()> {
+        TcpStream::set_write_timeout(self, timeout)
+    }
+
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        TcpStream::shutdown(self, how)
+    }
+
+    fn configure(&self, server: &Server) -> io::Result<()> {
+        server.configure_stream(SockRef::from(self))
+    }
+
+    fn remote_addr(&self) -> Option<String> {
+        self.peer_addr().ok().map(|address| address.to_string())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn raw_fd(&self) -> Option<RawFd> {
+        Some(self.as_raw_fd())
+    }
+}
+
+#[cfg(unix)]
+impl ClientStream for UnixStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        UnixStream::try_clone(self)
+    }
+
+    fn set_read_timeout(
+        &self,
+        timeout: Option<Duration>,
+    ) -> io::Result<()> {
+        UnixStream::set_read_timeout(self, timeout)
+    }
+
+    fn set_write_timeout(
+        &self,
+        timeout: Option<Duration>,
+    ) -> io::Result<()> {
+        UnixStream::set_write_timeout(self, timeout)
+    }
+
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        UnixStream::shutdown(self, how)
+    }
+
+    fn configure(&self, _server: &Server) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn remote_addr(&self) -> Option<String> {
+        // Clients of a Unix domain socket are usually unnamed
+        None
+    }
+
+    #[cfg(target_os = "linux")]
+    fn raw_fd(&self) -> Option<RawFd> {
+        Some(self.as_raw_fd())
+    }
+}
+
+/// Answers a connection over the limit with `503 Service Unavailable` and closes it.
+///
+/// This runs on a thread of its own, as sending the response performs the TLS
+/// handshake on a TLS listener, and only [`MAX_REJECTING_CONNECTIONS`] such threads run
+/// at once. The read timeout of the server bounds both reading from and writing to the
+/// client, so an idle or stalled client only holds that thread for so long. Any
+/// request already sent is drained before closing, so that the client receives the
+/// response rather than a reset.
+///
+/// # Arguments
+///
+/// * `stream` - The rejected connection.
+/// * `server` - The `Server` whose socket options and timeout apply.
+fn reject_connection<S: ClientStream>(mut stream: S, server: &Server) {
+    let timeout = Some(server.read_timeout).filter(|t| !t.is_zero());
+    if let Err(e) = stream
+        .configure(server)
+        .and_then(|_| stream.set_read_timeout(timeout))
+        .and_then(|_| stream.set_write_timeout(timeout))
+    {
+        warn!("Failed to configure connection: {}", e);
+    }
+    // Best effort: the client may already have gone away
+    if generate_503_response().send(&mut stream).is_ok() {
+        lingering_close(&mut stream);
+    }
+}
+
+/// Runs a connection handler, containing any panic to that connection.
+///
+/// Errors returned by the handler are logged. A panic, for example from a bug
+/// triggered by unusual input, is caught and logged as an error, and a
+/// `500 Internal Server Error` response is sent on a best-effort basis, so the
+/// accept loop and other connections carry on unaffected.
+///
+/// # Arguments
+///
+/// * `stream` - The client connection.
+/// * `handler` - The function serving the connection.
+fn isolate_connection<S, F>(stream: S, handler: F)
+where
+    S: ClientStream,
+    F: FnOnce(S) -> Result<(), ServerError>,
+{
+    let fallback = stream.try_clone();
+    match panic::catch_unwind(AssertUnwindSafe(|| handler(stream))) {
+        Ok(Ok(())) => {}
+        Ok(Err(ServerError::Timeout(e))) => {
+            warn!("Connection timed out: {}", e)
+        }
+        Ok(Err(e)) => error!("Error handling connection: {}", e),
+        Err(payload) => {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|m| m.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            error!("Panic while handling connection: {}", message);
+
+            if let Ok(mut stream) = fallback {
+                let mut response = generate_error_response(
+                    &ServerError::Custom(message),
+                );
+                response.add_header("Connection", "close");
+                let _ = response.send(&mut stream);
+                lingering_close(&mut stream);
+            }
+        }
+    }
+}
+
+/// Closes a connection whose request may not have been fully read.
+///
+/// Closing a socket with unread input makes the kernel reset the connection, which can
+/// discard a response the client has not read yet. The write side is shut down first
+/// and pending input is drained for a short while.
+///
+/// # Arguments
+///
+/// * `stream` - The client connection.
+fn lingering_close<S: ClientStream>(stream: &mut S) {
+    let _ = stream.shutdown(Shutdown::Write);
+    let _ = stream.set_read_timeout(Some(Duration::from_secs(1)));
+    let _ = io::copy(&mut stream.take(64 * 1024), &mut io::sink());
+}
+
+/// Handles a client connection, serving requests until it is closed.
+///
+/// Connections are persistent by default: after each response, the next request is
+/// read from the same connection. The connection is closed when the client asks for
+/// it (see [`Request::is_keep_alive`]), when a request carries a chunked body (which is
+/// not decoded), when `max_requests_per_connection` requests have been served, or when the
+/// client closes it or stays idle past the read timeout. The final response carries
+/// `Connection: close`, and the write side of the connection is then shut down so the
+/// client sees a clean end of stream. A client stalling past the read timeout after
+/// starting a request is answered with `408 Request Timeout` before the connection is
+/// closed.
+///
+/// A body announced by `Content-Length` is read whatever the method, so a `GET` that
+/// carries one, which the static file handler ignores, does not leave bytes behind
+/// that would be misread as the next request.
+///
+/// Once `shutdown` is stopped, the current response is the final one, and an idle
+/// connection is closed without waiting for the read timeout.
+///
+/// # Arguments
+///
+/// * `stream` - The client connection, over TCP or a Unix domain socket.
+/// * `server` - The `Server` configuration used to answer the requests.
+/// * `shutdown` - The handle signalling that the server is shutting down.
+///
+/// # Returns
+///
+/// A `Result` indicating success or a `ServerError`.
+fn handle_connection<S: ClientStream>(
+    mut stream: S,
+    server: &Server,
+    shutdown: &ShutdownHandle,
+) -> Result<(), ServerError> {
+    let mut reader = BufReader::with_capacity(
+        server.read_buffer_size,
+        stream.try_clone()?,
+    );
+    let mut summary = ConnectionSummary::new(stream.remote_addr());
+
+    loop {
+        // Before a request starts, a closed or idle connection ends quietly;
+        // stalling once it has started is answered by handle_request
+        if !wait_for_request(
+            &mut reader,
+            &stream,
+            server.read_timeout,
+            shutdown,
+        ) {
+            return Ok(());
+        }
+        summary.requests += 1;
+
+        match handle_request(
+            &mut stream,
+            &mut reader,
+            server,
+            &mut summary,
+            shutdown,
+        ) {
+            Ok(true) => {}
+            Ok(false) => {
+                finish_connection(&stream);
+                return Ok(());
+            }
+            Err(e) => {
+                finish_connection(&stream);
+                return Err(e);
+            }
+        }
+    }
+}
+
+/// The running totals of a connection, logged when the connection is closed.
+///
+/// The summary is logged at the debug level when it is dropped, so every connection
+/// produces a close record, including those ending with an error or a panic.
+#[derive(Debug)]
+pub(crate) struct ConnectionSummary {
+    /// The address of the client, if known.
+    pub(crate) remote_addr: Option<String>,
+    /// When the connection was accepted.
+    opened: Instant,
+    /// The number of requests received, including one being handled.
+    pub(crate) requests: usize,
+    /// The number of body bytes sent.
+    pub(crate) bytes: u64,
+}
+
+impl ConnectionSummary {
+    /// Starts the summary of a newly accepted connection.
+    ///
+    /// # Arguments
+    ///
+    /// * `remote_addr` - The address of the client, if known.
+    pub(crate) fn new(remote_addr: Option<String>) -> Self {
+        ConnectionSummary {
+            remote_addr,
+            opened: Instant::now(),
+            requests: 0,
+            bytes: 0,
+        }
+    }
+}
+
+impl Drop for ConnectionSummary {
+    fn drop(&mut self) {
+        debug!(
+            "Connection from {} closed after {} request(s), {} bytes sent in {:?}",
+            self.remote_addr.as_deref().unwrap_or("local client"),
+            self.requests,
+            self.bytes,
+            self.opened.elapsed()
+        );
+    }
+}
+
+/// The details of a served request recorded in the access log.
+#[derive(Debug)]
+pub(crate) struct AccessLogEntry<'a> {
+    /// When the response was sent.
+    pub(crate) time: SystemTime,
+    /// The request that was served.
+    pub(crate) request: &'a Request,
+    /// The status code of the response.
+    pub(crate) status: u16,
+    /// The number of body bytes sent.
+    pub(crate) bytes: u64,
+    /// The time taken to handle the request.
+    pub(crate) duration: Duration,
+    /// The address of the client, if known.
+    pub(crate) remote_addr: Option<&'a str>,
+    /// Whether the file was served from the file cache (`HIT` or `MISS`), if cached.
+    pub(crate) cache: Option<&'static str>,
+}
+
+impl AccessLogEntry<'_> {
+    /// Formats the entry as an access log line.
+    ///
+    /// # Arguments
+    ///
+    /// * `format` - The format of the line.
+    ///
+    /// # Returns
+    ///
+    /// The log line, without a trailing line break.
+    fn format(&self, format: LogFormat) -> String {
+        match format {
+            LogFormat::Text => {
+                let mut line = format!(
+                    "{} {} {} in {:?}",
+                    self.request.method(),
+                    self.request.path(),
+                    self.status,
+                    self.duration
+                );
+                if let Some(cache) = self.cache {
+                    line.push_str(&format!(" (cache {})", cache));
+                }
+                line
+            }
+            LogFormat::Json => serde_json::json!({
+                "timestamp": format_rfc3339(self.time),
+                "method": self.request.method(),
+                "path": self.request.path(),
+                "status": self.status,
+                "bytes": self.bytes,
+                "duration_ms": self.duration.as_secs_f64() * 1000.0,
+                "remote_addr": self.remote_addr,
+                "user_agent": self.request.header("User-Agent"),
+                "cache": self.cache,
+            })
+            .to_string(),
+        }
+    }
+}
+
+/// Waits for the next request on a connection.
+///
+/// The wait is split into short read timeouts, so that an idle connection notices a
+/// shutdown promptly, while still giving up after the read timeout. Once a request has
+/// started to arrive, the read timeout is restored for reading the rest of it.
+///
+/// # Arguments
+///
+/// * `reader` - The buffered reader over the connection.
+/// * `stream` - The client connection, whose read timeout is adjusted.
+/// * `timeout` - The read timeout of the server.
+/// * `shutdown` - The handle signalling that the server is shutting down.
+///
+/// # Returns
+///
+/// `true` if a request has started to arrive, `false` if the connection was closed,
+/// stayed idle too long, failed, or the server is shutting down.
+fn wait_for_request<R: BufRead, S: ClientStream>(
+    reader: &mut R,
+    stream: &S,
+    timeout: Duration,
+    shutdown: &ShutdownHandle,
+) -> bool {
+    let deadline = Instant::now() + timeout;
+    if stream
+        .set_read_timeout(Some(SHUTDOWN_POLL_INTERVAL))
+        .is_err()
+    {
+        return false;
+    }
+    let ready = loop {
+        match reader.fill_buf() {
+            Ok(buf) => break !buf.is_empty(),
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock
+                        | io::ErrorKind::TimedOut
+                        | io::ErrorKind::Interrupted
+                ) =>
+            {
+                if shutdown.is_stopped() || Instant::now() >= deadline {
+                    break false;
+                }
+            }
+            Err(_) => break false,
+        }
+    };
+    ready && stream.set_read_timeout(Some(timeout)).is_ok()
+}
+
+/// Shuts down the write side of a connection after its final response.
+///
+/// This sends the client an end of stream even while other handles to the socket are
+/// still open. A failure, typically because the client already closed the connection,
+/// is only logged.
+///
+/// # Arguments
+///
+/// * `stream` - The client connection.
+fn finish_connection<S: ClientStream>(stream: &S) {
+    if let Err(e) = stream.shutdown(Shutdown::Write) {
+        debug!("Failed to shut down connection: {}", e);
+    }
+}
+
+impl Server {
+    /// Returns whether a connection can be kept open after answering a request.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The request being answered.
+    /// * `requests` - The number of requests received on the connection so far.
+    ///
+    /// # Returns
+    ///
+    /// `true` if the client asked to keep the connection alive and the request cap
+    /// per connection has not been reached.
+    pub(crate) fn keeps_alive(
+        &self,
+        request: &Request,
+        requests: usize,
+    ) -> bool {
+        // Chunked request bodies are not decoded, so the connection cannot be reused
+        // after one; `Content-Length` bodies have been read by the parser
+        let has_unread_body =
+            request.header("Transfer-Encoding").is_some();
+        request.is_keep_alive()
+            && !has_unread_body
+            && requests < self.max_requests_per_connection
+    }
+
+    /// Applies the processing every response goes through right before it is sent.
+    ///
+    /// This takes the cache status, injects the live reload script, adds the configured
+    /// headers and `Server-Timing`, runs the response interceptor, then makes sure the
+    /// body is delimited and the `Connection` header matches `keep_alive`. The body of
+    /// a response to `HEAD` is dropped once its `Content-Length` is known, whether it
+    /// comes from a file, a handler, middleware or the interceptor.
+    ///
+    /// # Arguments
+    ///
+    /// * `request` - The request being answered.
+    /// * `response` - The response to send.
+    /// * `start` - When the request arrived.
+    /// * `keep_alive` - Whether the connection stays open after the response.
+    /// * `upgrade` - Whether the request asked to upgrade to a WebSocket endpoint.
+    ///
+    /// # Returns
+    ///
+    /// The cache status for the access log, and whether the response completes the
+    /// upgrade.
+    pub(crate) fn finish_response(
+        &self,
+        request: &Request,
+        response: &mut Response,
+        start: Instant,
+        keep_alive: bool,
+        upgrade: bool,
+    ) -> (Option<&'static str>, bool) {
+        let cache = self.take_cache_status(response);
+        self.inject_live_reload(request, response);
+        self.apply_response_headers(response);
+        if self.server_timing {
+            response.add_header(
+                "Server-Timing",
+                &format!(
+                    "total;dur={:.3}",
+                    start.elapsed().as_secs_f64() * 1000.0
+                ),
+            );
+        }
+        self.intercept_response(request, response);
+        let upgraded = upgrade && response.status_code == 101;
+
+        // Persistent connections need every body to be delimited
+        let has_length = response.headers.iter().any(|(name, _)| {
+            name.eq_ignore_ascii_case("Content-Length")
+        });
+        if !has_length && !upgraded {
+            response.add_header(
+                "Content-Length",
+                &response.body.len().to_string(),
+            );
+        }
+        if request.method().eq_ignore_ascii_case("HEAD") {
+            response.body.clear();
+        }
+        if upgraded {
+            // The handshake response already says `Connection: Upgrade`
+        } else if !keep_alive {
+            response.add_header("Connection", "close");
+        } else if request.version_enum() == Some(Version::Http10) {
+            response.add_header("Connection", "keep-alive");
+        }
+        (cache, upgraded)
+    }
+}
+
+/// Reads one request from a connection and sends its response.
+///
+/// The time taken to handle the request, from the arrival of the request until the
+/// response has been sent, is included in the request log line. With the `tracing`
+/// feature enabled, the request is handled inside a `request` span that records the
+/// method, path, response status and elapsed time in milliseconds.
+///
+/// # Arguments
+///
+/// * `stream` - The client connection, used to write the response.
+/// * `reader` - The buffered reader over the connection, positioned at the request.
+/// * `server` - The `Server` configuration used to answer the request.
+/// * `summary` - The totals of the connection, counting this request and updated
+///   with the bytes sent.
+/// * `shutdown` - The handle signalling that the server is shutting down.
+///
+/// # Returns
+///
+/// A `Result` with `true` if the connection should be kept open for another request.
+fn handle_request<S: ClientStream>(
+    stream: &mut S,
+    reader: &mut BufReader<S>,
+    server: &Server,
+    summary: &mut ConnectionSummary,
+    shutdown: &ShutdownHandle,
+) -> Result<bool, ServerError> {
+    #[cfg(feature = "tracing")]
+    let span = tracing::info_span!(
+        "request",
+        method = tracing::field::Empty,
+        path = tracing::field::Empty,
+        status = tracing::field::Empty,
+        duration_ms = tracing::field::Empty,
+    );
+    #[cfg(feature = "tracing")]
+    let _entered = span.enter();
+    let start = Instant::now();
+
+    let mut request =
+        match Request::parse_with_limit(reader, server.max_body_size) {
+            Ok(request) => request,
+            Err(e) => {
+                // Best effort: the client may already have gone away
+                let mut response = server.error_response(&e);
+                server.apply_response_headers(&mut response);
+                response.add_header("Connection", "close");
+                let _ = response.send(stream);
+                return Err(e);
+            }
+        };
+    server.intercept_request(&mut request);
+    #[cfg(feature = "tracing")]
+    {
+        let _ = span.record("method", request.method());
+        let _ = span.record("path", request.path());
+    }
+
+    let keep_alive = server.keeps_alive(&request, summary.requests)
+        && !shutdown.is_stopped();
+
+    let upgrade = server.websocket_handler(&request);
+    let (mut response, file) = if upgrade.is_some() {
+        let response = websocket::handshake_response(&request)
+            .unwrap_or_else(|e| {
+                warn!("WebSocket handshake {} failed: {}", request, e);
+                server.error_response(&e)
+            });
+        (response, None)
+    } else {
+        match server.health_check_response(&request) {
+            Some(response) => (response, None),
+            None if server.middleware.is_empty() => {
+                prepare_response(&request, server).unwrap_or_else(|e| {
+                    warn!("Request {} failed: {}", request, e);
+                    (server.error_response(&e), None)
+                })
+            }
+            None => (server.dispatch(&request), None),
+        }
+    };
+    let (cache, upgraded) = server.finish_response(
+        &request,
+        &mut response,
+        start,
+        keep_alive,
+        upgrade.is_some(),
+    );
+
+    response.send(stream)?;
+    let mut bytes = if response.allows_body() {
+        response.body.len() as u64
+    } else {
+        0
+    };
+    if let Some((mut file, length)) = file {
+        // Exactly the announced length, even if the file has changed since
+        transmit_file(stream, &mut file, length)?;
+        bytes += length;
+    }
+    summary.bytes += bytes;
+
+    let elapsed = start.elapsed();
+    #[cfg(feature = "tracing")]
+    {
+        let _ = span.record("status", response.status_code);
+        let _ =
+            span.record("duration_ms", elapsed.as_secs_f64() * 1000.0);
+    }
+
+    let entry = AccessLogEntry {
+        time: SystemTime::now(),
+        request: &request,
+        status: response.status_code,
+        bytes,
+        duration: elapsed,
+        remote_addr: summary.remote_addr.as_deref(),
+        cache,
+    };
+    server.log_access(&entry);
+
+    if let Some(handler) = upgrade.filter(|_| upgraded) {
+        // Hand over the connection with anything the client sent after the request
+        stream.set_read_timeout(None)?;
+        let buffered = reader.buffer().to_vec();
+        reader.consume(buffered.len());
+        let upgraded_stream = UpgradedStream::new(
+            buffered,
+            Box::new(stream.try_clone()?),
+            shutdown.clone(),
+        );
+        (handler.0)(&request, upgraded_stream);
+        return Ok(false);
+    }
+    Ok(keep_alive)
+}
+
+/// The outcome of resolving a request, before any file contents are read.
+#[derive(Debug)]
+enum Resolution {
+    /// The request is answered without serving a file (errors, redirects, `OPTIONS`).
+    Response(Box<Response>),
+    /// The request targets the static file at the given path.
+    File(PathBuf),
+}
+
+/// Generates an HTTP response based on the requested file.
+///
+/// `HEAD` requests go through the same resolution, including range and conditional
+/// handling, and get the resulting status and headers with an empty body and the
+/// `Content-Length` of the body a `GET` would have received.
+///
+/// # Arguments
+///
+/// * `request` - A `Request` instance representing the client's request.
+/// * `server` - The `Server` configuration used to answer the request.
+///
+/// # Returns
+///
+/// A `Result` containing the `Response` or a `ServerError`.
+pub(crate) fn generate_response(
+    request: &Request,
+    server: &Server,
+) -> Result<Response, ServerError> {
+    let mut response = match resolve_request(request, server)? {
+        Resolution::Response(response) => *response,
+        Resolution::File(path) => {
+            file_response(request, server, &path)?
+        }
+    };
+
+    // A HEAD response carries the headers a GET would, without the body
+    if request.method().eq_ignore_ascii_case("HEAD") {
+        let has_length = response.headers.iter().any(|(name, _)| {
+            name.eq_ignore_ascii_case("Content-Length")
+        });
+        if !has_length {
+            response.add_header(
+                "Content-Length",
+                &response.body.len().to_string(),
+            );
+        }
+        response.body.clear();
+    }
+    Ok(response)
+}
+
+/// Prepares the response for a connection, deferring eligible file bodies.
+///
+/// When `sendfile` is enabled and neither middleware nor a response interceptor is
+/// set, a plain `GET` of a whole file is answered with the
+/// response head only (including `Content-Length`) and the opened file, whose contents
+/// are then transmitted directly by [`transmit_file`]. Range requests and every other
+/// response are generated in memory by [`generate_response`].
+///
+/// # Arguments
+///
+/// * `request` - A `Request` instance representing the client's request.
+/// * `server` - The `Server` configuration used to answer the request.
+///
+/// # Returns
+///
+/// A `Result` containing the `Response` and, for deferred bodies, the file to send
+/// with the length announced in its `Content-Length`.
+fn prepare_response(
+    request: &Request,
+    server: &Server,
+) -> Result<(Response, Option<(File, u64)>), ServerError> {
+    let deferrable = server.sendfile
+        && server.file_cache.is_none()
+        && server.live_reload.is_none()
+        && server.response_interceptor.is_none()
+        && server.middleware.is_empty()
+        && request.method().eq_ignore_ascii_case("GET")
+        && request.header("Range").is_none()
+        && request.header("If-None-Match").is_none()
+        && request.header("If-Modified-Since").is_none()
+        && request.header("If-Unmodified-Since").is_none()
+        && request.header("If-Match").is_none();
+    if !deferrable {
+        return generate_response(request, server)
+            .map(|response| (response, None));
+    }
+
+    match resolve_request(request, server)? {
+        Resolution::File(path)
+            if negotiate_coding(request, server, &path).is_none() =>
+        {
+            Ok((generate_406_response(), None))
+        }
+        // A compressed body must be built in memory
+        Resolution::File(path) if server.compresses(&path) => {
+            Ok((file_response(request, server, &path)?, None))
+        }
+        Resolution::File(path) => {
+            let file =
+                File::open(&path).map_err(|e| file_error(e, &path))?;
+            let metadata = file.metadata()?;
+            let mut response = Response::new(200, "OK", Vec::new());
+            response
+                .add_header("Content-Type", get_content_type(&path));
+            add_validators(
+                &mut response,
+                &generate_etag(&metadata, server.weak_etags),
+                last_modified(&metadata),
+            );
+            response.add_header("Accept-Ranges", "bytes");
+            response.add_header(
+                "Content-Length",
+                &metadata.len().to_string(),
+            );
+            server.apply_file_headers(request, &path, &mut response);
+            Ok((response, Some((file, metadata.len()))))
+        }
+        Resolution::Response(response) => Ok((*response, None)),
+    }
+}
+
+/// Transmits the whole contents of `file` to the client.
+///
+/// On Linux this uses `sendfile(2)`, which copies the data from the file to the socket
+/// inside the kernel without passing through user space. If the kernel refuses (for
+/// example on file systems without `sendfile` support), or the stream has no descriptor
+/// to write to, it falls back to a buffered copy. Exactly `length` bytes are sent, so
+/// that the body matches its `Content-Length`; a file that has shrunk below it is an
+/// error, which closes the connection rather than leaving the client waiting.
+///
+/// # Arguments
+///
+/// * `stream` - The client connection.
+/// * `file` - The file whose contents form the response body.
+/// * `length` - The number of bytes announced in `Content-Length`.
+///
+/// # Returns
+///
+/// A `Result` indicating success or an I/O error.
+#[cfg(target_os = "linux")]
+fn transmit_file<S: ClientStream>(
+    stream: &mut S,
+    file: &mut File,
+    length: u64,
+) -> io::Result<()> {
+    let fd = match stream.raw_fd() {
+        Some(fd) => fd,
+        None => return copy_file(stream, file, length),
+    };
+    let mut offset: libc::off_t = 0;
+    let mut remaining = length;
+    while remaining > 0 {
+        // Linux transfers at most 0x7ffff000 bytes per call
+        let count = remaining.min(0x7fff_f000) as usize;
+        // SAFETY: both descriptors are valid for the duration of the call and
+        // `offset` points to a live `off_t`.
+        let sent = unsafe {
+            libc::sendfile(fd, file.as_raw_fd(), &mut offset, count)
+        };
+
+        if sent < 0 {
+            let error = io::Error::last_os_error();
+            if error.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            if remaining == length
+                && matches!(
+                    error.raw_os_error(),
+                    Some(libc::EINVAL) | Some(libc::ENOSYS)
+                )
+            {
+                return copy_file(stream, file, length);
+            }
+            return Err(error);
+        }
+        if sent == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "file shrank while being sent",
+            ));
+        }
+        remaining -= sent as u64;
+    }
+    Ok(())
+}
+
+/// Transmits the whole contents of `file` to the client with a buffered copy.
+///
+/// # Arguments
+///
+/// * `stream` - The client connection.
+/// * `file` - The file whose contents form the response body.
+/// * `length` - The number of bytes announced in `Content-Length`.
+///
+/// # Returns
+///
+/// A `Result` indicating success or an I/O error.
+#[cfg(not(target_os = "linux"))]
+fn transmit_file<S: ClientStream>(
+    stream: &mut S,
+    file: &mut File,
+    length: u64,
+) -> io::Result<()> {
+    copy_file(stream, file, length)
+}
+
+/// Copies exactly `length` bytes of `file` to the client through user space.
+///
+/// # Arguments
+///
+/// * `stream` - The client connection.
+/// * `file` - The file whose contents form the response body.
+/// * `length` - The number of bytes announced in `Content-Length`.
+///
+/// # Returns
+///
+/// A `Result` indicating success or an I/O error, `UnexpectedEof` if the file holds
+/// fewer than `length` bytes.
+fn copy_file<S: ClientStream>(
+    stream: &mut S,
+    file: &mut File,
+    length: u64,
+) -> io::Result<()> {
+    if io::copy(&mut file.take(length), stream)? < length {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "file shrank while being sent",
+        ));
+    }
+    Ok(())
+}
+
+/// Decodes the path of a request target.
+///
+/// The query string and fragment are left out, and each valid `%XX` escape is replaced
+/// by the byte it stands for; a `%` not followed by two hexadecimal digits is kept as
+/// is. Unlike form decoding, `+` is left unchanged.
+///
+/// # Arguments
+///
+/// * `target` - The request target, as received.
+///
+/// # Returns
+///
+/// The bytes of the decoded path.
+fn decode_path(target: &str) -> Vec<u8> {
+    let path =
+        target.split(|c| c == '?' || c == '#').next().unwrap_or("");
+    let hex = |b: u8| (b as char).to_digit(16);
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        match (
+            bytes[index],
+            bytes.get(index + 1).copied().and_then(hex),
+            bytes.get(index + 2).copied().and_then(hex),
+        ) {
+            (b'%', Some(high), Some(low)) => {
+                decoded.push((high * 16 + low) as u8);
+                index += 3;
+            }
+            (b, _, _) => {
+                decoded.push(b);
+                index += 1;
+            }
+        }
+    }
+    decoded
+}
+
+/// Resolves a request to either a complete response or the static file to serve.
+///
+/// This performs every check that does not need the file contents: `Host` validation,
+/// the path length limit, method policy, `OPTIONS`, path resolution with traversal
+/// protection, directory redirects and `404 Not Found`. The path is percent-decoded
+/// once, and both the length limit and the file lookup use the decoded value.
+///
+/// # Arguments
+///
+/// * `request` - A `Request` instance representing the client's request.
+/// * `server` - The `Server` configuration used to answer the request.
+///
+/// # Returns
+///
+/// A `Result` containing the `Resolution` or a `ServerError`.
+fn resolve_request(
+    request: &Request,
+    server: &Server,
+) -> Result<Resolution, ServerError> {
+    if server.require_host
+        && request.version_enum() == Some(Version::Http11)
+        && request.host().is_none()
+    {
+        return Ok(Resolution::Response(Box::new(
+            server.error_response(&ServerError::invalid_request(
+                "Missing or invalid Host header",
+            )),
+        )));
+    }
+
+    let decoded_path = decode_path(request.path());
+    if server
+        .max_path_length
+        .map_or(false, |max| decoded_path.len() > max)
+    {
+        return Ok(Resolution::Response(Box::new(
+            server.error_response(&ServerError::UriTooLong(format!(
+                "Path longer than {} bytes",
+                server.max_path_length.unwrap_or_default()
+            ))),
+        )));
+    }
+
+    let method = request.method();
+    if method.eq_ignore_ascii_case("OPTIONS") {
+        // Both `OPTIONS *` and per-resource requests advertise the server-wide methods
+        let mut response = Response::no_content();
+        response.add_header("Allow", ALLOWED_METHODS);
+        return Ok(Resolution::Response(Box::new(response)));
+    }
+
+    let document_roots = server.document_roots_for(request);
+    // Escapes that are not UTF-8 name no file that can be served
+    let decoded_path = String::from_utf8_lossy(&decoded_path);
+    let request_path = decoded_path.trim_start_matches('/');
+
+    if !method.eq_ignore_ascii_case("GET")
+        && !method.eq_ignore_ascii_case("HEAD")
+    {
+        if let Some(fallback) = &server.fallback {
+            for document_root in &document_roots {
+                let _ =
+                    confine_path(server, document_root, request_path)?;
+            }
+            return Ok(Resolution::Response(Box::new((fallback.0)(
+                request,
+            ))));
+        }
+        return match server.unsupported_method_policy {
+            UnsupportedMethodPolicy::MethodNotAllowed => {
+                let mut response = Response::new(
+                    405,
+                    "Method Not Allowed",
+                    b"405 Method Not Allowed".to_vec(),
+                );
+                response.add_header("Allow", ALLOWED_METHODS);
+                response.add_header("Content-Type", "text/plain");
+                Ok(Resolution::Response(Box::new(response)))
+            }
+            UnsupportedMethodPolicy::NotImplemented => {
+                Err(ServerError::not_implemented(method))
+            }
+        };
+    }
+
+    for document_root in &document_roots {
+        let mut path =
+            confine_path(server, document_root, request_path)?;
+
+        if path.is_dir() {
+            // The root itself needs no redirect
+            if server.redirect_directories
+                && !request_path.is_empty()
+                && !request_path.ends_with('/')
+            {
+                return Ok(Resolution::Response(Box::new(
+                    Response::redirect(
+                        301,
+                        &directory_location(request.path()),
+                    ),
+                )));
+            }
+
+            // Serve the first index file present; without one, the name still
+            // stands for the directory's language variants, or a 404
+            let index = server
+                .index_files
+                .iter()
+                .find(|name| path.join(name).is_file())
+                .or_else(|| server.index_files.first());
+            if let Some(index) = index {
+                path.push(index);
+            }
+        }
+
+        if !server.languages.is_empty() {
+            if let Some(variant) = select_language_variant(
+                request,
+                &path,
+                &server.languages,
+            ) {
+                path = variant;
+            }
+        }
+
+        if path.is_file() {
+            if let Some(prefix) = &server.accel_redirect {
+                return Ok(Resolution::Response(Box::new(
+                    accel_redirect_response(
+                        prefix,
+                        &path,
+                        document_root,
+                    ),
+                )));
+            }
+            return Ok(Resolution::File(path));
+        }
+    }
+
+    let response = match (&server.default_favicon, &server.fallback) {
+        (Some(icon), _) if request_path == "favicon.ico" => {
+            match icon {
+                Some(icon) => {
+                    let mut response =
+                        Response::new(200, "OK", icon.clone());
+                    response.add_header("Content-Type", "image/x-icon");
+                    response
+                }
+                None => Response::no_content(),
+            }
+        }
+        (_, Some(fallback)) => (fallback.0)(request),
+        (_, None) => generate_404_response(document_roots[0])?,
+    };
+    Ok(Resolution::Response(Box::new(response)))
+}
+
+/// Maps a decoded request path onto a document root, refusing paths it may not reach.
+///
+/// `..` components are resolved, and a path ending up outside the document root, or
+/// naming a hidden file while dotfiles are blocked, is refused.
+///
+/// # Arguments
+///
+/// * `server` - The `Server` configuration deciding whether dotfiles are blocked.
+/// * `document_root` - The document root the path is relative to.
+/// * `request_path` - The decoded request path, without its leading slash.
+///
+/// # Returns
+///
+/// A `Result` containing the file system path or a `ServerError::Forbidden`.
+fn confine_path(
+    server: &Server,
+    document_root: &Path,
+    request_path: &str,
+) -> Result<PathBuf, ServerError> {
+    let mut path = PathBuf::from(document_root);
+    for component in request_path.split('/') {
+        if component == ".." {
+            let _ = path.pop();
+        } else if !component.is_empty() {
+            path.push(component);
+        }
+    }
+
+    if !path.starts_with(document_root) {
+        return Err(ServerError::forbidden("Access denied"));
+    }
+
+    if server.block_dotfiles && is_hidden_path(&path, document_root) {
+        return Err(ServerError::forbidden(
+            "Access to hidden file denied",
+        ));
+    }
+    Ok(path)
+}
+
+/// Builds the complete response for a resolved static file.
+///
+/// The file is served with [`serve_file`], then given the configured file headers and,
+/// when enabled, compressed. A body compressed on the fly has no stable byte offsets,
+/// so `Range` is ignored and the full file is served whenever it will be compressed,
+/// or have the live reload script injected.
+///
+/// # Arguments
+///
+/// * `request` - A `Request` instance representing the client's request.
+/// * `server` - A reference to the `Server` instance.
+/// * `path` - The resolved path of the file to serve.
+///
+/// # Returns
+///
+/// A `Result` containing the `Response` or a `ServerError`.
+fn file_response(
+    request: &Request,
+    server: &Server,
+    path: &Path,
+) -> Result<Response, ServerError> {
+    let coding = negotiate_coding(request, server, path);
+    let compressed = coding.map_or(false, |c| c != "identity");
+    let allow_ranges = !compressed && !server.injects_live_reload(path);
+    let mut response = serve_file(
+        request,
+        server,
+        path,
+        allow_ranges,
+        coding.is_some(),
+    )?;
+    if response.status_code == 406 {
+        return Ok(response);
+    }
+    server.apply_file_headers(request, path, &mut response);
+    if server.compresses(path) {
+        // Both variants, including a 304 for either, vary on the encoding
+        response.add_vary("Accept-Encoding");
+        #[cfg(any(feature = "gzip", feature = "brotli"))]
+        if let Some(encoding) = coding.filter(|_| compressed) {
+            compress_response(
+                encoding,
+                server.compression_level,
+                &mut response,
+            )?;
+        }
+    }
+    Ok(response)
+}
+
+/// Picks the content coding a file is sent with.
+///
+/// The file may be compressed with the server's enabled encodings when it is
+/// compressible, and sent unencoded as `identity` unless `Accept-Encoding` refuses
+/// it, directly or through `*;q=0`.
+///
+/// # Arguments
+///
+/// * `request` - A `Request` instance representing the client's request.
+/// * `server` - The `Server` configuration listing the enabled encodings.
+/// * `path` - The resolved path of the file to serve.
+///
+/// # Returns
+///
+/// The chosen coding, `identity` when the request has no `Accept-Encoding`, or `None`
+/// if the full file should be answered with `406 Not Acceptable`.
+fn negotiate_coding(
+    request: &Request,
+    server: &Server,
+    path: &Path,
+) -> Option<&'static str> {
+    let accept_encoding = match request.header("Accept-Encoding") {
+        Some(accept_encoding) => accept_encoding,
+        None => return Some("identity"),
+    };
+    let mut supported = Vec::new();
+    if server.compresses(path) {
+        #[cfg(any(feature = "gzip", feature = "brotli"))]
+        supported.extend(server.encodings());
+    }
+    supported.push("identity");
+    negotiate_encoding(accept_encoding, &supported)
+}
+
+/// Compresses a full response body with the negotiated encoding.
+///
+/// Only `200 OK` responses are compressed. The `ETag` is made weak because the
+/// compressed bytes differ from the file, which still lets `If-None-Match` revalidate
+/// either variant, and `Accept-Ranges: bytes` is replaced by `none` since ranges of
+/// the compressed body are not served.
+///
+/// # Arguments
+///
+/// * `encoding` - The content coding to apply, as chosen by [`negotiate_coding`].
+/// * `level` - The configured compression level, if any.
+/// * `response` - The file response to compress in place.
+///
+/// # Returns
+///
+/// A `Result` indicating success or a `ServerError` if compression fails.
+#[cfg(any(feature = "gzip", feature = "brotli"))]
+fn compress_response(
+    encoding: &str,
+    level: Option<u32>,
+    response: &mut Response,
+) -> Result<(), ServerError> {
+    if response.status_code != 200
+        || response.body.is_empty()
+        || has_header(response, "Content-Encoding")
+    {
+        return Ok(());
+    }
+
+    let compressed =
+        compression::encode(encoding, &response.body, level)?;
+    response.set_body(compressed);
+    response.add_header("Content-Encoding", encoding);
+    for (name, value) in &mut response.headers {
+        if name.eq_ignore_ascii_case("ETag") && !value.starts_with("W/")
+        {
+            value.insert_str(0, "W/");
+        } else if name.eq_ignore_ascii_case("Accept-Ranges") {
+            *value = "none".to_string();
+        }
+    }
+    Ok(())
+}
+
+/// Serves a static file, honouring `Range` and `If-Range` request headers.
+///
+/// Every file response carries an `ETag`, weak if the server is configured for it, and
+/// a `Last-Modified` date. A file not matching `If-Match`, or without it, modified
+/// after the `If-Unmodified-Since` date is answered with `412 Precondition Failed`,
+/// and one matching `If-None-Match`, or without it, not modified since the
+/// `If-Modified-Since` date, with `304 Not Modified`.
+/// A `GET` with satisfiable byte ranges is answered with `206 Partial Content`, using a
+/// `multipart/byteranges` body when several disjoint ranges remain after coalescing,
+/// and an unsatisfiable one with `416 Range Not Satisfiable`. When `If-Range` is present
+/// the ranges are only applied if its validator strongly matches the current `ETag`;
+/// otherwise the full file is returned.
+///
+/// # Arguments
+///
+/// * `request` - A `Request` instance representing the client's request.
+/// * `server` - A reference to the `Server` instance.
+/// * `path` - The resolved path of the file to serve.
+/// * `allow_ranges` - Whether `Range` is honoured; when `false` the full file is
+///   always served, e.g. because its body will be compressed.
+/// * `acceptable` - Whether the full file may be sent; when `false` a request it would
+///   answer is refused with `406 Not Acceptable` before the file is read.
+///
+/// # Returns
+///
+/// A `Result` containing the `Response` or a `ServerError`.
+fn serve_file(
+    request: &Request,
+    server: &Server,
+    path: &Path,
+    allow_ranges: bool,
+    acceptable: bool,
+) -> Result<Response, ServerError> {
+    let metadata =
+        fs::metadata(path).map_err(|e| file_error(e, path))?;
+    let etag = generate_etag(&metadata, server.weak_etags);
+    let last_modified = last_modified(&metadata);
+
+    match evaluate_preconditions(request, &etag, last_modified) {
+        Precondition::Proceed => {}
+        Precondition::NotModified => {
+            let mut response = Response::not_modified();
+            add_validators(&mut response, &etag, last_modified);
+            return Ok(response);
+        }
+        Precondition::Failed => {
+            let mut response =
+                Response::new(412, "Precondition Failed", Vec::new());
+            response.add_header("Accept-Ranges", "bytes");
+            return Ok(response);
+        }
+    }
+
+    let range_header = request.header("Range").filter(|_| {
+        allow_ranges
+            && (request.method().eq_ignore_ascii_case("GET")
+                || request.method().eq_ignore_ascii_case("HEAD"))
+            && request
+                .header("If-Range")
+                .map_or(true, |v| if_range_matches(v, &etag))
+    });
+    // Refuse before reading the file, or caching it, for nothing
+    if !acceptable
+        && range_header.map_or(true, |range| {
+            matches!(
+                evaluate_range(range, metadata.len()),
+                RangeOutcome::Full
+            )
+        })
+    {
+        return Ok(generate_406_response());
+    }
+
+    let (contents, cache) = server
+        .read_file(path, &metadata)
+        .map_err(|e| file_error(e, path))?;
+    let content_type = get_content_type(path);
+    let length = contents.len() as u64;
+
+    let range = match range_header {
+        Some(range) => evaluate_range(range, length),
+        None => RangeOutcome::Full,
+    };
+
+    let mut response = match range {
+        RangeOutcome::Full => {
+            Response::with_file_contents(path, contents)
+        }
+        RangeOutcome::Partial(start, end) => {
+            let body = contents[start as usize..=end as usize].to_vec();
+            let mut response =
+                Response::new(206, "Partial Content", body);
+            response.add_header("Content-Type", content_type);
+            response.add_header(
+                "Content-Range",
+                &format!("bytes {}-{}/{}", start, end, length),
+            );
+            response
+        }
+        RangeOutcome::Multipart(ranges) => {
+            let boundary = generate_boundary(&contents);
+            let body = multipart_byteranges(
+                &contents,
+                &ranges,
+                content_type,
+                &boundary,
+            );
+            let mut response =
+                Response::new(206, "Partial Content", body);
+            response.add_header(
+                "Content-Type",
+                &format!("multipart/byteranges; boundary={}", boundary),
+            );
+            response
+        }
+        RangeOutcome::Unsatisfiable => {
+            let mut response =
+                Response::new(416, "Range Not Satisfiable", Vec::new());
+            response.add_header(
+                "Content-Range",
+                &format!("bytes */{}", length),
+            );
+            response
+        }
+    };
+    add_validators(&mut response, &etag, last_modified);
+    response.add_header("Accept-Ranges", "bytes");
+    if let Some(cache) = cache {
+        response.add_header("X-Cache", cache.as_str());
+    }
+    Ok(response)
+}
+
+/// Returns the modification time of a file at the one-second precision of HTTP dates.
+///
+/// # Arguments
+///
+/// * `metadata` - The metadata of the file.
+///
+/// # Returns
+///
+/// The modification time, or `None` if the platform does not provide it.
+fn last_modified(metadata: &fs::Metadata) -> Option<SystemTime> {
+    let modified = metadata.modified().ok()?;
+    let seconds = modified.duration_since(UNIX_EPOCH).ok()?.as_secs();
+    Some(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+/// Adds the `ETag` and, when known, `Last-Modified` headers of a file response.
+///
+/// # Arguments
+///
+/// * `response` - The response to add the headers to.
+/// * `etag` - The entity tag of the file.
+/// * `last_modified` - The modification time of the file.
+fn add_validators(
+    response: &mut Response,
+    etag: &str,
+    last_modified: Option<SystemTime>,
+) {
+    response.add_header("ETag", etag);
+    if let Some(modified) = last_modified {
+        response
+            .add_header("Last-Modified", &format_http_date(modified));
+    }
+}
+
+/// Checks whether a resolved path points at or into a hidden file or directory.
+///
+/// A path is hidden when any component below the document root starts with a dot,
+/// such as `.git/config` or `.env`. A leading component listed in
+/// [`DOTFILE_ALLOWLIST`] (i.e. `.well-known`) is exempt, so ACME HTTP-01 challenges
+/// and other well-known URIs (RFC 8615) are still served.
+///
+/// # Arguments
+///
+/// * `path` - The resolved file system path.
+/// * `document_root` - The document root `path` was resolved against.
+///
+/// # Returns
+///
+/// `true` if the path must not be served while dotfile blocking is enabled.
+fn is_hidden_path(path: &Path, document_root: &Path) -> bool {
+    let relative = match path.strip_prefix(document_root) {
+        Ok(relative) => relative,
+        Err(_) => return false,
+    };
+
+    relative.iter().enumerate().any(|(index, component)| {
+        let component = component.to_string_lossy();
+        component.starts_with('.')
+            && !(index == 0 && DOTFILE_ALLOWLIST.contains(&&*component))
+    })
+}
+
+/// Extracts the language tag from a file name such as `index.en.html`.
+///
+/// The tag is the dot-separated segment before the extension, and only counts when it
+/// is one of the configured languages, compared case-insensitively.
+///
+/// # Arguments
+///
+/// * `path` - The path of the file.
+/// * `languages` - The language tags the site uses.
+///
+/// # Returns
+///
+/// The language tag, or `None` if the file name has none.
+fn language_tag<'a>(
+    path: &'a Path,
+    languages: &[String],
+) -> Option<&'a str> {
+    let stem = path.file_stem()?.to_str()?;
+    let (_, tag) = stem.rsplit_once('.')?;
+    if languages
+        .iter()
+        .any(|language| language.eq_ignore_ascii_case(tag))
+    {
+        Some(tag)
+    } else {
+        None
+    }
+}
+
+/// Lists the language variants of a file, such as `index.fr.html` for `index.html`.
+///
+/// Only the names built from the configured languages are checked, so this costs one
+/// lookup per language rather than a scan of the directory.
+///
+/// # Arguments
+///
+/// * `path` - The untagged path of the file, which need not exist.
+/// * `languages` - The language tags the site uses, sorted.
+///
+/// # Returns
+///
+/// The language tags and paths of the existing variants, sorted by tag.
+fn language_variants(
+    path: &Path,
+    languages: &[String],
+) -> Vec<(String, PathBuf)> {
+    let name = match path.file_name().and_then(|name| name.to_str()) {
+        Some(name) => name,
+        None => return Vec::new(),
+    };
+    languages
+        .iter()
+        .filter_map(|tag| {
+            let variant = match name.rsplit_once('.') {
+                Some((stem, extension)) => {
+                    format!("{}.{}.{}", stem, tag, extension)
+                }
+                None => format!("{}.{}", name, tag),
+            };
+            let variant = path.with_file_name(variant);
+            if variant.is_file() {
+                Some((tag.clone(), variant))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Picks the language variant to serve for a request, if the file has any.
+///
+/// The variant is chosen with [`negotiate_language`] from `Accept-Language`. Without
+/// a match, the file at `path` itself is kept if it exists, and otherwise the first
+/// variant is used.
+///
+/// # Arguments
+///
+/// * `request` - The request being answered.
+/// * `path` - The untagged path the request resolved to.
+/// * `languages` - The language tags the site uses, sorted.
+///
+/// # Returns
+///
+/// The path of the chosen variant, or `None` to serve `path` unchanged.
+fn select_language_variant(
+    request: &Request,
+    path: &Path,
+    languages: &[String],
+) -> Option<PathBuf> {
+    let mut variants = language_variants(path, languages);
+    if variants.is_empty() {
+        return None;
+    }
+
+    let tags: Vec<&str> =
+        variants.iter().map(|(tag, _)| tag.as_str()).collect();
+    let preferred = request
+        .header("Accept-Language")
+        .and_then(|header| negotiate_language(header, &tags))
+        .and_then(|tag| {
+            variants.iter().position(|(variant, _)| *variant == tag)
+        });
+    match preferred {
+        Some(index) => Some(variants.swap_remove(index).1),
+        None if path.is_file() => None,
+        None => Some(variants.swap_remove(0).1),
+    }
+}
+
+/// Recursively collects the servable files below a directory.
+///
+/// # Arguments
+///
+/// * `document_root` - The document root that returned paths are relative to.
+/// * `directory` - The directory to walk.
+/// * `block_dotfiles` - Whether hidden files and directories are skipped.
+/// * `visited` - The canonical paths of the directories already walked.
+/// * `files` - The list the relative file paths are appended to.
+///
+/// # Returns
+///
+/// A `Result` indicating success or a `ServerError` if a directory cannot be read.
+fn collect_files(
+    document_root: &Path,
+    directory: &Path,
+    block_dotfiles: bool,
+    visited: &mut BTreeSet<PathBuf>,
+    files: &mut Vec<PathBuf>,
+) -> Result<(), ServerError> {
+    // A directory reached again through a symbolic link ends the walk there
+    if !visited.insert(fs::canonicalize(directory)?) {
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(directory)? {
+        let path = entry?.path();
+        if block_dotfiles && is_hidden_path(&path, document_root) {
+            continue;
+        }
+
+        // Follows symbolic links; dangling ones are skipped
+        let metadata = match fs::metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(_) => continue,
+        };
+        if metadata.is_dir() {
+            collect_files(
+                document_root,
+                &path,
+                block_dotfiles,
+                visited,
+                files,
+            )?;
+        } else if metadata.is_file() {
+            if let Ok(relative) = path.strip_prefix(document_root) {
+                files.push(relative.to_path_buf());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Outcome of evaluating a `Range` header against a representation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum RangeOutcome {
+    /// The header is absent, ignored or unsupported: send the full representation.
+    Full,
+    /// A single satisfiable range, as inclusive start and end offsets.
+    Partial(u64, u64),
+    /// Several disjoint ranges in ascending order, sent as `multipart/byteranges`.
+    Multipart(Vec<(u64, u64)>),
+    /// The range cannot be satisfied for the representation's length.
+    Unsatisfiable,
+}
+
+/// Evaluates a `Range` header value against a representation of `length` bytes.
+///
+/// Overlapping and adjacent ranges are coalesced, so the outcome is either a single
+/// range or several disjoint ones. Ranges that start past the end are dropped, and the
+/// request is unsatisfiable only if none remain. Other units, syntactically invalid
+/// values and headers with more than [`MAX_RANGES`] ranges are ignored, as permitted by
+/// RFC 7233.
+///
+/// # Arguments
+///
+/// * `header` - The `Range` header value (e.g., "bytes=0-499, 1000-1499").
+/// * `length` - The length of the full representation in bytes.
+///
+/// # Returns
+///
+/// The `RangeOutcome` describing how to answer the request.
+fn evaluate_range(header: &str, length: u64) -> RangeOutcome {
+    let specs = match header.trim().strip_prefix("bytes=") {
+        Some(specs) => specs.split(',').collect::<Vec<_>>(),
+        None => return RangeOutcome::Full,
+    };
+    if specs.len() > MAX_RANGES {
+        return RangeOutcome::Full;
+    }
+
+    let mut ranges = Vec::with_capacity(specs.len());
+    for spec in specs {
+        match parse_byte_range(spec.trim(), length) {
+            Some(Some(range)) => ranges.push(range),
+            Some(None) => {}
+            None => return RangeOutcome::Full,
+        }
+    }
+
+    ranges.sort_unstable();
+    let mut coalesced: Vec<(u64, u64)> =
+        Vec::with_capacity(ranges.len());
+    for (start, end) in ranges {
+        match coalesced.last_mut() {
+            Some(last) if start <= last.1.saturating_add(1) => {
+                last.1 = last.1.max(end);
+            }
+            _ => coalesced.push((start, end)),
+        }
+    }
+
+    match coalesced.len() {
+        0 => RangeOutcome::Unsatisfiable,
+        1 => RangeOutcome::Partial(coalesced[0].0, coalesced[0].1),
+        _ => RangeOutcome::Multipart(coalesced),
+    }
+}
+
+/// Parses a single byte range specification such as `0-499`, `500-` or `-200`.
+///
+/// # Arguments
+///
+/// * `spec` - One comma-separated element of a `Range` header.
+/// * `length` - The length of the full representation in bytes.
+///
+/// # Returns
+///
+/// `None` if the specification is invalid, `Some(None)` if it cannot be satisfied, or
+/// the inclusive start and end offsets, clamped to the representation.
+fn parse_byte_range(
+    spec: &str,
+    length: u64,
+) -> Option<Option<(u64, u64)>> {
+    let (start, end) = spec.split_once('-')?;
+
+    if start.is_empty() {
+        // Suffix range: the last `end` bytes
+        let suffix = end.parse::<u64>().ok()?;
+        return Some(if suffix == 0 || length == 0 {
+            None
+        } else {
+            Some((length.saturating_sub(suffix), length - 1))
+        });
+    }
+
+    let start = start.parse::<u64>().ok()?;
+    let end = if end.is_empty() {
+        u64::MAX
+    } else {
+        match end.parse::<u64>() {
+            Ok(end) if end >= start => end,
+            _ => return None,
+        }
+    };
+
+    Some(if start >= length {
+        None
+    } else {
+        Some((start, end.min(length - 1)))
+    })
+}
+
+/// Builds a `multipart/byteranges` body for several ranges of a representation.
+///
+/// Each part carries its own `Content-Type` and `Content-Range` headers, as described
+/// in RFC 7233 appendix A.
+///
+/// # Arguments
+///
+/// * `contents` - The full representation.
+/// * `ranges` - The disjoint, inclusive ranges to include.
+/// * `content_type` - The media type of the representation.
+/// * `boundary` - The multipart boundary, which must not occur in the parts, as
+///   ensured by [`generate_boundary`].
+///
+/// # Returns
+///
+/// The multipart body.
+fn multipart_byteranges(
+    contents: &[u8],
+    ranges: &[(u64, u64)],
+    content_type: &str,
+    boundary: &str,
+) -> Vec<u8> {
+    let length = contents.len();
+    let mut body = Vec::new();
+    for &(start, end) in ranges {
+        body.extend_from_slice(
+            format!(
+                "--{}\r\nContent-Type: {}\r\nContent-Range: bytes {}-{}/{}\r\n\r\n",
+                boundary, content_type, start, end, length
+            )
+            .as_bytes(),
+        );
+        body.extend_from_slice(
+            &contents[start as usize..=end as usize],
+        );
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+    body
+}
+
+/// Generates a multipart boundary that is unpredictable to clients.
+///
+/// A boundary found in the representation would end a part early, so a new one is
+/// generated until it does not occur in `contents`.
+///
+/// # Arguments
+///
+/// * `contents` - The representation the parts are taken from.
+///
+/// # Returns
+///
+/// A boundary string made of hexadecimal digits.
+fn generate_boundary(contents: &[u8]) -> String {
+    loop {
+        // `RandomState` keys are random once per thread and then incremented for each
+        // instance, which keeps boundaries unpredictable and distinct from each other
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u128(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |duration| duration.as_nanos()),
+        );
+        let boundary = format!("http-handle-{:016x}", hasher.finish());
+        if !contents
+            .windows(boundary.len())
+            .any(|window| window == boundary.as_bytes())
+        {
+            return boundary;
+        }
+    }
+}
+
+/// The outcome of evaluating the conditional headers of a request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Precondition {
+    /// The conditions hold; the request is served normally.
+    Proceed,
+    /// The client's copy is current; answer `304 Not Modified`.
+    NotModified,
+    /// A condition failed; answer `412 Precondition Failed`.
+    Failed,
+}
+
+/// Evaluates the conditional headers of a request in the order of RFC 7232 section 6.
+///
+/// The precedence matters when several headers are present:
+///
+/// 1. `If-Match` fails the request unless it is `*` or lists the current entity tag.
+/// 2. Only when `If-Match` is absent, `If-Unmodified-Since` fails the request if the
+///    file changed after its date.
+/// 3. `If-None-Match` then decides on its own: a match means `304 Not Modified` for
+///    `GET` and `HEAD`, and `412 Precondition Failed` for other methods.
+/// 4. Only when `If-None-Match` is absent, `If-Modified-Since` answers `GET` and `HEAD`
+///    with `304 Not Modified` if the file has not changed since its date.
+///
+/// Invalid dates are ignored, as are date conditions for files without a modification
+/// time. `If-Range` is evaluated later, when the range is applied.
+///
+/// # Arguments
+///
+/// * `request` - The request carrying the conditional headers.
+/// * `etag` - The current entity tag of the file.
+/// * `last_modified` - The modification time of the file, in whole seconds.
+///
+/// # Returns
+///
+/// What to do with the request.
+fn evaluate_preconditions(
+    request: &Request,
+    etag: &str,
+    last_modified: Option<SystemTime>,
+) -> Precondition {
+    let date_header = |name: &str| {
+        last_modified.and_then(|modified| {
+            request
+                .header(name)
+                .and_then(parse_http_date)
+                .map(|date| (modified, date))
+        })
+    };
+    let is_get_or_head = request.method().eq_ignore_ascii_case("GET")
+        || request.method().eq_ignore_ascii_case("HEAD");
+
+    if let Some(value) = request.header("If-Match") {
+        if !if_match_matches(value, etag) {
+            return Precondition::Failed;
+        }
+    } else if let Some((modified, date)) =
+        date_header("If-Unmodified-Since")
+    {
+        if modified > date {
+            return Precondition::Failed;
+        }
+    }
+
+    if let Some(value) = request.header("If-None-Match") {
+        return match (
+            if_none_match_matches(value, etag),
+            is_get_or_head,
+        ) {
+            (false, _) => Precondition::Proceed,
+            (true, true) => Precondition::NotModified,
+            (true, false) => Precondition::Failed,
+        };
+    }
+
+    match date_header("If-Modified-Since") {
+        Some((modified, date))
+            if is_get_or_head && modified <= date =>
+        {
+            Precondition::NotModified
+        }
+        _ => Precondition::Proceed,
+    }
+}
+
+/// Checks whether an `If-Range` validator matches the current entity tag.
+///
+/// Only entity tags are supported; a date validator never matches, which safely falls
+/// back to sending the full representation. The comparison is strong, so it fails
+/// whenever either the validator or the current tag is weak.
+///
+/// # Arguments
+///
+/// * `value` - The `If-Range` header value.
+/// * `etag` - The current entity tag of the file.
+///
+/// # Returns
+///
+/// `true` if the requested range may be served, `false` otherwise.
+fn if_range_matches(value: &str, etag: &str) -> bool {
+    let value = value.trim();
+    !value.starts_with("W/") && !etag.starts_with("W/") && value == etag
+}
+
+/// Checks whether an `If-Match` header matches the current entity tag.
+///
+/// The header holds either `*`, which matches any existing representation, or a
+/// comma-separated list of entity tags, compared with the strong comparison function
+/// of RFC 7232: a weak tag on either side never matches.
+///
+/// # Arguments
+///
+/// * `value` - The `If-Match` header value.
+/// * `etag` - The current entity tag of the file.
+///
+/// # Returns
+///
+/// `true` if the request may proceed, `false` if `412 Precondition Failed` should be
+/// sent.
+fn if_match_matches(value: &str, etag: &str) -> bool {
+    value.trim() == "*"
+        || value.split(',').any(|tag| if_range_matches(tag, etag))
+}
+
+/// Checks whether an `If-None-Match` header matches the current entity tag.
+///
+/// The header holds either `*`, which matches any existing representation, or a
+/// comma-separated list of entity tags, compared with the weak comparison function of
+/// RFC 7232: a `W/` prefix on either side is ignored.
+///
+/// # Arguments
+///
+/// * `value` - The `If-None-Match` header value.
+/// * `etag` - The current entity tag of the file.
+///
+/// # Returns
+///
+/// `true` if one of the listed tags matches, so `304 Not Modified` should be sent.
+fn if_none_match_matches(value: &str, etag: &str) -> bool {
+    if value.trim() == "*" {
+        return true;
+    }
+    let opaque =
+        |tag: &str| tag.trim().trim_start_matches("W/").to_string();
+    let current = opaque(etag);
+    value.split(',').any(|tag| opaque(tag) == current)
+}
+
+/// Generates an entity tag from a file's size and modification time.
+///
+/// # Arguments
+///
+/// * `metadata` - The metadata of the file being served.
+/// * `weak` - Whether to mark the tag as weak with a `W/` prefix.
+///
+/// # Returns
+///
+/// A quoted entity tag (e.g., `"1a2b-5f3c9e10"` or `W/"1a2b-5f3c9e10"`).
+fn generate_etag(metadata: &fs::Metadata, weak: bool) -> String {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |duration| duration.as_nanos());
+    format!(
+        "{}\"{:x}-{:x}\"",
+        if weak { "W/" } else { "" },
+        metadata.len(),
+        modified
+    )
+}
+
+/// Builds the slashed location a directory request should be redirected to.
+///
+/// Any query string is preserved after the added slash.
+///
+/// # Arguments
+///
+/// * `target` - The request target as received (e.g., "/docs?lang=en").
+///
+/// # Returns
+///
+/// The target with a trailing slash appended to its path (e.g., "/docs/?lang=en").
+fn directory_location(target: &str) -> String {
+    match target.split_once('?') {
+        Some((path, query)) => format!("{}/?{}", path, query),
+        None => format!("{}/", target),
+    }
+}
+
+/// Builds the `X-Accel-Redirect` response handing a static file to the proxy.
+///
+/// # Arguments
+///
+/// * `prefix` - The URI prefix of the proxy's internal location.
+/// * `path` - The resolved file, inside `document_root`.
+/// * `document_root` - The document root the file was found in.
+///
+/// # Returns
+///
+/// A `Response` pointing the proxy at `prefix` joined with the file's relative path.
+fn accel_redirect_response(
+    prefix: &str,
+    path: &Path,
+    document_root: &Path,
+) -> Response {
+    let relative = path
+        .strip_prefix(document_root)
+        .unwrap_or(path)
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+    Response::accel_redirect(&format!("{}/{}", prefix, relative))
+}
+
+/// Removes the port, if any, from a `Host` header value.
+///
+/// # Arguments
+///
+/// * `host` - The `Host` header value (e.g., "example.com:8080" or "[::1]:8080").
+///
+/// # Returns
+///
+/// The host name or IP literal without the port.
+fn strip_port(host: &str) -> &str {
+    if host.starts_with('[') {
+        // IPv6 literal: keep everything up to the closing bracket
+        match host.find(']') {
+            Some(end) => &host[..=end],
+            None => host,
+        }
+    } else {
+        host.split(':').next().unwrap_or(host)
+    }
+}
+
+/// Generates a 404 Not Found response.
+///
+/// # Arguments
+///
+/// * `document_root` - A `Path` representing the server's document root.
+///
+/// # Returns
+///
+/// A `Result` containing the `Response` or a `ServerError`.
+fn generate_404_response(
+    document_root: &Path,
+) -> Result<Response, ServerError> {
+    let not_found_path = document_root.join("404/index.html");
+    if !not_found_path.is_file() {
+        let mut response =
+            Response::new(404, "NOT FOUND", b"404 Not Found".to_vec());
+        response.add_header("Content-Type", "text/html; charset=utf-8");
+        return Ok(response);
+    }
+    let contents = fs::read(&not_found_path)
+        .map_err(|e| file_error(e, &not_found_path))?;
+    let mut response =
+        Response::with_file_contents(&not_found_path, contents);
+    response.status_code = 404;
+    response.status_text = "NOT FOUND".to_string();
+    Ok(response)
+}
+
+/// Generates a 406 Not Acceptable response for a refused content coding.
+///
+/// # Returns
+///
+/// A `Response` with status 406 and a plain-text body, varying on `Accept-Encoding`.
+fn generate_406_response() -> Response {
+    let mut response = Response::new(
+        406,
+        "Not Acceptable",
+        b"406 Not Acceptable".to_vec(),
+    );
+    response.add_header("Content-Type", "text/plain");
+    response.add_vary("Accept-Encoding");
+    response
+}
+
+/// Returns the default `Server` header value, naming this crate and its version.
+fn default_server_header() -> String {
+    format!("http-handle/{}", env!("CARGO_PKG_VERSION"))
+}
+
+/// Checks whether a response already carries a header.
+///
+/// # Arguments
+///
+/// * `response` - The response to inspect.
+/// * `name` - The header name, compared case-insensitively.
+///
+/// # Returns
+///
+/// `true` if the header is present.
+fn has_header(response: &Response, name: &str) -> bool {
+    response
+        .headers
+        .iter()
+        .any(|(header, _)| header.eq_ignore_ascii_case(name))
+}
+
+/// Generates a 503 Service Unavailable response for connections over the limit.
+///
+/// # Returns
+///
+/// A `Response` with status 503 and a plain-text body.
+fn generate_503_response() -> Response {
+    let mut response = Response::new(
+        503,
+        "Service Unavailable",
+        b"503 Service Unavailable".to_vec(),
+    );
+    response.add_header("Content-Type", "text/plain");
+    response.add_header("Retry-After", "1");
+    response
+}
+
+/// Generates the response sent to the client when request handling fails.
+///
+/// The body only contains the status line text so that internal details, such as
+/// filesystem paths, are never disclosed.
+///
+/// # Arguments
+///
+/// * `error` - The `ServerError` raised while handling the request.
+///
+/// # Returns
+///
+/// A `Response` with the status code mapped from the error.
+pub(crate) fn generate_error_response(error: &ServerError) -> Response {
+    let status_code = error.status_code();
+    let status_text = reason_phrase(status_code);
+    let body = format!("{} {}", status_code, status_text);
+    let mut response =
+        Response::new(status_code, status_text, body.into_bytes());
+    response.add_header("Content-Type", "text/plain");
+    response
+}
+
+/// Generates an error response explaining the error as JSON, for debugging clients.
+///
+/// The message of errors that may carry file system paths or internal details (file,
+/// permission, I/O and custom errors) is replaced by the reason phrase.
+///
+/// # Arguments
+///
+/// * `error` - The `ServerError` raised while handling the request.
+///
+/// # Returns
+///
+/// A `Response` with the status code mapped from the error and a JSON body.
+fn generate_debug_error_response(error: &ServerError) -> Response {
+    let status_code = error.status_code();
+    let status_text = reason_phrase(status_code);
+    let (kind, message) = match error {
+        ServerError::InvalidRequest(message) => {
+            ("invalid_request", message.as_str())
+        }
+        ServerError::NotImplemented(message) => {
+            ("not_implemented", message.as_str())
+        }
+        ServerError::PayloadTooLarge(message) => {
+            ("payload_too_large", message.as_str())
+        }
+        ServerError::UriTooLong(message) => {
+            ("uri_too_long", message.as_str())
+        }
+        ServerError::Timeout(message) => ("timeout", message.as_str()),
+        ServerError::ExpectationFailed(message) => {
+            ("expectation_failed", message.as_str())
+        }
+        ServerError::NotFound(_) => ("not_found", status_text),
+        ServerError::Forbidden(_) => ("forbidden", status_text),
+        ServerError::Io(_) => ("io", status_text),
+        ServerError::Custom(_) => ("custom", status_text),
+    };
+    let body = serde_json::json!({
+        "status": status_code,
+        "kind": kind,
+        "message": message,
+    });
+    let mut response = Response::new(
+        status_code,
+        status_text,
+        body.to_string().into_bytes(),
+    );
+    response.add_header("Content-Type", "application/json");
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Write;
+    use tempfile::TempDir;
+
+    fn setup_test_directory() -> TempDir {
+        let temp_dir = TempDir::new().unwrap();
+        let root_path = temp_dir.path();
+
+        // Create index.html
+        let mut index_file =
+            File::create(root_path.join("index.html")).unwrap();
+        index_file
+            .write_all(b"<html><body>Hello, World!</body></html>")
+            .unwrap();
+
+        // Create 404/index.html
+        fs::create_dir(root_path.join("404")).unwrap();
+        let mut not_found_file =
+            File::create(root_path.join("404/index.html")).unwrap();
+        not_found_file
+            .write_all(b"<html><body>404 Not Found</body></html>")
+            .unwrap();
+
+        // Create a subdirectory with its own index.html
+        fs::create_dir(root_path.join("subdir")).unwrap();
+        let mut subdir_index_file =
+            File::create(root_path.join("subdir/index.html")).unwrap();
+        subdir_index_file
+            .write_all(b"<html><body>Subdirectory Index</body></html>")
+            .unwrap();
+
+        temp_dir
+    }
+
+    #[test]
+    fn test_server_creation() {
+        let server = Server::new("127.0.0.1:8080", "/var/www");
+        assert_eq!(server.address, "127.0.0.1:8080");
+        assert_eq!(server.document_root, PathBuf::from("/var/www"));
+    }
+
+    #[test]
+    fn test_server_deserializes_with_defaults() {
+        let temp_dir = setup_test_directory();
+        let json = serde_json::json!({
+            "address": "127.0.0.1:0",
+            "document_root": temp_dir.path(),
+        });
+        let server: Server = serde_json::from_value(json).unwrap();
+        assert_eq!(server, test_server(temp_dir.path()));
+        assert_eq!(Server::default(), Server::new("", ""));
+
+        let response =
+            roundtrip(&server, b"GET /index.html HTTP/1.1\r\n\r\n");
+        let response = String::from_utf8_lossy(&response);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("Hello, World!</body></html>"));
+
+        // A serialized server loads back unchanged
+        let json = serde_json::to_string(&server).unwrap();
+        assert_eq!(
+            serde_json::from_str::<Server>(&json).unwrap(),
+            server
+        );
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn test_tls_server_does_not_serialize() {
+        let (config, _) = tls_test_config();
+        let server = Server::with_tls_config(
+            "127.0.0.1:0",
+            ".",
+            Arc::new(config),
+        );
+        let error = serde_json::to_string(&server).unwrap_err();
+        assert!(error.to_string().contains("TLS"), "{}", error);
+    }
+
+    fn test_server(document_root: &Path) -> Server {
+        Server::new("127.0.0.1:0", document_root.to_str().unwrap())
+    }
+
+    #[test]
+    fn test_generate_response() {
+        let temp_dir = setup_test_directory();
+        let server = test_server(temp_dir.path());
+
+        // Test root request (should serve index.html)
+        let root_request = Request {
+            method: "GET".to_string(),
+            path: "/".to_string(),
+            version: "HTTP/1.1".to_string(),
+            headers: Vec::new(),
+            raw_line: String::new(),
+            body: Vec::new(),
+        };
+
+        let root_response =
+            generate_response(&root_request, &server).unwrap();
+        assert_eq!(root_response.status_code, 200);
+        assert_eq!(root_response.status_text, "OK");
+        assert!(root_response
+            .body
+            .starts_with(b"<html><body>Hello, World!</body></html>"));
+
+        // Test specific file request
+        let file_request = Request {
+            method: "GET".to_string(),
+            path: "/index.html".to_string(),
+            version: "HTTP/1.1".to_string(),
+            headers: Vec::new(),
+            raw_line: String::new(),
+            body: Vec::new(),
+        };
+
+        let file_response =
+            generate_response(&file_request, &server).unwrap();
+        assert_eq!(file_response.status_code, 200);
+        assert_eq!(file_response.status_text, "OK");
+        assert!(file_response
+            .body
+            .starts_with(b"<html><body>Hello, World!</body></html>"));
+
+        // Test subdirectory index request
+        let subdir_request = Request {
+            method: "GET".to_string(),
+            path: "/subdir/".to_string(),
+            version: "HTTP/1.1".to_string(),
+            headers: Vec::new(),
+            raw_line: String::new(),
+            body: Vec::new(),
+        };
+
+        let subdir_response =
+            generate_response(&subdir_request, &server).unwrap();
+        assert_eq!(subdir_response.status_code, 200);
+        assert_eq!(subdir_response.status_text, "OK");
+        assert!(subdir_response.body.starts_with(
+            b"<html><body>Subdirectory Index</body></html>"
+        ));
+
+        // Test non-existent file request
+        let not_found_request = Request {
+            method: "GET".to_string(),
+            path: "/nonexistent.html".to_string(),
+            version: "HTTP/1.1".to_string(),
+            headers: Vec::new(),
+            raw_line: String::new(),
+            body: Vec::new(),
+        };
+
+        let not_found_response =
+            generate_response(&not_found_request, &server).unwrap();
+        assert_eq!(not_found_response.status_code, 404);
+        assert_eq!(not_found_response.status_text, "NOT FOUND");
+        assert!(not_found_response
+            .body
+            .starts_with(b"<html><body>404 Not Found</body></html>"));
+
+        // Test directory traversal attempt
+        let traversal_request = Request {
+            method: "GET".to_string(),
+            path: "/../outside.html".to_string(),
+            version: "HTTP/1.1".to_string(),
+            headers: Vec::new(),
+            raw_line: String::new(),
+            body: Vec::new(),
+        };
+
+        let traversal_response =
+            generate_response(&traversal_request, &server);
+        assert!(matches!(
+            traversal_response,
+            Err(ServerError::Forbidden(_))
+        ));
+    }
+
+    #[test]
+    fn test_require_host() {
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+        server.set_require_host(true);
+
+        let without_host = Request {
+            method: "GET".to_string(),
+            path: "/".to_string(),
+            version: "HTTP/1.1".to_string(),
+            headers: Vec::new(),
+            raw_line: String::new(),
+            body: Vec::new(),
+        };
+        let response =
+            generate_response(&without_host, &server).unwrap();
+        assert_eq!(response.status_code, 400);
+        assert_eq!(response.body, b"400 Bad Request");
+
+        // Debug mode explains the rejection like any other error
+        server.set_debug_errors(true);
+        let response =
+            generate_response(&without_host, &server).unwrap();
+        assert_eq!(response.status_code, 400);
+        assert_eq!(
+            header_value(&response, "Content-Type"),
+            Some("application/json")
+        );
+        let details: serde_json::Value =
+            serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(details["kind"], "invalid_request");
+        assert_eq!(
+            details["message"],
+            "Missing or invalid Host header"
+        );
+        server.set_debug_errors(false);
+
+        let with_host = Request {
+            headers: vec![(
+                "Host".to_string(),
+                "localhost".to_string(),
+            )],
+            ..without_host.clone()
+        };
+        let response = generate_response(&with_host, &server).unwrap();
+        assert_eq!(response.status_code, 200);
+
+        // HTTP/1.0 requests are not required to send a Host header
+        let http10 = Request {
+            version: "HTTP/1.0".to_string(),
+            ..without_host
+        };
+        let response = generate_response(&http10, &server).unwrap();
+        assert_eq!(response.status_code, 200);
+    }
+
+    #[test]
+    fn test_virtual_hosts() {
+        let temp_dir = setup_test_directory();
+        let vhost_dir = TempDir::new().unwrap();
+        fs::write(
+            vhost_dir.path().join("index.html"),
+            b"<html><body>Virtual Host</body></html>",
+        )
+        .unwrap();
+
+        let mut server = test_server(temp_dir.path());
+        server.add_virtual_host(
+            "A.Example.com",
+            vhost_dir.path().to_path_buf(),
+        );
+
+        // Known host, different case and with a port
+        let known = Request {
+            method: "GET".to_string(),
+            path: "/".to_string(),
+            version: "HTTP/1.1".to_string(),
+            headers: vec![(
+                "Host".to_string(),
+                "a.example.COM:8080".to_string(),
+            )],
+            raw_line: String::new(),
+            body: Vec::new(),
+        };
+        let response = generate_response(&known, &server).unwrap();
+        assert_eq!(response.status_code, 200);
+        assert!(response
+            .body
+            .starts_with(b"<html><body>Virtual Host</body></html>"));
+
+        // Unknown host falls back to the default document root
+        let unknown = Request {
+            headers: vec![(
+                "Host".to_string(),
+                "other.example.com".to_string(),
+            )],
+            ..known
+        };
+        let response = generate_response(&unknown, &server).unwrap();
+        assert_eq!(response.status_code, 200);
+        assert!(response
+            .body
+            .starts_with(b"<html><body>Hello, World!</body></html>"));
+    }
+
+    #[test]
+    fn test_multiple_document_roots() {
+        let overrides = setup_test_directory();
+        let base = TempDir::new().unwrap();
+        fs::write(base.path().join("index.html"), b"base index")
+            .unwrap();
+        fs::write(base.path().join("theme.css"), b"base theme")
+            .unwrap();
+        fs::create_dir(base.path().join("secret")).unwrap();
+        fs::write(base.path().join("secret/key.txt"), b"key").unwrap();
+
+        let mut server = test_server(overrides.path());
+        server.add_document_root(base.path().to_path_buf());
+
+        // Overridden file comes from the first root
+        let response = generate_response(
+            &get_request("/index.html", &[]),
+            &server,
+        )
+        .unwrap();
+        assert!(response
+            .body
+            .starts_with(b"<html><body>Hello, World!</body></html>"));
+
+        // Base-only file comes from the fallback root
+        let response =
+            generate_response(&get_request("/theme.css", &[]), &server)
+                .unwrap();
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.body, b"base theme");
+
+        // Missing everywhere: the first root's 404 page
+        let response =
+            generate_response(&get_request("/missing", &[]), &server)
+                .unwrap();
+        assert_eq!(response.status_code, 404);
+        assert!(response.body.starts_with(b"<html><body>404"));
+
+        // Traversal out of each root is still refused
+        let escape = format!(
+            "/../{}/secret/key.txt",
+            base.path().file_name().unwrap().to_str().unwrap()
+        );
+        assert!(matches!(
+            generate_response(&get_request(&escape, &[]), &server),
+            Err(ServerError::Forbidden(_))
+        ));
+
+        assert_eq!(
+            server.list_files().unwrap(),
+            [
+                "404/index.html",
+                "index.html",
+                "secret/key.txt",
+                "subdir/index.html",
+                "theme.css"
+            ]
+            .iter()
+            .map(PathBuf::from)
+            .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_interceptors() {
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+        server.set_request_interceptor(|request: &mut Request| {
+            if request.header("X-Request-Id").is_none() {
+                request.add_header("X-Request-Id", "generated-1");
+            }
+        });
+        server.set_response_interceptor(
+            |request: &Request, response: &mut Response| {
+                if let Some(id) = request.header("X-Request-Id") {
+                    response.add_header("X-Request-Id", id);
+                }
+                if response.status_code == 404 {
+                    response.set_body(b"gone".to_vec());
+                }
+            },
+        );
+
+        let raw = String::from_utf8(roundtrip(
+            &server,
+            b"GET /index.html HTTP/1.1\r\n\r\n\
+              GET /index.html HTTP/1.1\r\nX-Request-Id: abc\r\n\r\n\
+              GET /missing HTTP/1.1\r\n\r\n",
+        ))
+        .unwrap();
+        let responses: Vec<&str> =
+            raw.split("HTTP/1.1 ").skip(1).collect();
+        assert_eq!(responses.len(), 3, "{}", raw);
+        assert!(responses[0].contains("X-Request-Id: generated-1\r\n"));
+        assert!(responses[0]
+            .ends_with("<html><body>Hello, World!</body></html>"));
+        assert!(responses[1].contains("X-Request-Id: abc\r\n"));
+        assert!(responses[2].starts_with("404 "));
+        assert!(responses[2].contains("Content-Length: 4\r\n"));
+        assert!(responses[2].ends_with("\r\n\r\ngone"));
+    }
+
+    #[test]
+    fn test_middleware_chain() {
+        /// Records each request and the status it was answered with.
+        struct Logging(Arc<Mutex<Vec<String>>>);
+
+        impl Middleware for Logging {
+            fn handle(
+                &self,
+                request: &Request,
+                next: Next<'_>,
+            ) -> Response {
+                let response = next.run(request);
+                self.0.lock().unwrap().push(format!(
+                    "{} {}",
+                    request.path(),
+                    response.status_code
+                ));
+                response
+            }
+        }
+
+        /// Refuses requests without the expected token.
+        struct Auth;
+
+        impl Middleware for Auth {
+            fn handle(
+                &self,
+                request: &Request,
+                next: Next<'_>,
+            ) -> Response {
+                if request.header("Authorization")
+                    == Some("Bearer t0k3n")
+                {
+                    next.run(request)
+                } else {
+                    let mut response =
+                        Response::text(401, "Unauthorized");
+                    response.add_header("WWW-Authenticate", "Bearer");
+                    response
+                }
+            }
+        }
+
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+        let log = Arc::new(Mutex::new(Vec::new()));
+        server.add_middleware(Box::new(Logging(Arc::clone(&log))));
+        server.add_middleware(Box::new(Auth));
+
+        let raw = String::from_utf8(roundtrip(
+            &server,
+            b"GET /index.html HTTP/1.1\r\n\r\n\
+              GET /index.html HTTP/1.1\r\nAuthorization: Bearer t0k3n\r\n\r\n",
+        ))
+        .unwrap();
+        let responses: Vec<&str> =
+            raw.split("HTTP/1.1 ").skip(1).collect();
+        assert_eq!(responses.len(), 2, "{}", raw);
+        assert!(responses[0].starts_with("401 Unauthorized\r\n"));
+        assert!(responses[0].contains("WWW-Authenticate: Bearer\r\n"));
+        assert!(responses[1].starts_with("200 OK\r\n"));
+        assert!(responses[1]
+            .ends_with("<html><body>Hello, World!</body></html>"));
+
+        // The outer logging middleware saw both, blocked or not
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["/index.html 401", "/index.html 200"]
+        );
+    }
+
+    #[test]
+    fn test_debug_errors() {
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+        let error = ServerError::invalid_request(
+            "Invalid HTTP version: HTTP/9.9",
+        );
+
+        // Terse by default
+        let response = server.error_response(&error);
+        assert_eq!(response.status_code, 400);
+        assert_eq!(
+            header_value(&response, "Content-Type"),
+            Some("text/plain")
+        );
+        assert_eq!(response.body, b"400 Bad Request");
+
+        server.set_debug_errors(true);
+        let response = server.error_response(&error);
+        assert_eq!(response.status_code, 400);
+        assert_eq!(
+            header_value(&response, "Content-Type"),
+            Some("application/json")
+        );
+        let details: serde_json::Value =
+            serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(details["status"], 400);
+        assert_eq!(details["kind"], "invalid_request");
+        assert_eq!(
+            details["message"],
+            "Invalid HTTP version: HTTP/9.9"
+        );
+
+        // File errors never disclose server paths
+        let path = temp_dir.path().join("secret/file.html");
+        for error in [
+            file_error(io::ErrorKind::NotFound.into(), &path),
+            file_error(io::ErrorKind::PermissionDenied.into(), &path),
+        ] {
+            let response = server.error_response(&error);
+            let body = String::from_utf8(response.body).unwrap();
+            assert!(!body.contains("secret"), "{}", body);
+            assert!(
+                !body.contains(&*temp_dir.path().to_string_lossy()),
+                "{}",
+                body
+            );
+        }
+        let response = server.error_response(&file_error(
+            io::ErrorKind::NotFound.into(),
+            &path,
+        ));
+        assert_eq!(
+            response.body,
+            br#"{"kind":"not_found","message":"Not Found","status":404}"#
+        );
+    }
+
+    #[test]
+    fn test_health_check() {
+        /// Refuses every request.
+        struct DenyAll;
+
+        impl Middleware for DenyAll {
+            fn handle(&self, _: &Request, _: Next<'_>) -> Response {
+                Response::text(401, "Unauthorized")
+            }
+        }
+
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+        let raw = b"GET /healthz HTTP/1.1\r\n\r\n";
+        let response =
+            String::from_utf8(roundtrip(&server, raw)).unwrap();
+        assert!(response.starts_with("HTTP/1.1 404 "));
+
+        server.enable_health_check("/healthz");
+        server.add_middleware(Box::new(DenyAll));
+        let response =
+            String::from_utf8(roundtrip(&server, raw)).unwrap();
+        assert!(
+            response.starts_with("HTTP/1.1 200 OK\r\n"),
+            "{}",
+            response
+        );
+        assert!(response.ends_with("\r\n\r\nok"));
+
+        // Probes busting caches with a query string are answered too
+        let response = String::from_utf8(roundtrip(
+            &server,
+            b"GET /healthz?probe=1 HTTP/1.1\r\n\r\n",
+        ))
+        .unwrap();
+        assert!(
+            response.starts_with("HTTP/1.1 200 OK\r\n"),
+            "{}",
+            response
+        );
+
+        let response = String::from_utf8(roundtrip(
+            &server,
+            b"HEAD /healthz HTTP/1.1\r\n\r\n",
+        ))
+        .unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("Content-Length: 2\r\n"));
+        assert!(response.ends_with("\r\n\r\n"));
+
+        // Everything else still goes through the middleware
+        let response = String::from_utf8(roundtrip(
+            &server,
+            b"GET /index.html HTTP/1.1\r\n\r\n",
+        ))
+        .unwrap();
+        assert!(response.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+
+        // A HEAD answered by middleware gets the headers without the body
+        let response = String::from_utf8(roundtrip(
+            &server,
+            b"HEAD /index.html HTTP/1.1\r\n\r\n",
+        ))
+        .unwrap();
+        assert!(response.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+        assert!(response.contains("Content-Length: 12\r\n"));
+        assert!(response.ends_with("\r\n\r\n"), "{}", response);
+    }
+
+    #[test]
+    fn test_accel_redirect() {
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+        server.set_accel_redirect(Some("/protected/"));
+
+        let response =
+            generate_response(&get_request("/subdir/", &[]), &server)
+                .unwrap();
+        assert_eq!(response.status_code, 200);
+        assert!(response.body.is_empty());
+        assert_eq!(
+            header_value(&response, "X-Accel-Redirect"),
+            Some("/protected/subdir/index.html")
+        );
+
+        // Missing files are still answered by the server
+        let response =
+            generate_response(&get_request("/missing", &[]), &server)
+                .unwrap();
+        assert_eq!(response.status_code, 404);
+        assert_eq!(header_value(&response, "X-Accel-Redirect"), None);
+
+        server.set_accel_redirect(None);
+        let response = generate_response(
+            &get_request("/index.html", &[]),
+            &server,
+        )
+        .unwrap();
+        assert_eq!(response.body.len(), 39);
+    }
+
+    #[test]
+    fn test_language_variants() {
+        let temp_dir = setup_test_directory();
+        fs::write(temp_dir.path().join("page.en.html"), "English")
+            .unwrap();
+        fs::write(temp_dir.path().join("page.fr.html"), "Français")
+            .unwrap();
+        fs::write(temp_dir.path().join("index.fr.html"), "Accueil")
+            .unwrap();
+        let mut server = test_server(temp_dir.path());
+        let get = |server: &Server, path: &str, language: &str| {
+            let headers: &[(&str, &str)] = if language.is_empty() {
+                &[]
+            } else {
+                &[("Accept-Language", language)]
+            };
+            generate_response(&get_request(path, headers), server)
+                .unwrap()
+        };
+        assert_eq!(get(&server, "/page.html", "fr").status_code, 404);
+
+        server.enable_language_negotiation(&["fr", "en"]);
+        let response = get(&server, "/page.html", "fr");
+        assert_eq!(response.body, "Français".as_bytes());
+        assert_eq!(
+            header_value(&response, "Content-Language"),
+            Some("fr")
+        );
+        assert!(header_value(&response, "Vary")
+            .unwrap()
+            .contains("Accept-Language"));
+        assert_eq!(
+            get(&server, "/page.html", "de, EN").body,
+            b"English"
+        );
+        assert_eq!(
+            get(&server, "/page.html", "fr;q=0, en").body,
+            b"English"
+        );
+
+        assert_eq!(
+            get(&server, "/page.html", "en;q=0.5, fr-CA").body,
+            "Français".as_bytes()
+        );
+
+        // Without a match, the first variant stands in for the missing file
+        let response = get(&server, "/page.html", "de");
+        assert_eq!(response.body, b"English");
+        assert_eq!(
+            header_value(&response, "Content-Language"),
+            Some("en")
+        );
+
+        // ... and an existing untagged file is preferred
+        let response = get(&server, "/", "de");
+        assert_eq!(response.body.len(), 39);
+        assert_eq!(header_value(&response, "Content-Language"), None);
+        assert!(header_value(&response, "Vary")
+            .unwrap()
+            .contains("Accept-Language"));
+        assert_eq!(get(&server, "/", "fr").body, b"Accueil");
+
+        // Variants can still be requested by name
+        let response = get(&server, "/page.en.html", "fr");
+        assert_eq!(response.body, b"English");
+        assert_eq!(
+            header_value(&response, "Content-Language"),
+            Some("en")
+        );
+
+        // Segments that are not configured languages are not tags
+        fs::write(temp_dir.path().join("jquery.min.js"), "minified")
+            .unwrap();
+        let response = get(&server, "/jquery.min.js", "*");
+        assert_eq!(response.body, b"minified");
+        assert_eq!(header_value(&response, "Content-Language"), None);
+        assert!(header_value(&response, "Vary")
+            .map_or(true, |vary| !vary.contains("Accept-Language")));
+        assert_eq!(get(&server, "/jquery.js", "*").status_code, 404);
+        fs::write(temp_dir.path().join("jquery.js"), "full").unwrap();
+        assert_eq!(get(&server, "/jquery.js", "*").body, b"full");
+    }
+
+    #[test]
+    fn test_small_read_buffer() {
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+        server.set_read_buffer_size(7);
+
+        let raw = b"GET /index.html HTTP/1.1\r\nHost: example.com\r\nX-Padding: aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\r\n\r\nGET /subdir/ HTTP/1.1\r\nHost: example.com\r\nConnection: close\r\n\r\n";
+        let response =
+            String::from_utf8(roundtrip(&server, raw)).unwrap();
+        let responses: Vec<&str> =
+            response.split("HTTP/1.1 ").skip(1).collect();
+        assert_eq!(responses.len(), 2, "{}", response);
+        assert!(responses[0].starts_with("200 OK\r\n"));
+        assert!(responses[0].ends_with("Hello, World!</body></html>"));
+        assert!(responses[1].starts_with("200 OK\r\n"));
+    }
+
+    #[test]
+    fn test_root_index_files() {
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+        let root = |server: &Server| {
+            generate_response(&get_request("/", &[]), server).unwrap()
+        };
+        let response = root(&server);
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.body.len(), 39);
+        assert_eq!(
+            header_value(&response, "Content-Type"),
+            Some("text/html; charset=utf-8")
+        );
+
+        // The first index file present wins
+        fs::write(temp_dir.path().join("home.json"), "{}").unwrap();
+        server.set_index_files(&[
+            "default.htm",
+            "home.json",
+            "index.html",
+        ]);
+        let response = root(&server);
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.body, b"{}");
+        assert_eq!(
+            header_value(&response, "Content-Type"),
+            Some("application/json; charset=utf-8")
+        );
+        let response =
+            generate_response(&get_request("/subdir/", &[]), &server)
+                .unwrap();
+        assert_eq!(response.status_code, 200);
+
+        // Without one, the root is not found
+        server.set_index_files(&["default.htm"]);
+        assert_eq!(root(&server).status_code, 404);
+        server.set_index_files(&[]);
+        assert_eq!(root(&server).status_code, 404);
+    }
+
+    #[test]
+    fn test_websocket_upgrade() {
+        use std::io::Read;
+
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+        server.set_websocket_handler(
+            "/live",
+            |request: &Request, mut stream: UpgradedStream| {
+                let mut received = Vec::new();
+                let _ = stream.read_to_end(&mut received).unwrap();
+                write!(stream, "{} got ", request.path()).unwrap();
+                stream.write_all(&received).unwrap();
+            },
+        );
+        let handshake = |key: &str| {
+            format!(
+                "GET /live HTTP/1.1\r\nHost: example.com\r\n\
+                 Upgrade: websocket\r\nConnection: Upgrade\r\n\
+                 Sec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n\r\n",
+                key
+            )
+        };
+
+        let raw = handshake("dGhlIHNhbXBsZSBub25jZQ==") + "frame";
+        let response =
+            String::from_utf8(roundtrip(&server, raw.as_bytes()))
+                .unwrap();
+        assert!(
+            response
+                .starts_with("HTTP/1.1 101 Switching Protocols\r\n"),
+            "{}",
+            response
+        );
+        assert!(response.contains("Upgrade: websocket\r\n"));
+        assert!(response.contains(
+            "Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"
+        ));
+        assert!(!response.contains("Content-Length"));
+        assert!(response.ends_with("\r\n\r\n/live got frame"));
+
+        let response = String::from_utf8(roundtrip(
+            &server,
+            handshake("invalid").as_bytes(),
+        ))
+        .unwrap();
+        assert!(response.starts_with("HTTP/1.1 400 "), "{}", response);
+
+        // Plain requests for the endpoint are served as usual
+        let response = String::from_utf8(roundtrip(
+            &server,
+            b"GET /live HTTP/1.1\r\n\r\n",
+        ))
+        .unwrap();
+        assert!(response.starts_with("HTTP/1.1 404 "));
+    }
+
+    #[test]
+    fn test_live_reload_injection() {
+        let temp_dir = setup_test_directory();
+        fs::write(temp_dir.path().join("notes.txt"), "</body>")
+            .unwrap();
+        let mut server = test_server(temp_dir.path());
+        server.enable_live_reload();
+
+        let response = String::from_utf8(roundtrip(
+            &server,
+            b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n",
+        ))
+        .unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        assert!(body.contains("/__livereload"), "{}", body);
+        assert!(head
+            .contains(&format!("Content-Length: {}\r\n", body.len())));
+
+        // HEAD announces the same length without sending the script
+        let response = String::from_utf8(roundtrip(
+            &server,
+            b"HEAD / HTTP/1.1\r\nConnection: close\r\n\r\n",
+        ))
+        .unwrap();
+        assert!(response
+            .contains(&format!("Content-Length: {}\r\n", body.len())));
+        assert!(response.ends_with("\r\n\r\n"), "{}", response);
+
+        // The page is no longer the file, so its validator and ranges change
+        let response = String::from_utf8(roundtrip(
+            &server,
+            b"GET / HTTP/1.1\r\nRange: bytes=10-30\r\nConnection: close\r\n\r\n",
+        ))
+        .unwrap();
+        let (head, ranged) = response.split_once("\r\n\r\n").unwrap();
+        assert!(head.starts_with("HTTP/1.1 200 OK\r\n"), "{}", head);
+        assert_eq!(ranged, body);
+        assert!(head.contains("ETag: W/\""), "{}", head);
+        assert!(head.contains("Accept-Ranges: none\r\n"), "{}", head);
+        assert!(!head.contains("Content-Range"), "{}", head);
+
+        let response = String::from_utf8(roundtrip(
+            &server,
+            b"GET /notes.txt HTTP/1.1\r\nConnection: close\r\n\r\n",
+        ))
+        .unwrap();
+        assert!(response.ends_with("\r\n\r\n</body>"), "{}", response);
+    }
+
+    #[test]
+    fn test_default_favicon() {
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+        let favicon = |server: &Server| {
+            generate_response(&get_request("/favicon.ico", &[]), server)
+                .unwrap()
+        };
+        assert_eq!(favicon(&server).status_code, 404);
+
+        // A configured default icon
+        server.set_default_favicon(Some(b"icon bytes".to_vec()));
+        let response = favicon(&server);
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.body, b"icon bytes");
+        assert_eq!(
+            header_value(&response, "Content-Type"),
+            Some("image/x-icon")
+        );
+
+        // Suppressed with 204
+        server.set_default_favicon(None);
+        let response = favicon(&server);
+        assert_eq!(response.status_code, 204);
+        assert!(response.body.is_empty());
+
+        // Other missing files are still 404
+        let response = generate_response(
+            &get_request("/subdir/favicon.ico", &[]),
+            &server,
+        )
+        .unwrap();
+        assert_eq!(response.status_code, 404);
+
+        // A real file wins
+        fs::write(temp_dir.path().join("favicon.ico"), b"real icon")
+            .unwrap();
+        let response = favicon(&server);
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.body, b"real icon");
+        assert_eq!(
+            header_value(&response, "Content-Type"),
+            Some("image/x-icon")
+        );
+    }
+
+    #[test]
+    fn test_fallback_handler() {
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+        server.set_fallback(|request: &Request| {
+            Response::text(200, format!("fallback {}", request.path()))
+        });
+
+        // Existing files are still served
+        let response = generate_response(
+            &get_request("/index.html", &[]),
+            &server,
+        )
+        .unwrap();
+        assert!(response.body.starts_with(b"<html><body>Hello"));
+
+        let response =
+            generate_response(&get_request("/missing", &[]), &server)
+                .unwrap();
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.body, b"fallback /missing");
+
+        let mut request = get_request("/api", &[]);
+        request.method = "POST".to_string();
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(response.body, b"fallback /api");
+
+        // Hidden files are refused before the fallback
+        assert!(matches!(
+            generate_response(&get_request("/.env", &[]), &server),
+            Err(ServerError::Forbidden(_))
+        ));
+
+        // ... whatever the method
+        for path in ["/.env", "/../secret"] {
+            let mut request = get_request(path, &[]);
+            request.method = "POST".to_string();
+            assert!(matches!(
+                generate_response(&request, &server),
+                Err(ServerError::Forbidden(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_serve_blocking_reports_bind_errors() {
+        let temp_dir = setup_test_directory();
+        let result = Server::serve_blocking(
+            "not an address",
+            temp_dir.path().to_str().unwrap(),
+            None::<fn(&Request) -> Response>,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_access_log_formats() {
+        let request = get_request(
+            "/index.html",
+            &[("User-Agent", "curl/8.0 \"quoted\"")],
+        );
+        let entry = AccessLogEntry {
+            time: UNIX_EPOCH + Duration::from_secs(784_111_777),
+            request: &request,
+            status: 200,
+            bytes: 1234,
+            duration: Duration::from_micros(1500),
+            remote_addr: Some("127.0.0.1:54321"),
+            cache: None,
+        };
+        assert_eq!(
+            entry.format(LogFormat::Text),
+            "GET /index.html 200 in 1.5ms"
+        );
+
+        let line = entry.format(LogFormat::Json);
+        assert!(!line.contains('\n'));
+        let json: serde_json::Value =
+            serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "timestamp": "1994-11-06T08:49:37.000Z",
+                "method": "GET",
+                "path": "/index.html",
+                "status": 200,
+                "bytes": 1234,
+                "duration_ms": 1.5,
+                "remote_addr": "127.0.0.1:54321",
+                "user_agent": "curl/8.0 \"quoted\"",
+                "cache": null,
+            })
+        );
+        let cached = AccessLogEntry {
+            cache: Some("HIT"),
+            ..entry
+        };
+        assert_eq!(
+            cached.format(LogFormat::Text),
+            "GET /index.html 200 in 1.5ms (cache HIT)"
+        );
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(
+                &cached.format(LogFormat::Json)
+            )
+            .unwrap()["cache"],
+            "HIT"
+        );
+
+        // Unknown values are null
+        let request = get_request("/", &[]);
+        let entry = AccessLogEntry {
+            request: &request,
+            remote_addr: None,
+            ..entry
+        };
+        let json: serde_json::Value =
+            serde_json::from_str(&entry.format(LogFormat::Json))
+                .unwrap();
+        assert!(json["remote_addr"].is_null());
+        assert!(json["user_agent"].is_null());
+    }
+
+    #[test]
+    fn test_strip_port() {
+        assert_eq!(strip_port("example.com"), "example.com");
+        assert_eq!(strip_port("example.com:8080"), "example.com");
+        assert_eq!(strip_port("[::1]:8080"), "[::1]");
+    }
+
+    #[test]
+    fn test_directory_redirect() {
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+
+        let request = Request {
+            method: "GET".to_string(),
+            path: "/subdir".to_string(),
+            version: "HTTP/1.1".to_string(),
+            headers: Vec::new(),
+            raw_line: String::new(),
+            body: Vec::new(),
+        };
+
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(response.status_code, 301);
+        assert!(response.headers.contains(&(
+            "Location".to_string(),
+            "/subdir/".to_string()
+        )));
+
+        // With redirects disabled the index is served directly
+        server.set_redirect_directories(false);
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(response.status_code, 200);
+        assert!(response.body.starts_with(
+            b"<html><body>Subdirectory Index</body></html>"
+        ));
+    }
+
+    #[test]
+    fn test_query_string_ignored_in_resolution() {
+        let temp_dir = setup_test_directory();
+        let server = test_server(temp_dir.path());
+
+        let response = String::from_utf8(roundtrip(
+            &server,
+            b"GET /subdir?lang=en HTTP/1.1\r\nConnection: close\r\n\r\n",
+        ))
+        .unwrap();
+        assert!(
+            response.starts_with("HTTP/1.1 301 Moved Permanently\r\n"),
+            "{}",
+            response
+        );
+        assert!(response.contains("Location: /subdir/?lang=en\r\n"));
+
+        let response = generate_response(
+            &get_request("/index.html?v=2", &[]),
+            &server,
+        )
+        .unwrap();
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.body.len(), 39);
+    }
+
+    #[test]
+    fn test_percent_encoded_path() {
+        let temp_dir = setup_test_directory();
+        fs::write(temp_dir.path().join("my file.html"), "spaced")
+            .unwrap();
+        let server = test_server(temp_dir.path());
+        let get = |path: &str| {
+            generate_response(&get_request(path, &[]), &server).unwrap()
+        };
+
+        let response = get("/my%20file.html");
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.body, b"spaced");
+        assert_eq!(get("/%69ndex.html?v=%20").body.len(), 39);
+        assert_eq!(get("/my+file.html").status_code, 404);
+
+        // Encoded separators cannot escape the document root either
+        assert!(matches!(
+            generate_response(
+                &get_request("/subdir/%2e%2e/%2e%2e/etc/passwd", &[]),
+                &server
+            ),
+            Err(ServerError::Forbidden(_))
+        ));
+    }
+
+    #[test]
+    fn test_directory_location() {
+        assert_eq!(directory_location("/docs"), "/docs/");
+        assert_eq!(
+            directory_location("/docs?lang=en"),
+            "/docs/?lang=en"
+        );
+    }
+
+    /// Returns the value of the first response header matching `name`.
+    fn header_value<'a>(
+        response: &'a Response,
+        name: &str,
+    ) -> Option<&'a str> {
+        response
+            .headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn get_request(path: &str, headers: &[(&str, &str)]) -> Request {
+        Request {
+            method: "GET".to_string(),
+            path: path.to_string(),
+            version: "HTTP/1.1".to_string(),
+            headers: headers
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect(),
+            raw_line: String::new(),
+            body: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_evaluate_range() {
+        assert_eq!(
+            evaluate_range("bytes=0-4", 10),
+            RangeOutcome::Partial(0, 4)
+        );
+        assert_eq!(
+            evaluate_range("bytes=5-", 10),
+            RangeOutcome::Partial(5, 9)
+        );
+        assert_eq!(
+            evaluate_range("bytes=-3", 10),
+            RangeOutcome::Partial(7, 9)
+        );
+        assert_eq!(
+            evaluate_range("bytes=2-100", 10),
+            RangeOutcome::Partial(2, 9)
+        );
+        assert_eq!(
+            evaluate_range("bytes=10-20", 10),
+            RangeOutcome::Unsatisfiable
+        );
+        assert_eq!(evaluate_range("items=0-4", 10), RangeOutcome::Full);
+        assert_eq!(evaluate_range("bytes=4-2", 10), RangeOutcome::Full);
+    }
+
+    #[test]
+    fn test_evaluate_multiple_ranges() {
+        assert_eq!(
+            evaluate_range("bytes=0-1, 6-7", 10),
+            RangeOutcome::Multipart(vec![(0, 1), (6, 7)])
+        );
+        // Overlapping and adjacent ranges are coalesced, in ascending order
+        assert_eq!(
+            evaluate_range("bytes=6-8, 0-2, 1-3, 4-4", 10),
+            RangeOutcome::Multipart(vec![(0, 4), (6, 8)])
+        );
+        assert_eq!(
+            evaluate_range("bytes=0-5, 3-9", 10),
+            RangeOutcome::Partial(0, 9)
+        );
+        // Unsatisfiable ranges are dropped unless none remain
+        assert_eq!(
+            evaluate_range("bytes=2-3, 20-30", 10),
+            RangeOutcome::Partial(2, 3)
+        );
+        assert_eq!(
+            evaluate_range("bytes=20-30, 40-", 10),
+            RangeOutcome::Unsatisfiable
+        );
+        // An invalid element or too many ranges ignore the header
+        assert_eq!(
+            evaluate_range("bytes=0-1, x-2", 10),
+            RangeOutcome::Full
+        );
+        let many = vec!["0-0"; MAX_RANGES + 1].join(",");
+        assert_eq!(
+            evaluate_range(&format!("bytes={}", many), 10),
+            RangeOutcome::Full
+        );
+    }
+
+    #[test]
+    fn test_generate_boundary_avoids_contents() {
+        let taken = generate_boundary(b"");
+        assert!(taken.starts_with("http-handle-"));
+        let contents = format!("--{}\r\n", taken);
+        let boundary = generate_boundary(contents.as_bytes());
+        assert!(!contents.contains(&boundary));
+    }
+
+    #[test]
+    fn test_multipart_byteranges_response() {
+        let temp_dir = setup_test_directory();
+        fs::write(temp_dir.path().join("data.bin"), b"0123456789")
+            .unwrap();
+        let server = test_server(temp_dir.path());
+
+        let request = get_request(
+            "/data.bin",
+            &[("Range", "bytes=0-1, 6-7, 7-8")],
+        );
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(response.status_code, 206);
+        let content_type =
+            header_value(&response, "Content-Type").unwrap();
+        let boundary = content_type
+            .strip_prefix("multipart/byteranges; boundary=")
+            .unwrap();
+
+        // Parse the parts back out of the body
+        let body = String::from_utf8(response.body.clone()).unwrap();
+        let closing = format!("--{}--\r\n", boundary);
+        assert!(body.ends_with(&closing));
+        let parts: Vec<(Vec<&str>, &str)> = body
+            [..body.len() - closing.len()]
+            .split(&format!("--{}\r\n", boundary))
+            .skip(1)
+            .map(|part| {
+                let (head, data) = part.split_once("\r\n\r\n").unwrap();
+                (
+                    head.split("\r\n").collect(),
+                    data.strip_suffix("\r\n").unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            parts,
+            vec![
+                (
+                    vec![
+                        "Content-Type: application/octet-stream",
+                        "Content-Range: bytes 0-1/10"
+                    ],
+                    "01"
+                ),
+                (
+                    vec![
+                        "Content-Type: application/octet-stream",
+                        "Content-Range: bytes 6-8/10"
+                    ],
+                    "678"
+                ),
+            ]
+        );
+        assert_eq!(header_value(&response, "Content-Range"), None);
+    }
+
+    #[test]
+    fn test_if_range() {
+        let temp_dir = setup_test_directory();
+        fs::write(temp_dir.path().join("data.bin"), b"0123456789")
+            .unwrap();
+        let server = test_server(temp_dir.path());
+
+        let full =
+            generate_response(&get_request("/data.bin", &[]), &server)
+                .unwrap();
+        let etag = header_value(&full, "ETag").unwrap().to_string();
+
+        // Matching validator: the range is served
+        let matching = get_request(
+            "/data.bin",
+            &[("Range", "bytes=2-5"), ("If-Range", &etag)],
+        );
+        let response = generate_response(&matching, &server).unwrap();
+        assert_eq!(response.status_code, 206);
+        assert_eq!(response.body, b"2345");
+        assert_eq!(
+            header_value(&response, "Content-Range"),
+            Some("bytes 2-5/10")
+        );
+
+        // Mismatched validator: the full file is served
+        let mismatched = get_request(
+            "/data.bin",
+            &[("Range", "bytes=2-5"), ("If-Range", "\"stale\"")],
+        );
+        let response = generate_response(&mismatched, &server).unwrap();
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.body, b"0123456789");
+    }
+
+    #[test]
+    fn test_unsatisfiable_range() {
+        let temp_dir = setup_test_directory();
+        fs::write(temp_dir.path().join("data.bin"), b"0123456789")
+            .unwrap();
+        let server = test_server(temp_dir.path());
+
+        let request =
+            get_request("/data.bin", &[("Range", "bytes=20-30")]);
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(response.status_code, 416);
+        assert!(response.body.is_empty());
+        assert_eq!(
+            header_value(&response, "Content-Range"),
+            Some("bytes */10")
+        );
+    }
+
+    #[test]
+    fn test_unsupported_method_policy() {
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+        let request = Request {
+            method: "PATCH".to_string(),
+            ..get_request("/index.html", &[])
+        };
+
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(response.status_code, 405);
+        assert_eq!(
+            header_value(&response, "Allow"),
+            Some("GET, HEAD, OPTIONS")
+        );
+
+        server.set_unsupported_method_policy(
+            UnsupportedMethodPolicy::NotImplemented,
+        );
+        let error = generate_response(&request, &server).unwrap_err();
+        assert!(matches!(error, ServerError::NotImplemented(_)));
+
+        let response = generate_error_response(&error);
+        assert_eq!(response.status_code, 501);
+        assert_eq!(response.status_text, "Not Implemented");
+    }
+
+    /// Logger capturing formatted log messages so tests can inspect them.
+    struct CaptureLogger;
+
+    static CAPTURED_LOGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+    impl log::Log for CaptureLogger {
+        fn enabled(&self, _metadata: &log::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn log(&self, record: &log::Record<'_>) {
+            CAPTURED_LOGS
+                .lock()
+                .unwrap()
+                .push(record.args().to_string());
+        }
+
+        fn flush(&self) {}
+    }
+
+    fn install_capture_logger() {
+        static LOGGER: CaptureLogger = CaptureLogger;
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(log::LevelFilter::Trace);
+    }
+
+    /// Sends `raw` to `handle_connection` over a loopback socket and returns the
+    /// raw response bytes.
+    fn roundtrip(server: &Server, raw: &[u8]) -> Vec<u8> {
+        use std::io::Read;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut client = TcpStream::connect(addr).unwrap();
+        client.write_all(raw).unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+
+        let (stream, _) = listener.accept().unwrap();
+        handle_connection(stream, server, &ShutdownHandle::new())
+            .unwrap();
+
+        let mut response = Vec::new();
+        let _ = client.read_to_end(&mut response).unwrap();
+        response
+    }
+
+    #[test]
+    fn test_get_with_body_keeps_connection_aligned() {
+        let temp_dir = setup_test_directory();
+        let server = test_server(temp_dir.path());
+        let raw = String::from_utf8(roundtrip(
+            &server,
+            b"GET /index.html HTTP/1.1\r\nContent-Length: 25\r\n\r\n\
+              GET /ignored HTTP/1.1\r\n\r\n\
+              GET /subdir/index.html HTTP/1.1\r\n\r\n",
+        ))
+        .unwrap();
+
+        let responses: Vec<&str> =
+            raw.split("HTTP/1.1 ").skip(1).collect();
+        assert_eq!(responses.len(), 2, "{}", raw);
+        assert!(responses[0].starts_with("200 OK\r\n"));
+        assert!(responses[0]
+            .ends_with("<html><body>Hello, World!</body></html>"));
+        assert!(responses[1].starts_with("200 OK\r\n"), "{}", raw);
+    }
+
+    #[test]
+    fn test_request_timeout() {
+        use std::io::Read;
+
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+        server.set_read_timeout(Duration::from_millis(200));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Sends `raw`, then stalls until the server closes the connection
+        let exchange = |raw: &[u8]| {
+            let mut client = TcpStream::connect(addr).unwrap();
+            client.write_all(raw).unwrap();
+            let (stream, _) = listener.accept().unwrap();
+            let result = handle_connection(
+                stream,
+                &server,
+                &ShutdownHandle::new(),
+            );
+            let mut response = Vec::new();
+            let _ = client.read_to_end(&mut response).unwrap();
+            (result, String::from_utf8_lossy(&response).into_owned())
+        };
+
+        // A half-sent request is answered with 408
+        let (result, response) =
+            exchange(b"GET /index.html HTTP/1.1\r\n");
+        assert!(matches!(result, Err(ServerError::Timeout(_))));
+        assert!(
+            response.starts_with("HTTP/1.1 408 Request Timeout\r\n"),
+            "{}",
+            response
+        );
+        assert!(response.contains("Connection: close\r\n"));
+
+        // A connection that never starts a request is closed quietly
+        let (result, response) = exchange(b"");
+        assert!(result.is_ok());
+        assert!(response.is_empty(), "{}", response);
+
+        // So is a keep-alive connection idle after its request
+        let (result, response) =
+            exchange(b"GET /index.html HTTP/1.1\r\n\r\n");
+        assert!(result.is_ok());
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(!response.contains("408"), "{}", response);
+    }
+
+    #[test]
+    fn test_closing_response_shuts_down_connection() {
+        use std::io::Read;
+
+        let temp_dir = setup_test_directory();
+        let server = test_server(temp_dir.path());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client =
+            TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client
+            .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+            .unwrap();
+
+        // A second handle keeps the socket open after handle_connection drops its
+        // own, so the client only sees an end of stream through the shutdown
+        let (stream, _) = listener.accept().unwrap();
+        let _kept_open = stream.try_clone().unwrap();
+        handle_connection(stream, &server, &ShutdownHandle::new())
+            .unwrap();
+
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut response = Vec::new();
+        let _ = client.read_to_end(&mut response).unwrap();
+        assert!(String::from_utf8_lossy(&response)
+            .starts_with("HTTP/1.1 200 OK\r\n"));
+
+        // The peer having gone away already is not an error
+        let (stream, _) = {
+            let _client =
+                TcpStream::connect(listener.local_addr().unwrap())
+                    .unwrap();
+            listener.accept().unwrap()
+        };
+        finish_connection(&stream);
+    }
+
+    #[test]
+    fn test_oversized_body_rejected_before_reading() {
+        use std::io::Read;
+
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+        server.set_max_body_size(16);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // The client waits for permission before sending the body, so
+        // the refusal has to come without reading it; without the
+        // expectation, the body is refused as too large
+        let cases: [(&[u8], &str); 2] = [
+            (
+                b"Expect: 100-continue\r\n",
+                "HTTP/1.1 417 Expectation Failed\r\n",
+            ),
+            (b"", "HTTP/1.1 413 Content Too Large\r\n"),
+        ];
+        for (expect, status_line) in cases.iter() {
+            let mut client = TcpStream::connect(addr).unwrap();
+            client
+                .write_all(b"POST /upload HTTP/1.1\r\nContent-Length: 1024\r\n")
+                .unwrap();
+            client.write_all(expect).unwrap();
+            client.write_all(b"\r\n").unwrap();
+            let (stream, _) = listener.accept().unwrap();
+            assert!(handle_connection(
+                stream,
+                &server,
+                &ShutdownHandle::new()
+            )
+            .is_err());
+
+            client
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            let mut response = Vec::new();
+            let _ = client.read_to_end(&mut response).unwrap();
+            let response = String::from_utf8_lossy(&response);
+            assert!(response.starts_with(status_line), "{}", response);
+            assert!(response.contains("Connection: close\r\n"));
+        }
+    }
+
+    #[test]
+    fn test_request_latency_logged() {
+        install_capture_logger();
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+        server.set_server_timing(true);
+
+        let response = roundtrip(
+            &server,
+            b"GET /index.html?latency HTTP/1.1\r\n\r\n",
+        );
+        let response = String::from_utf8_lossy(&response);
+        assert!(response.contains("Server-Timing: total;dur="));
+
+        let logs = CAPTURED_LOGS.lock().unwrap();
+        let line = logs
+            .iter()
+            .find(|line| line.starts_with("GET /index.html?latency "))
+            .expect("request log line");
+        let duration = line.rsplit(" in ").next().unwrap();
+        assert_ne!(duration, "0ns");
+    }
+
+    #[test]
+    fn test_file_cache_status() {
+        install_capture_logger();
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+        server.enable_file_cache(1024);
+        server.set_cache_status_header(true);
+
+        let raw = b"GET /subdir/index.html HTTP/1.1\r\n\r\n\
+                    GET /subdir/index.html HTTP/1.1\r\n\r\n";
+        let response =
+            String::from_utf8(roundtrip(&server, raw)).unwrap();
+        let responses: Vec<&str> =
+            response.split("HTTP/1.1 ").skip(1).collect();
+        assert_eq!(responses.len(), 2);
+        assert!(
+            responses[0].contains("X-Cache: MISS\r\n"),
+            "{}",
+            response
+        );
+        assert!(responses[1].contains("X-Cache: HIT\r\n"));
+        assert!(responses[1].ends_with("</body></html>"));
+
+        // Without the header, the outcome is still logged
+        server.set_cache_status_header(false);
+        let response = String::from_utf8(roundtrip(
+            &server,
+            b"GET /subdir/index.html HTTP/1.1\r\n\r\n",
+        ))
+        .unwrap();
+        assert!(!response.contains("X-Cache"));
+
+        let logs = CAPTURED_LOGS.lock().unwrap();
+        let outcomes: Vec<&str> = logs
+            .iter()
+            .filter(|line| line.starts_with("GET /subdir/index.html "))
+            .filter(|line| line.contains("cache"))
+            .filter_map(|line| line.rsplit(" (cache ").next())
+            .collect();
+        assert_eq!(outcomes, ["MISS)", "HIT)", "HIT)"]);
+    }
+
+    #[test]
+    fn test_connection_close_is_logged() {
+        use std::io::Read;
+
+        install_capture_logger();
+        let temp_dir = setup_test_directory();
+        let server = test_server(temp_dir.path());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client =
+            TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client
+            .write_all(
+                b"GET /index.html HTTP/1.1\r\n\r\n\
+                  GET /index.html HTTP/1.1\r\nConnection: close\r\n\r\n",
+            )
+            .unwrap();
+
+        let (stream, _) = listener.accept().unwrap();
+        handle_connection(stream, &server, &ShutdownHandle::new())
+            .unwrap();
+        let mut response = Vec::new();
+        let _ = client.read_to_end(&mut response).unwrap();
+
+        let prefix = format!(
+            "Connection from {} closed after 2 request(s), 78 bytes sent in ",
+            client.local_addr().unwrap()
+        );
+        let logs = CAPTURED_LOGS.lock().unwrap();
+        assert!(
+            logs.iter().any(|line| line.starts_with(&prefix)),
+            "no close log starting with {:?}",
+            prefix
+        );
+    }
+
+    #[test]
+    fn test_options_asterisk() {
+        let temp_dir = setup_test_directory();
+        let server = test_server(temp_dir.path());
+        let request = Request {
+            method: "OPTIONS".to_string(),
+            ..get_request("*", &[])
+        };
+
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(response.status_code, 204);
+        assert!(response.body.is_empty());
+        assert_eq!(
+            header_value(&response, "Allow"),
+            Some("GET, HEAD, OPTIONS")
+        );
+    }
+
+    #[test]
+    fn test_tcp_nodelay_applied() {
+        let mut server = Server::new("127.0.0.1:0", ".");
+        server.set_listen_backlog(16);
+        let listener = server.bind().unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        for nodelay in [true, false] {
+            server.set_tcp_nodelay(nodelay);
+            let _client = TcpStream::connect(addr).unwrap();
+            let (stream, _) = listener.accept().unwrap();
+            server.configure_stream(SockRef::from(&stream)).unwrap();
+            assert_eq!(stream.nodelay().unwrap(), nodelay);
+        }
+    }
+
+    #[test]
+    fn test_bind_ipv6_loopback() {
+        let server = Server::new("[::1]:0", ".");
+        let listener = match server.bind() {
+            Ok(listener) => listener,
+            // IPv6 may be disabled on the host running the tests
+            Err(_) => return,
+        };
+        let addr = listener.local_addr().unwrap();
+        assert!(addr.is_ipv6());
+
+        let _client = TcpStream::connect(addr).unwrap();
+        let (_, peer) = listener.accept().unwrap();
+        assert!(peer.is_ipv6());
+    }
+
+    #[test]
+    fn test_bind_dual_stack() {
+        let server = Server::new("[::]:0", ".");
+        let listener = match server.bind_dual_stack(0) {
+            Ok(listener) => listener,
+            Err(e) => {
+                assert!(matches!(e, ServerError::Custom(_)));
+                return;
+            }
+        };
+        let port = listener.local_addr().unwrap().port();
+
+        let _v4 = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        let _ = listener.accept().unwrap();
+        let _v6 = TcpStream::connect(("::1", port)).unwrap();
+        let _ = listener.accept().unwrap();
+    }
+
+    #[test]
+    fn test_rebind_after_drop() {
+        let server = Server::new("127.0.0.1:0", ".");
+        let listener = server.bind().unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        // Leave a closed connection behind so the port lingers in TIME_WAIT
+        let _client = TcpStream::connect(addr).unwrap();
+        drop(listener.accept().unwrap());
+        drop(listener);
+
+        let server = Server::new(&addr.to_string(), ".");
+        assert!(server.bind().is_ok());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_reuse_port() {
+        let mut server = Server::new("127.0.0.1:0", ".");
+        server.set_reuse_port(true);
+        let first = server.bind().unwrap();
+
+        let server = Server {
+            address: first.local_addr().unwrap().to_string(),
+            ..server
+        };
+        let second = server.bind();
+        assert!(second.is_ok());
+
+        // Dual-stack listeners share the port the same way
+        let first = match server.bind_dual_stack(0) {
+            Ok(listener) => listener,
+            // IPv6 is not available here
+            Err(ServerError::Custom(_)) => return,
+            Err(e) => panic!("{}", e),
+        };
+        let port = first.local_addr().unwrap().port();
+        assert!(server.bind_dual_stack(port).is_ok());
+    }
+
+    #[test]
+    fn test_sendfile_transmits_whole_file() {
+        let temp_dir = setup_test_directory();
+        let contents: Vec<u8> = (0..200_000).map(|i| i as u8).collect();
+        fs::write(temp_dir.path().join("large.bin"), &contents)
+            .unwrap();
+
+        for sendfile in [true, false] {
+            let mut server = test_server(temp_dir.path());
+            server.set_sendfile(sendfile);
+
+            let response =
+                roundtrip(&server, b"GET /large.bin HTTP/1.1\r\n\r\n");
+            let head_end = response
+                .windows(4)
+                .position(|w| w == b"\r\n\r\n")
+                .unwrap();
+            let head = String::from_utf8_lossy(&response[..head_end]);
+            assert!(head.starts_with("HTTP/1.1 200 OK"));
+            if sendfile {
+                assert!(head.contains("Content-Length: 200000"));
+            }
+            assert_eq!(&response[head_end + 4..], contents.as_slice());
+        }
+    }
+
+    #[test]
+    fn test_transmit_file_sends_announced_length() {
+        use std::io::Read;
+
+        let temp_dir = setup_test_directory();
+        let path = temp_dir.path().join("changing.bin");
+        fs::write(&path, b"0123456789").unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client =
+            TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut stream, _) = listener.accept().unwrap();
+
+        // A file that grew is cut at the announced length
+        let mut file = File::open(&path).unwrap();
+        transmit_file(&mut stream, &mut file, 4).unwrap();
+
+        // One that shrank fails instead of leaving the body short
+        let mut file = File::open(&path).unwrap();
+        let error =
+            transmit_file(&mut stream, &mut file, 20).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+
+        drop(stream);
+        let mut received = Vec::new();
+        let _ = client.read_to_end(&mut received).unwrap();
+        assert_eq!(received, b"01230123456789");
+    }
+
+    #[test]
+    fn test_request_smuggling_rejected_with_400() {
+        use std::io::Read;
+
+        let temp_dir = setup_test_directory();
+        let server = test_server(temp_dir.path());
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(
+                b"POST /index.html HTTP/1.1\r\nContent-Length: 5\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n",
+            )
+            .unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+
+        let (stream, _) = listener.accept().unwrap();
+        let result =
+            handle_connection(stream, &server, &ShutdownHandle::new());
+        assert!(matches!(result, Err(ServerError::InvalidRequest(_))));
+
+        let mut response = String::new();
+        let _ = client.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 400 Bad Request"));
+    }
+
+    #[test]
+    fn test_max_connections() {
+        use std::io::Read;
+        use std::time::Duration;
+
+        let temp_dir = setup_test_directory();
+        for policy in [
+            ConnectionLimitPolicy::Reject,
+            ConnectionLimitPolicy::Queue,
+        ] {
+            let mut server = test_server(temp_dir.path());
+            server.set_max_connections(1);
+            server.set_connection_limit_policy(policy);
+
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let addr = listener.local_addr().unwrap();
+            let _ = thread::spawn(move || server.serve(listener));
+
+            // The first connection holds the only slot without sending a request
+            let idle = TcpStream::connect(addr).unwrap();
+            thread::sleep(Duration::from_millis(100));
+
+            let mut extra = TcpStream::connect(addr).unwrap();
+            let mut response = String::new();
+            match policy {
+                ConnectionLimitPolicy::Reject => {
+                    let _ =
+                        extra.read_to_string(&mut response).unwrap();
+                    assert!(response.starts_with(
+                        "HTTP/1.1 503 Service Unavailable"
+                    ));
+                }
+                ConnectionLimitPolicy::Queue => {
+                    extra
+                        .write_all(
+                            b"GET /index.html HTTP/1.1\r\nConnection: close\r\n\r\n",
+                        )
+                        .unwrap();
+                    extra
+                        .set_read_timeout(Some(Duration::from_millis(
+                            200,
+                        )))
+                        .unwrap();
+                    assert!(extra
+                        .read_to_string(&mut response)
+                        .is_err());
+
+                    // Closing the idle connection frees the slot
+                    drop(idle);
+                    extra.set_read_timeout(None).unwrap();
+                    let _ =
+                        extra.read_to_string(&mut response).unwrap();
+                    assert!(response.starts_with("HTTP/1.1 200 OK"));
+                    continue;
+                }
+            }
+            drop(idle);
+        }
+    }
+
+    #[test]
+    fn test_rejections_are_bounded() {
+        use std::io::Read;
+        use std::time::Duration;
+
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+        server.set_max_connections(1);
+        server
+            .set_connection_limit_policy(ConnectionLimitPolicy::Reject);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let _ = thread::spawn(move || server.serve(listener));
+
+        let _idle = TcpStream::connect(addr).unwrap();
+        thread::sleep(Duration::from_millis(100));
+
+        // Clients that never close keep every rejection thread busy
+        let mut rejected = Vec::new();
+        for _ in 0..MAX_REJECTING_CONNECTIONS {
+            let mut client = TcpStream::connect(addr).unwrap();
+            let mut status = [0; 12];
+            client.read_exact(&mut status).unwrap();
+            assert_eq!(&status, b"HTTP/1.1 503");
+            rejected.push(client);
+        }
+
+        // ... so the next one is closed without a response
+        let mut flooded = TcpStream::connect(addr).unwrap();
+        let mut response = Vec::new();
+        let _ = flooded.read_to_end(&mut response);
+        assert!(response.is_empty());
+    }
+
+    #[test]
+    fn test_graceful_shutdown() {
+        use std::io::Read;
+        use std::sync::mpsc;
+
+        let temp_dir = setup_test_directory();
+        let server = test_server(temp_dir.path());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let shutdown = ShutdownHandle::new();
+        let handle = shutdown.clone();
+        let (done, stopped) = mpsc::channel();
+        let _ = thread::spawn(move || {
+            done.send(server.serve_with_shutdown(listener, &handle))
+                .unwrap();
+        });
+
+        // A persistent connection left idle after its first response,
+        // which has no body and so arrives in a single read
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        client.write_all(b"HEAD / HTTP/1.1\r\n\r\n").unwrap();
+        let mut buffer = [0; 4096];
+        let read = client.read(&mut buffer).unwrap();
+        let response = String::from_utf8_lossy(&buffer[..read]);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(!response.contains("Connection: close"));
+
+        shutdown.stop();
+        assert!(shutdown.is_stopped());
+        stopped
+            .recv_timeout(Duration::from_secs(5))
+            .expect("server did not stop")
+            .unwrap();
+
+        // The idle connection was closed and the listener is gone
+        let mut rest = Vec::new();
+        let _ = client.read_to_end(&mut rest).unwrap();
+        assert!(rest.is_empty());
+        assert!(TcpStream::connect(addr).is_err());
+    }
+
+    /// Generates a self-signed certificate for `localhost`, returning a server
+    /// configuration using it and the certificate for clients to trust.
+    #[cfg(feature = "tls")]
+    fn tls_test_config() -> (
+        rustls::ServerConfig,
+        rustls::pki_types::CertificateDer<'static>,
+    ) {
+        use rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+
+        let certified =
+            rcgen::generate_simple_self_signed(
+                vec!["localhost".into()],
+            )
+            .unwrap();
+        let cert = certified.cert.der().clone();
+        let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(
+            certified.key_pair.serialize_der(),
+        ));
+        let config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(vec![cert.clone()], key)
+            .unwrap();
+        (config, cert)
+    }
+
+    /// Sends a raw request to a TLS server trusting `cert`, offering the `alpn`
+    /// protocols, and returns the client session after reading the whole response
+    /// into `response`.
+    #[cfg(feature = "tls")]
+    fn tls_roundtrip(
+        addr: SocketAddr,
+        cert: rustls::pki_types::CertificateDer<'static>,
+        alpn: &[&[u8]],
+        raw: &[u8],
+        response: &mut Vec<u8>,
+    ) -> rustls::ClientConnection {
+        use std::convert::TryInto;
+        use std::io::Read;
+
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(cert).unwrap();
+        let mut config = rustls::ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        config.alpn_protocols =
+            alpn.iter().map(|protocol| protocol.to_vec()).collect();
+        let connection = rustls::ClientConnection::new(
+            Arc::new(config),
+            "localhost".try_into().unwrap(),
+        )
+        .unwrap();
+        let socket = TcpStream::connect(addr).unwrap();
+        socket
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let mut client = rustls::StreamOwned::new(connection, socket);
+        client.write_all(raw).unwrap();
+        let _ = client.read_to_end(response).unwrap();
+        client.conn
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn test_tls_rejection_does_not_block_accept() {
+        let temp_dir = setup_test_directory();
+        let (config, cert) = tls_test_config();
+        let mut server = Server::with_tls_config(
+            "127.0.0.1:0",
+            temp_dir.path().to_str().unwrap(),
+            Arc::new(config),
+        );
+        server.set_max_connections(1);
+        server
+            .set_connection_limit_policy(ConnectionLimitPolicy::Reject);
+        server.set_read_timeout(Duration::from_secs(10));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let shutdown = ShutdownHandle::new();
+        let handle = shutdown.clone();
+        let serving = thread::spawn(move || {
+            server.serve_with_shutdown(listener, &handle)
+        });
+
+        // One idle connection holds the only slot and another, rejected, never
+        // starts its handshake
+        let idle = TcpStream::connect(addr).unwrap();
+        thread::sleep(Duration::from_millis(100));
+        let rejected = TcpStream::connect(addr).unwrap();
+        thread::sleep(Duration::from_millis(100));
+
+        let start = Instant::now();
+        let mut response = Vec::new();
+        let _ = tls_roundtrip(
+            addr,
+            cert,
+            &[],
+            b"GET / HTTP/1.1\r\n\r\n",
+            &mut response,
+        );
+        assert!(
+            response
+                .starts_with(b"HTTP/1.1 503 Service Unavailable\r\n"),
+            "{}",
+            String::from_utf8_lossy(&response)
+        );
+        assert!(start.elapsed() < Duration::from_secs(5));
+
+        drop(idle);
+        drop(rejected);
+        shutdown.stop();
+        serving.join().unwrap().unwrap();
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn test_tls_config() {
+        let temp_dir = setup_test_directory();
+        let (config, cert) = tls_test_config();
+        let mut server = Server::with_tls_config(
+            "127.0.0.1:0",
+            temp_dir.path().to_str().unwrap(),
+            Arc::new(config),
+        );
+        server.set_sendfile(true);
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let shutdown = ShutdownHandle::new();
+        let handle = shutdown.clone();
+        let serving = thread::spawn(move || {
+            server.serve_with_shutdown(listener, &handle)
+        });
+
+        let mut response = Vec::new();
+        let _ = tls_roundtrip(
+            addr,
+            cert,
+            &[],
+            b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n",
+            &mut response,
+        );
+        let response = String::from_utf8(response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(
+            response
+                .ends_with("<html><body>Hello, World!</body></html>"),
+            "{}",
+            response
+        );
+
+        shutdown.stop();
+        serving.join().unwrap().unwrap();
+
+        // The path-based constructor accepts the same certificate as PEM
+        let certified =
+            rcgen::generate_simple_self_signed(
+                vec!["localhost".into()],
+            )
+            .unwrap();
+        let cert_path = temp_dir.path().join("cert.pem");
+        let key_path = temp_dir.path().join("key.pem");
+        fs::write(&cert_path, certified.cert.pem()).unwrap();
+        fs::write(&key_path, certified.key_pair.serialize_pem())
+            .unwrap();
+        let root = temp_dir.path().to_str().unwrap();
+        assert!(Server::new_tls(
+            "127.0.0.1:0",
+            root,
+            cert_path.to_str().unwrap(),
+            key_path.to_str().unwrap(),
+        )
+        .is_ok());
+        assert!(Server::new_tls(
+            "127.0.0.1:0",
+            root,
+            cert_path.to_str().unwrap(),
+            cert_path.to_str().unwrap(),
+        )
+        .is_err());
+    }
+
+    #[cfg(feature = "tls")]
+    #[test]
+    fn test_tls_alpn() {
+        let temp_dir = setup_test_directory();
+        let (config, cert) = tls_test_config();
+        let mut server = Server::with_tls_config(
+            "127.0.0.1:0",
+            temp_dir.path().to_str().unwrap(),
+            Arc::new(config),
+        );
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let shutdown = ShutdownHandle::new();
+        let handle = shutdown.clone();
+        let serving = {
+            let server = server.clone();
+            thread::spawn(move || {
+                server.serve_with_shutdown(listener, &handle)
+            })
+        };
+        let request = b"HEAD / HTTP/1.1\r\nConnection: close\r\n\r\n";
+
+        let mut response = Vec::new();
+        let client = tls_roundtrip(
+            addr,
+            cert.clone(),
+            &[b"h2", b"http/1.1"],
+            request,
+            &mut response,
+        );
+        assert_eq!(client.alpn_protocol(), Some(&b"http/1.1"[..]));
+        assert!(response.starts_with(b"HTTP/1.1 200 OK\r\n"));
+        shutdown.stop();
+        serving.join().unwrap().unwrap();
+
+        // Added protocols are preferred
+        server.add_alpn_protocol("x-custom");
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let shutdown = ShutdownHandle::new();
+        let handle = shutdown.clone();
+        let serving = thread::spawn(move || {
+            server.serve_with_shutdown(listener, &handle)
+        });
+        let mut response = Vec::new();
+        let client = tls_roundtrip(
+            addr,
+            cert,
+            &[b"http/1.1", b"x-custom"],
+            request,
+            &mut response,
+        );
+        assert_eq!(client.alpn_protocol(), Some(&b"x-custom"[..]));
+        shutdown.stop();
+        serving.join().unwrap().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_socket() {
+        use std::io::Read;
+        use std::sync::mpsc;
+
+        let temp_dir = setup_test_directory();
+        let socket_dir = TempDir::new().unwrap();
+        let socket_path = socket_dir.path().join("server.sock");
+        let server = Server::new_unix(
+            socket_path.to_str().unwrap(),
+            temp_dir.path().to_str().unwrap(),
+        );
+        let shutdown = ShutdownHandle::new();
+        let handle = shutdown.clone();
+        let (done, stopped) = mpsc::channel();
+        let _ = thread::spawn(move || {
+            done.send(server.start_with_shutdown(&handle)).unwrap();
+        });
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut client = loop {
+            match UnixStream::connect(&socket_path) {
+                Ok(client) => break client,
+                Err(_) if Instant::now() < deadline => {
+                    thread::sleep(Duration::from_millis(10));
+                }
+                Err(e) => panic!("server did not start: {}", e),
+            }
+        };
+        client
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        client
+            .write_all(
+                b"GET /index.html HTTP/1.1\r\nConnection: close\r\n\r\n",
+            )
+            .unwrap();
+        let mut response = Vec::new();
+        let _ = client.read_to_end(&mut response).unwrap();
+        let response = String::from_utf8_lossy(&response);
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response
+            .ends_with("<html><body>Hello, World!</body></html>"));
+
+        shutdown.stop();
+        stopped
+            .recv_timeout(Duration::from_secs(5))
+            .expect("server did not stop")
+            .unwrap();
+        assert!(!socket_path.exists());
+    }
+
+    #[cfg(all(unix, feature = "signals"))]
+    #[test]
+    fn test_stop_on_signals() {
+        let shutdown = ShutdownHandle::new();
+        shutdown.stop_on_signals().unwrap();
+        signal_hook::low_level::raise(signal_hook::consts::SIGTERM)
+            .unwrap();
+
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while !shutdown.is_stopped() && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(shutdown.is_stopped());
+    }
+
+    #[test]
+    fn test_attachment_extensions() {
+        let temp_dir = setup_test_directory();
+        fs::write(temp_dir.path().join("data.CSV"), "a,b\n1,2\n")
+            .unwrap();
+        let mut server = test_server(temp_dir.path());
+        server.add_attachment_extension(".csv");
+
+        let response =
+            generate_response(&get_request("/data.CSV", &[]), &server)
+                .unwrap();
+        assert_eq!(
+            header_value(&response, "Content-Disposition"),
+            Some("attachment; filename=\"data.CSV\"")
+        );
+
+        // The zero-copy path marks downloads too
+        let raw = roundtrip(&server, b"GET /data.CSV HTTP/1.1\r\n\r\n");
+        let raw = String::from_utf8_lossy(&raw);
+        assert!(raw.starts_with("HTTP/1.1 200 OK\r\n"), "{}", raw);
+        assert!(
+            raw.contains(
+                "Content-Disposition: attachment; filename=\"data.CSV\"\r\n"
+            ),
+            "{}",
+            raw
+        );
+        assert!(raw.ends_with("\r\n\r\na,b\n1,2\n"), "{}", raw);
+
+        let response = generate_response(
+            &get_request("/index.html", &[]),
+            &server,
+        )
+        .unwrap();
+        assert_eq!(
+            header_value(&response, "Content-Disposition"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_accept_ranges_advertised() {
+        let temp_dir = setup_test_directory();
+        let server = test_server(temp_dir.path());
+
+        let response = generate_response(
+            &get_request("/index.html", &[]),
+            &server,
+        )
+        .unwrap();
+        assert_eq!(response.status_code, 200);
+        assert_eq!(
+            header_value(&response, "Accept-Ranges"),
+            Some("bytes")
+        );
+
+        // The zero-copy path advertises ranges too
+        let raw =
+            roundtrip(&server, b"GET /index.html HTTP/1.1\r\n\r\n");
+        assert!(String::from_utf8_lossy(&raw)
+            .contains("Accept-Ranges: bytes\r\n"));
+
+        // Every other response says ranges are not served
+        for request in [
+            &b"GET /missing.html HTTP/1.1\r\n\r\n"[..],
+            b"GET /subdir HTTP/1.1\r\n\r\n",
+            b"OPTIONS * HTTP/1.1\r\n\r\n",
+            b"DELETE /index.html HTTP/1.1\r\n\r\n",
+        ] {
+            let raw =
+                String::from_utf8_lossy(&roundtrip(&server, request))
+                    .into_owned();
+            assert!(raw.contains("Accept-Ranges: none\r\n"), "{}", raw);
+        }
+        let mut response = generate_error_response(
+            &ServerError::Custom("failure".to_string()),
+        );
+        server.apply_response_headers(&mut response);
+        assert_eq!(
+            header_value(&response, "Accept-Ranges"),
+            Some("none")
+        );
+
+        // A `304 Not Modified` leaves the cached value alone
+        let mut response = Response::not_modified();
+        server.apply_response_headers(&mut response);
+        assert_eq!(header_value(&response, "Accept-Ranges"), None);
+    }
+
+    #[test]
+    fn test_read_failure_returns_500() {
+        let temp_dir = setup_test_directory();
+        let server = test_server(temp_dir.path());
+        let request = get_request("/", &[]);
+
+        // Reading a directory as a file fails with an error other than NotFound
+        let error =
+            serve_file(&request, &server, temp_dir.path(), true, true)
+                .unwrap_err();
+        assert!(matches!(error, ServerError::Io(_)));
+        let response = generate_error_response(&error);
+        assert_eq!(response.status_code, 500);
+        assert_eq!(response.status_text, "Internal Server Error");
+        assert_eq!(response.body, b"500 Internal Server Error");
+
+        // A file removed between resolution and reading is still a 404
+        let error = serve_file(
+            &request,
+            &server,
+            &temp_dir.path().join("vanished.html"),
+            true,
+            true,
+        )
+        .unwrap_err();
+        assert_eq!(generate_error_response(&error).status_code, 404);
+    }
+
+    #[test]
+    fn test_file_permissions_mapping() {
+        let temp_dir = setup_test_directory();
+        let server = test_server(temp_dir.path());
+
+        // Readable file
+        let response = generate_response(
+            &get_request("/index.html", &[]),
+            &server,
+        )
+        .unwrap();
+        assert_eq!(response.status_code, 200);
+
+        // Missing file
+        let error = serve_file(
+            &get_request("/gone.html", &[]),
+            &server,
+            &temp_dir.path().join("gone.html"),
+            true,
+            true,
+        )
+        .unwrap_err();
+        assert!(matches!(error, ServerError::NotFound(_)));
+
+        // Permission denied
+        let path = Path::new("/srv/private.html");
+        let denied =
+            io::Error::new(io::ErrorKind::PermissionDenied, "denied");
+        let error = file_error(denied, path);
+        assert!(matches!(error, ServerError::Forbidden(_)));
+        assert_eq!(generate_error_response(&error).status_code, 403);
+
+        let other = io::Error::new(io::ErrorKind::Other, "disk error");
+        assert!(matches!(file_error(other, path), ServerError::Io(_)));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[ignore = "needs an unprivileged user, since root reads any file; \
+                run with `cargo test -- --ignored`"]
+    fn test_unreadable_file_returns_403() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = setup_test_directory();
+        let path = temp_dir.path().join("secret.html");
+        fs::write(&path, "secret").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o000))
+            .unwrap();
+
+        // Privileged users bypass file permissions, which would prove nothing
+        assert!(
+            fs::read(&path).is_err(),
+            "the file is readable; run this test as an unprivileged user"
+        );
+
+        let mut server = test_server(temp_dir.path());
+        for sendfile in [true, false] {
+            server.set_sendfile(sendfile);
+            let response = roundtrip(
+                &server,
+                b"GET /secret.html HTTP/1.1\r\n\r\n",
+            );
+            assert!(String::from_utf8_lossy(&response)
+                .starts_with("HTTP/1.1 403 Forbidden"));
+        }
+    }
+
+    #[test]
+    fn test_dotfile_blocking_allows_well_known() {
+        let temp_dir = setup_test_directory();
+        let challenge_dir =
+            temp_dir.path().join(".well-known").join("acme-challenge");
+        fs::create_dir_all(&challenge_dir).unwrap();
+        fs::write(challenge_dir.join("token"), "token.thumbprint")
+            .unwrap();
+        fs::write(temp_dir.path().join(".env"), "SECRET=1").unwrap();
+        fs::create_dir(temp_dir.path().join(".git")).unwrap();
+        fs::write(temp_dir.path().join(".git").join("config"), "")
+            .unwrap();
+        let mut server = test_server(temp_dir.path());
+
+        let response = generate_response(
+            &get_request("/.well-known/acme-challenge/token", &[]),
+            &server,
+        )
+        .unwrap();
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.body, b"token.thumbprint");
+
+        for path in ["/.env", "/.git/config", "/.well-known/../.env"] {
+            let result =
+                generate_response(&get_request(path, &[]), &server);
+            assert!(matches!(result, Err(ServerError::Forbidden(_))));
+        }
+
+        server.set_block_dotfiles(false);
+        let response =
+            generate_response(&get_request("/.env", &[]), &server)
+                .unwrap();
+        assert_eq!(response.status_code, 200);
+    }
+
+    #[test]
+    fn test_cache_control() {
+        let temp_dir = setup_test_directory();
+        fs::write(temp_dir.path().join("app.3f9a.js"), "void 0;")
+            .unwrap();
+        let mut server = test_server(temp_dir.path());
+
+        let response = generate_response(
+            &get_request("/index.html", &[]),
+            &server,
+        )
+        .unwrap();
+        assert_eq!(
+            header_value(&response, "Cache-Control"),
+            Some("public, max-age=3600")
+        );
+
+        server.set_cache_control("public, max-age=31536000, immutable");
+        server.set_cache_control_for_extension("HTML", "no-cache");
+        let response = generate_response(
+            &get_request("/index.html", &[]),
+            &server,
+        )
+        .unwrap();
+        assert_eq!(
+            header_value(&response, "Cache-Control"),
+            Some("no-cache")
+        );
+
+        // The zero-copy path applies the same headers
+        server.add_attachment_extension("js");
+        let raw =
+            roundtrip(&server, b"GET /app.3f9a.js HTTP/1.1\r\n\r\n");
+        let raw = String::from_utf8_lossy(&raw);
+        assert!(raw.contains(
+            "Cache-Control: public, max-age=31536000, immutable\r\n"
+        ));
+        assert!(raw.contains("Content-Disposition: attachment"));
+
+        server.set_cache_control("");
+        let response = generate_response(
+            &get_request("/app.3f9a.js", &[]),
+            &server,
+        )
+        .unwrap();
+        assert_eq!(header_value(&response, "Cache-Control"), None);
+    }
+
+    #[test]
+    fn test_head_with_range() {
+        let temp_dir = setup_test_directory();
+        let server = test_server(temp_dir.path());
+        let head = |headers: &[(&str, &str)]| {
+            let mut request = get_request("/index.html", headers);
+            request.method = "HEAD".to_string();
+            generate_response(&request, &server).unwrap()
+        };
+
+        let response = head(&[]);
+        assert_eq!(response.status_code, 200);
+        assert_eq!(
+            header_value(&response, "Content-Length"),
+            Some("39")
+        );
+        assert!(response.body.is_empty());
+
+        let response = head(&[("Range", "bytes=0-4")]);
+        assert_eq!(response.status_code, 206);
+        assert_eq!(
+            header_value(&response, "Content-Range"),
+            Some("bytes 0-4/39")
+        );
+        assert_eq!(
+            header_value(&response, "Content-Length"),
+            Some("5")
+        );
+        assert!(response.body.is_empty());
+
+        let response = head(&[("Range", "bytes=100-")]);
+        assert_eq!(response.status_code, 416);
+        assert_eq!(
+            header_value(&response, "Content-Range"),
+            Some("bytes */39")
+        );
+        assert!(response.body.is_empty());
+    }
+
+    #[test]
+    fn test_last_modified_conditionals() {
+        let temp_dir = setup_test_directory();
+        let server = test_server(temp_dir.path());
+        let response = generate_response(
+            &get_request("/index.html", &[]),
+            &server,
+        )
+        .unwrap();
+        let last_modified = header_value(&response, "Last-Modified")
+            .unwrap()
+            .to_string();
+        assert!(last_modified.ends_with(" GMT"));
+
+        // Not modified since the date it was last modified
+        let request = get_request(
+            "/index.html",
+            &[("If-Modified-Since", &last_modified)],
+        );
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(response.status_code, 304);
+        assert_eq!(
+            header_value(&response, "Last-Modified"),
+            Some(last_modified.as_str())
+        );
+        assert!(response.body.is_empty());
+
+        // Modified since an old date, and an invalid date is ignored
+        for since in ["Thu, 01 Jan 1970 00:00:00 GMT", "not a date"] {
+            let request = get_request(
+                "/index.html",
+                &[("If-Modified-Since", since)],
+            );
+            let response =
+                generate_response(&request, &server).unwrap();
+            assert_eq!(response.status_code, 200, "{}", since);
+        }
+
+        // If-None-Match takes precedence over If-Modified-Since
+        let request = get_request(
+            "/index.html",
+            &[
+                ("If-None-Match", "\"stale\""),
+                ("If-Modified-Since", &last_modified),
+            ],
+        );
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(response.status_code, 200);
+
+        // Changed since the client's copy, in any of the date formats
+        for since in [
+            "Sun, 06 Nov 1994 08:49:37 GMT",
+            "Sunday, 06-Nov-94 08:49:37 GMT",
+            "Sun Nov  6 08:49:37 1994",
+        ] {
+            let request = get_request(
+                "/index.html",
+                &[("If-Unmodified-Since", since)],
+            );
+            let response =
+                generate_response(&request, &server).unwrap();
+            assert_eq!(response.status_code, 412, "{}", since);
+        }
+        let request = get_request(
+            "/index.html",
+            &[("If-Unmodified-Since", &last_modified)],
+        );
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(response.status_code, 200);
+
+        // The zero-copy path sends the header too
+        let raw =
+            roundtrip(&server, b"GET /index.html HTTP/1.1\r\n\r\n");
+        assert!(String::from_utf8_lossy(&raw).contains(&format!(
+            "Last-Modified: {}\r\n",
+            last_modified
+        )));
     }
 
     #[test]
-    fn test_get_content_type() {
+    fn test_evaluate_preconditions_ordering() {
+        let etag = "\"abc\"";
+        let modified = parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT");
+        let earlier = "Sat, 05 Nov 1994 08:49:37 GMT";
+        let later = "Mon, 07 Nov 1994 08:49:37 GMT";
+        let evaluate = |request: &Request| {
+            evaluate_preconditions(request, etag, modified)
+        };
+
         assert_eq!(
-            get_content_type(Path::new("test.html")),
-            "text/html"
+            evaluate(&get_request("/", &[])),
+            Precondition::Proceed
         );
-        assert_eq!(
-            get_content_type(Path::new("style.css")),
-            "text/css"
+
+        // A failed If-Unmodified-Since wins over a matching If-None-Match
+        let request = get_request(
+            "/",
+            &[
+                ("If-None-Match", etag),
+                ("If-Unmodified-Since", earlier),
+            ],
+        );
+        assert_eq!(evaluate(&request), Precondition::Failed);
+
+        // A stale If-None-Match overrides a fresh If-Modified-Since
+        let request = get_request(
+            "/",
+            &[
+                ("If-None-Match", "\"old\""),
+                ("If-Modified-Since", later),
+            ],
+        );
+        assert_eq!(evaluate(&request), Precondition::Proceed);
+
+        // A matching If-None-Match overrides a stale If-Modified-Since
+        let request = get_request(
+            "/",
+            &[("If-None-Match", etag), ("If-Modified-Since", earlier)],
+        );
+        assert_eq!(evaluate(&request), Precondition::NotModified);
+
+        // Both date conditions hold: not modified
+        let request = get_request(
+            "/",
+            &[
+                ("If-Unmodified-Since", later),
+                ("If-Modified-Since", later),
+            ],
+        );
+        assert_eq!(evaluate(&request), Precondition::NotModified);
+
+        // Other methods fail on a matching If-None-Match and ignore If-Modified-Since
+        let mut request = get_request("/", &[("If-None-Match", "*")]);
+        request.method = "POST".to_string();
+        assert_eq!(evaluate(&request), Precondition::Failed);
+        let mut request =
+            get_request("/", &[("If-Modified-Since", later)]);
+        request.method = "POST".to_string();
+        assert_eq!(evaluate(&request), Precondition::Proceed);
+
+        // A matching If-Match takes the place of If-Unmodified-Since
+        let request = get_request(
+            "/",
+            &[("If-Match", etag), ("If-Unmodified-Since", earlier)],
+        );
+        assert_eq!(evaluate(&request), Precondition::Proceed);
+        let request = get_request(
+            "/",
+            &[("If-Match", "\"old\""), ("If-Unmodified-Since", later)],
+        );
+        assert_eq!(evaluate(&request), Precondition::Failed);
+
+        // Date conditions are ignored without a modification time
+        let request = get_request(
+            "/",
+            &[
+                ("If-Unmodified-Since", earlier),
+                ("If-Modified-Since", later),
+            ],
         );
         assert_eq!(
-            get_content_type(Path::new("script.js")),
-            "application/javascript"
+            evaluate_preconditions(&request, etag, None),
+            Precondition::Proceed
+        );
+    }
+
+    #[test]
+    fn test_max_path_length() {
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+        server.set_max_path_length(11);
+
+        // "/index.html" is exactly at the limit
+        let response = generate_response(
+            &get_request("/index.html", &[]),
+            &server,
+        )
+        .unwrap();
+        assert_eq!(response.status_code, 200);
+
+        let response = generate_response(
+            &get_request("/index.htmlx", &[]),
+            &server,
+        )
+        .unwrap();
+        assert_eq!(response.status_code, 414);
+        assert_eq!(response.status_text, "URI Too Long");
+
+        // Only the decoded path counts, not the query string or escapes
+        let query = format!("/index.html?q={}", "a".repeat(100));
+        let response =
+            generate_response(&get_request(&query, &[]), &server)
+                .unwrap();
+        assert_ne!(response.status_code, 414);
+        let response = generate_response(
+            &get_request("/%69ndex.html", &[]),
+            &server,
+        )
+        .unwrap();
+        assert_ne!(response.status_code, 414);
+        assert_eq!(decode_path("/%69ndex.html%2"), b"/index.html%2");
+
+        // The zero-copy path and other methods are limited too
+        let raw = roundtrip(
+            &server,
+            b"GET /subdir/index.html HTTP/1.1\r\n\r\n",
         );
+        assert!(String::from_utf8_lossy(&raw)
+            .starts_with("HTTP/1.1 414 URI Too Long\r\n"));
+        let mut request = get_request("/subdir/index.html", &[]);
+        request.method = "DELETE".to_string();
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(response.status_code, 414);
+
+        // Debug mode explains the rejection like any other error
+        server.set_debug_errors(true);
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(response.status_code, 414);
+        let details: serde_json::Value =
+            serde_json::from_slice(&response.body).unwrap();
+        assert_eq!(details["kind"], "uri_too_long");
+        assert_eq!(details["message"], "Path longer than 11 bytes");
+    }
+
+    #[test]
+    fn test_if_match() {
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+        let response = generate_response(
+            &get_request("/index.html", &[]),
+            &server,
+        )
+        .unwrap();
+        let etag = header_value(&response, "ETag").unwrap().to_string();
+        let status = |server: &Server, if_match: &str| {
+            let request =
+                get_request("/index.html", &[("If-Match", if_match)]);
+            generate_response(&request, server).unwrap().status_code
+        };
+
+        assert_eq!(status(&server, &etag), 200);
         assert_eq!(
-            get_content_type(Path::new("data.json")),
-            "application/json"
+            status(&server, &format!("\"other\", {}", etag)),
+            200
+        );
+        assert_eq!(status(&server, "*"), 200);
+        assert_eq!(status(&server, "\"other\""), 412);
+
+        // The comparison is strong, so weak tags never match
+        assert_eq!(status(&server, &format!("W/{}", etag)), 412);
+        server.set_weak_etags(true);
+        assert_eq!(status(&server, &format!("W/{}", etag)), 412);
+        assert_eq!(status(&server, "*"), 200);
+
+        // The zero-copy path evaluates it too
+        let raw = roundtrip(
+            &server,
+            b"GET /index.html HTTP/1.1\r\nIf-Match: \"other\"\r\n\r\n",
         );
+        assert!(String::from_utf8_lossy(&raw)
+            .starts_with("HTTP/1.1 412 Precondition Failed\r\n"));
+    }
+
+    #[test]
+    fn test_if_none_match() {
+        let temp_dir = setup_test_directory();
+        let server = test_server(temp_dir.path());
+        let etag = header_value(
+            &generate_response(
+                &get_request("/index.html", &[]),
+                &server,
+            )
+            .unwrap(),
+            "ETag",
+        )
+        .unwrap()
+        .to_string();
+
+        let mut request =
+            get_request("/index.html", &[("If-None-Match", &etag)]);
+        request.method = "HEAD".to_string();
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(response.status_code, 304);
         assert_eq!(
-            get_content_type(Path::new("image.png")),
-            "image/png"
+            header_value(&response, "ETag"),
+            Some(etag.as_str())
+        );
+        assert!(response.body.is_empty());
+
+        // Weak comparison, with the tag somewhere in a list
+        let weak = format!("\"other\", W/{}", etag);
+        let request =
+            get_request("/index.html", &[("If-None-Match", &weak)]);
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(response.status_code, 304);
+
+        let request = get_request(
+            "/index.html",
+            &[("If-None-Match", "\"stale\"")],
         );
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(response.status_code, 200);
+
+        // The wildcard matches any existing file, but not a missing one
+        let request =
+            get_request("/index.html", &[("If-None-Match", "*")]);
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(response.status_code, 304);
         assert_eq!(
-            get_content_type(Path::new("photo.jpg")),
-            "image/jpeg"
+            header_value(&response, "ETag"),
+            Some(etag.as_str())
+        );
+
+        let request =
+            get_request("/missing.html", &[("If-None-Match", "*")]);
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(response.status_code, 404);
+
+        // The zero-copy path defers to the conditional logic
+        let raw = format!(
+            "GET /index.html HTTP/1.1\r\nIf-None-Match: {}\r\n\r\n",
+            etag
+        );
+        let response = roundtrip(&server, raw.as_bytes());
+        assert!(String::from_utf8_lossy(&response)
+            .starts_with("HTTP/1.1 304 Not Modified"));
+    }
+
+    #[test]
+    fn test_weak_etags() {
+        let temp_dir = setup_test_directory();
+        fs::write(temp_dir.path().join("data.bin"), b"0123456789")
+            .unwrap();
+        let mut server = test_server(temp_dir.path());
+        server.set_weak_etags(true);
+
+        let full =
+            generate_response(&get_request("/data.bin", &[]), &server)
+                .unwrap();
+        let etag = header_value(&full, "ETag").unwrap().to_string();
+        assert!(etag.starts_with("W/\""));
+
+        // The zero-copy path sends the same weak tag
+        let raw = roundtrip(&server, b"GET /data.bin HTTP/1.1\r\n\r\n");
+        assert!(String::from_utf8_lossy(&raw)
+            .contains(&format!("ETag: {}\r\n", etag)));
+
+        // A weak tag matches for a conditional GET, with or without the prefix
+        for tag in [etag.as_str(), etag.trim_start_matches("W/")] {
+            let request =
+                get_request("/data.bin", &[("If-None-Match", tag)]);
+            let response =
+                generate_response(&request, &server).unwrap();
+            assert_eq!(response.status_code, 304);
+        }
+
+        // ...but never satisfies If-Range, so the full file is sent
+        for tag in [etag.as_str(), etag.trim_start_matches("W/")] {
+            let request = get_request(
+                "/data.bin",
+                &[("Range", "bytes=2-5"), ("If-Range", tag)],
+            );
+            let response =
+                generate_response(&request, &server).unwrap();
+            assert_eq!(response.status_code, 200);
+            assert_eq!(response.body, b"0123456789");
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_list_files() {
+        let temp_dir = setup_test_directory();
+        let root = temp_dir.path();
+        fs::create_dir_all(root.join("docs/guide")).unwrap();
+        fs::write(root.join("docs/guide/intro.html"), b"intro")
+            .unwrap();
+        fs::create_dir(root.join(".git")).unwrap();
+        fs::write(root.join(".git/config"), b"secret").unwrap();
+        fs::write(root.join(".env"), b"secret").unwrap();
+        fs::create_dir(root.join(".well-known")).unwrap();
+        fs::write(root.join(".well-known/security.txt"), b"contact")
+            .unwrap();
+        // A loop back to the root and a dangling link
+        std::os::unix::fs::symlink(root, root.join("docs/loop"))
+            .unwrap();
+        std::os::unix::fs::symlink(
+            root.join("nowhere"),
+            root.join("dangling"),
+        )
+        .unwrap();
+
+        let mut server = test_server(root);
+        let files = server.list_files().unwrap();
+        assert!(files.contains(&PathBuf::from("index.html")));
+        assert!(files.contains(&PathBuf::from("docs/guide/intro.html")));
+        assert!(
+            files.contains(&PathBuf::from(".well-known/security.txt"))
+        );
+        assert!(!files.iter().any(|path| path.starts_with(".git")
+            || path == Path::new(".env")
+            || path.starts_with("dangling")));
+
+        server.set_block_dotfiles(false);
+        let files = server.list_files().unwrap();
+        assert!(files.contains(&PathBuf::from(".git/config")));
+        assert!(files.contains(&PathBuf::from(".env")));
+    }
+
+    #[test]
+    fn test_not_acceptable_encoding() {
+        let temp_dir = setup_test_directory();
+        fs::write(
+            temp_dir.path().join("image.png"),
+            b"not really a png",
+        )
+        .unwrap();
+        let server = test_server(temp_dir.path());
+
+        for (path, accept_encoding) in [
+            ("/image.png", "gzip, identity;q=0"),
+            ("/index.html", "*;q=0"),
+        ] {
+            let request = get_request(
+                path,
+                &[("Accept-Encoding", accept_encoding)],
+            );
+            let response =
+                generate_response(&request, &server).unwrap();
+            assert_eq!(response.status_code, 406, "{}", path);
+            assert_eq!(
+                header_value(&response, "Vary"),
+                Some("Accept-Encoding")
+            );
+        }
+
+        // The zero-copy path refuses too
+        let raw = roundtrip(
+            &server,
+            b"GET /image.png HTTP/1.1\r\nAccept-Encoding: identity;q=0\r\n\r\n",
+        );
+        assert!(raw.starts_with(b"HTTP/1.1 406 Not Acceptable\r\n"));
+
+        // The refused file is neither read nor cached
+        let mut server = test_server(temp_dir.path());
+        server.enable_file_cache(1024);
+        server.set_cache_status_header(true);
+        let refused =
+            get_request("/image.png", &[("Accept-Encoding", "*;q=0")]);
+        let response = generate_response(&refused, &server).unwrap();
+        assert_eq!(response.status_code, 406);
+        assert_eq!(header_value(&response, "X-Cache"), None);
+        let response =
+            generate_response(&get_request("/image.png", &[]), &server)
+                .unwrap();
+        assert_eq!(header_value(&response, "X-Cache"), Some("MISS"));
+
+        // Identity is acceptable unless refused
+        let request =
+            get_request("/image.png", &[("Accept-Encoding", "br")]);
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(response.status_code, 200);
+        assert_eq!(response.body, b"not really a png");
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip_response_varies_on_accept_encoding() {
+        use flate2::read::GzDecoder;
+        use std::io::Read;
+
+        let temp_dir = setup_test_directory();
+        fs::write(
+            temp_dir.path().join("image.png"),
+            b"not really a png",
+        )
+        .unwrap();
+        let server = test_server(temp_dir.path());
+
+        let request = get_request(
+            "/index.html",
+            &[("Accept-Encoding", "gzip, deflate")],
         );
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(response.status_code, 200);
         assert_eq!(
-            get_content_type(Path::new("animation.gif")),
-            "image/gif"
+            header_value(&response, "Content-Encoding"),
+            Some("gzip")
         );
         assert_eq!(
-            get_content_type(Path::new("icon.svg")),
-            "image/svg+xml"
+            header_value(&response, "Vary"),
+            Some("Accept-Encoding")
         );
+        assert!(header_value(&response, "ETag")
+            .unwrap()
+            .starts_with("W/"));
+        let mut body = Vec::new();
+        let _ = GzDecoder::new(response.body.as_slice())
+            .read_to_end(&mut body)
+            .unwrap();
+        assert_eq!(body, b"<html><body>Hello, World!</body></html>");
+
+        // The identity variant still tells caches that it varies
+        let response = generate_response(
+            &get_request("/index.html", &[]),
+            &server,
+        )
+        .unwrap();
+        assert_eq!(header_value(&response, "Content-Encoding"), None);
         assert_eq!(
-            get_content_type(Path::new("unknown.xyz")),
-            "application/octet-stream"
+            header_value(&response, "Vary"),
+            Some("Accept-Encoding")
+        );
+
+        // Binary types are never compressed
+        let request =
+            get_request("/image.png", &[("Accept-Encoding", "gzip")]);
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(header_value(&response, "Content-Encoding"), None);
+        assert_eq!(header_value(&response, "Vary"), None);
+
+        // The zero-copy path leaves compressible files to the in-memory path
+        let raw = roundtrip(
+            &server,
+            b"GET /index.html HTTP/1.1\r\nAccept-Encoding: gzip\r\n\r\n",
         );
+        let raw = String::from_utf8_lossy(&raw);
+        assert!(raw.contains("Content-Encoding: gzip\r\n"));
+        assert!(raw.contains("Vary: Accept-Encoding\r\n"));
+
+        // Without any enabled encoding, responses no longer vary
+        let mut server = server;
+        server.set_gzip(false);
+        #[cfg(feature = "brotli")]
+        server.set_brotli(false);
+        let request =
+            get_request("/index.html", &[("Accept-Encoding", "gzip")]);
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(header_value(&response, "Content-Encoding"), None);
+        assert_eq!(header_value(&response, "Vary"), None);
     }
 
+    #[cfg(feature = "gzip")]
     #[test]
-    fn test_generate_response() {
+    fn test_compression_settings() {
         let temp_dir = setup_test_directory();
-        let document_root = temp_dir.path();
-
-        // Test root request (should serve index.html)
-        let root_request = Request {
-            method: "GET".to_string(),
-            path: "/".to_string(),
-            version: "HTTP/1.1".to_string(),
+        fs::write(temp_dir.path().join("data.json"), b"{\"a\": 1}")
+            .unwrap();
+        let mut server = test_server(temp_dir.path());
+        let encoding = |server: &Server, path: &str| {
+            let request =
+                get_request(path, &[("Accept-Encoding", "gzip")]);
+            let response = generate_response(&request, server).unwrap();
+            (
+                header_value(&response, "Content-Encoding")
+                    .map(str::to_string),
+                header_value(&response, "Vary").map(str::to_string),
+            )
         };
 
-        let root_response =
-            generate_response(&root_request, document_root).unwrap();
-        assert_eq!(root_response.status_code, 200);
-        assert_eq!(root_response.status_text, "OK");
-        assert!(root_response
-            .body
-            .starts_with(b"<html><body>Hello, World!</body></html>"));
+        // index.html is 39 bytes, below the threshold
+        server.set_compression(40, 9);
+        assert_eq!(encoding(&server, "/index.html"), (None, None));
+        server.set_compression(39, 1);
+        assert_eq!(
+            encoding(&server, "/index.html").0.as_deref(),
+            Some("gzip")
+        );
 
-        // Test specific file request
-        let file_request = Request {
-            method: "GET".to_string(),
-            path: "/index.html".to_string(),
-            version: "HTTP/1.1".to_string(),
-        };
+        // Types off the allowlist are sent unencoded
+        server.set_compressible_types(&["application/json"]);
+        assert_eq!(encoding(&server, "/index.html"), (None, None));
+        server.set_compression(0, 6);
+        assert_eq!(
+            encoding(&server, "/data.json").0.as_deref(),
+            Some("gzip")
+        );
+    }
 
-        let file_response =
-            generate_response(&file_request, document_root).unwrap();
-        assert_eq!(file_response.status_code, 200);
-        assert_eq!(file_response.status_text, "OK");
-        assert!(file_response
-            .body
-            .starts_with(b"<html><body>Hello, World!</body></html>"));
+    #[cfg(any(feature = "gzip", feature = "brotli"))]
+    #[test]
+    #[should_panic(expected = "level must be at most")]
+    fn test_compression_level_out_of_range() {
+        let mut server = Server::new("127.0.0.1:0", ".");
+        server.set_compression(0, 12);
+    }
 
-        // Test subdirectory index request
-        let subdir_request = Request {
-            method: "GET".to_string(),
-            path: "/subdir/".to_string(),
-            version: "HTTP/1.1".to_string(),
-        };
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_range_ignored_when_compressing() {
+        use flate2::read::GzDecoder;
+        use std::io::Read;
 
-        let subdir_response =
-            generate_response(&subdir_request, document_root).unwrap();
-        assert_eq!(subdir_response.status_code, 200);
-        assert_eq!(subdir_response.status_text, "OK");
-        assert!(subdir_response.body.starts_with(
-            b"<html><body>Subdirectory Index</body></html>"
-        ));
+        let temp_dir = setup_test_directory();
+        let server = test_server(temp_dir.path());
 
-        // Test non-existent file request
-        let not_found_request = Request {
-            method: "GET".to_string(),
-            path: "/nonexistent.html".to_string(),
-            version: "HTTP/1.1".to_string(),
-        };
+        let request = get_request(
+            "/index.html",
+            &[("Range", "bytes=0-4"), ("Accept-Encoding", "gzip")],
+        );
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(response.status_code, 200);
+        assert_eq!(header_value(&response, "Content-Range"), None);
+        assert_eq!(
+            header_value(&response, "Accept-Ranges"),
+            Some("none")
+        );
+        let mut body = Vec::new();
+        let _ = GzDecoder::new(response.body.as_slice())
+            .read_to_end(&mut body)
+            .unwrap();
+        assert_eq!(body, b"<html><body>Hello, World!</body></html>");
 
-        let not_found_response =
-            generate_response(&not_found_request, document_root)
+        // Without compression the range is still served
+        let request = get_request(
+            "/index.html",
+            &[("Range", "bytes=0-4"), ("Accept-Encoding", "identity")],
+        );
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(response.status_code, 206);
+        assert_eq!(response.body, b"<html");
+        assert_eq!(
+            header_value(&response, "Accept-Ranges"),
+            Some("bytes")
+        );
+    }
+
+    #[cfg(feature = "brotli")]
+    #[test]
+    fn test_brotli_response() {
+        use std::io::Read;
+
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+
+        let request = get_request(
+            "/index.html",
+            &[("Accept-Encoding", "gzip;q=0.8, br")],
+        );
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(
+            header_value(&response, "Content-Encoding"),
+            Some("br")
+        );
+        assert_eq!(
+            header_value(&response, "Vary"),
+            Some("Accept-Encoding")
+        );
+        let mut body = Vec::new();
+        let _ =
+            brotli::Decompressor::new(response.body.as_slice(), 4096)
+                .read_to_end(&mut body)
                 .unwrap();
-        assert_eq!(not_found_response.status_code, 404);
-        assert_eq!(not_found_response.status_text, "NOT FOUND");
-        assert!(not_found_response
-            .body
-            .starts_with(b"<html><body>404 Not Found</body></html>"));
+        assert_eq!(body, b"<html><body>Hello, World!</body></html>");
 
-        // Test directory traversal attempt
-        let traversal_request = Request {
-            method: "GET".to_string(),
-            path: "/../outside.html".to_string(),
-            version: "HTTP/1.1".to_string(),
-        };
+        // A refused coding is never used
+        let request = get_request(
+            "/index.html",
+            &[("Accept-Encoding", "br;q=0")],
+        );
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(header_value(&response, "Content-Encoding"), None);
 
-        let traversal_response =
-            generate_response(&traversal_request, document_root);
-        assert!(matches!(
-            traversal_response,
-            Err(ServerError::Forbidden(_))
+        server.set_brotli(false);
+        let request =
+            get_request("/index.html", &[("Accept-Encoding", "br")]);
+        let response = generate_response(&request, &server).unwrap();
+        assert_eq!(header_value(&response, "Content-Encoding"), None);
+    }
+
+    #[test]
+    fn test_header_rules() {
+        let temp_dir = setup_test_directory();
+        fs::create_dir(temp_dir.path().join("assets")).unwrap();
+        fs::write(temp_dir.path().join("assets/app.js"), b"app()")
+            .unwrap();
+        let mut server = test_server(temp_dir.path());
+        server.add_header_rule("/", "X-Site", "docs");
+        server.add_header_rule(
+            "/assets/",
+            "Cache-Control",
+            "immutable",
+        );
+        server.add_header_rule("/assets/", "X-Asset", "1");
+        server.add_header_rule("/assets/app", "X-Asset", "2");
+
+        let response = generate_response(
+            &get_request("/assets/app.js", &[]),
+            &server,
+        )
+        .unwrap();
+        assert_eq!(header_value(&response, "X-Site"), Some("docs"));
+        assert_eq!(
+            header_value(&response, "Cache-Control"),
+            Some("immutable")
+        );
+        // The later rule wins, and no header is duplicated
+        assert_eq!(header_value(&response, "X-Asset"), Some("2"));
+        assert_eq!(
+            response
+                .headers
+                .iter()
+                .filter(|(name, _)| name == "Cache-Control"
+                    || name == "X-Asset")
+                .count(),
+            2
+        );
+
+        // Non-matching prefixes leave the defaults alone
+        let response = generate_response(
+            &get_request("/index.html", &[]),
+            &server,
+        )
+        .unwrap();
+        assert_eq!(header_value(&response, "X-Site"), Some("docs"));
+        assert_eq!(
+            header_value(&response, "Cache-Control"),
+            Some(DEFAULT_CACHE_CONTROL)
+        );
+        assert_eq!(header_value(&response, "X-Asset"), None);
+
+        // The zero-copy path applies the same rules
+        let raw =
+            roundtrip(&server, b"GET /assets/app.js HTTP/1.1\r\n\r\n");
+        let raw = String::from_utf8_lossy(&raw);
+        assert!(raw.contains("Cache-Control: immutable\r\n"));
+        assert!(raw.contains("X-Asset: 2\r\n"));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid header rule")]
+    fn test_header_rule_rejects_invalid_value() {
+        let mut server = Server::new("127.0.0.1:0", ".");
+        server.add_header_rule("/", "X-Bad", "a\r\nb");
+    }
+
+    #[test]
+    fn test_keep_alive_request_cap() {
+        use std::io::Read;
+
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+        server.set_max_requests_per_connection(3);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client =
+            TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        // Five pipelined requests; the connection stays open for writing
+        client
+            .write_all(&b"GET /index.html HTTP/1.1\r\n\r\n".repeat(5))
+            .unwrap();
+
+        let (stream, _) = listener.accept().unwrap();
+        handle_connection(stream, &server, &ShutdownHandle::new())
+            .unwrap();
+
+        let mut response = String::new();
+        let _ = client.read_to_string(&mut response).unwrap();
+        let responses: Vec<&str> = response
+            .split("HTTP/1.1 ")
+            .filter(|r| !r.is_empty())
+            .collect();
+        assert_eq!(responses.len(), 3);
+        assert!(responses.iter().all(|r| r.starts_with("200 OK")
+            && r.contains("Content-Length: 39\r\n")));
+        assert!(!responses[1].contains("Connection: close"));
+        assert!(responses[2].contains("Connection: close\r\n"));
+    }
+
+    #[test]
+    fn test_http10_closes_without_keep_alive() {
+        let temp_dir = setup_test_directory();
+        let server = test_server(temp_dir.path());
+
+        let response = roundtrip(
+            &server,
+            b"GET /index.html HTTP/1.0\r\n\r\nGET /index.html HTTP/1.0\r\n\r\n",
+        );
+        let response = String::from_utf8_lossy(&response);
+        assert_eq!(response.matches("HTTP/1.1 200 OK").count(), 1);
+        assert!(response.contains("Connection: close\r\n"));
+
+        let response = roundtrip(
+            &server,
+            b"GET /index.html HTTP/1.0\r\nConnection: keep-alive\r\n\r\nGET /index.html HTTP/1.0\r\n\r\n",
+        );
+        let response = String::from_utf8_lossy(&response);
+        assert_eq!(response.matches("HTTP/1.1 200 OK").count(), 2);
+        assert!(response.contains("Connection: keep-alive\r\n"));
+    }
+
+    #[test]
+    fn test_panicking_handler_is_isolated() {
+        use std::io::Read;
+
+        let temp_dir = setup_test_directory();
+        let server = test_server(temp_dir.path());
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let mut client = TcpStream::connect(addr).unwrap();
+        client
+            .write_all(b"GET /index.html HTTP/1.1\r\n\r\n")
+            .unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+        let (stream, _) = listener.accept().unwrap();
+        let handle = thread::spawn(move || {
+            isolate_connection(stream, |_| panic!("handler bug"));
+        });
+        assert!(handle.join().is_ok());
+
+        let mut response = String::new();
+        let _ = client.read_to_string(&mut response).unwrap();
+        assert!(
+            response.starts_with("HTTP/1.1 500 Internal Server Error")
+        );
+        assert!(!response.contains("handler bug"));
+
+        // The server keeps serving other connections
+        let response =
+            roundtrip(&server, b"GET /index.html HTTP/1.1\r\n\r\n");
+        assert!(response.starts_with(b"HTTP/1.1 200 OK"));
+    }
+
+    #[test]
+    fn test_server_header() {
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+
+        let response =
+            roundtrip(&server, b"GET /index.html HTTP/1.1\r\n\r\n");
+        let expected = format!(
+            "Server: http-handle/{}\r\n",
+            env!("CARGO_PKG_VERSION")
+        );
+        assert!(String::from_utf8_lossy(&response).contains(&expected));
+
+        server.set_server_header(Some("example/1.0".to_string()));
+        let response =
+            roundtrip(&server, b"GET /missing HTTP/1.1\r\n\r\n");
+        assert!(String::from_utf8_lossy(&response)
+            .contains("Server: example/1.0\r\n"));
+
+        server.set_server_header(None);
+        let response =
+            roundtrip(&server, b"GET /index.html HTTP/1.1\r\n\r\n");
+        assert!(!String::from_utf8_lossy(&response).contains("Server:"));
+    }
+
+    #[test]
+    fn test_security_headers() {
+        let temp_dir = setup_test_directory();
+        let mut server = test_server(temp_dir.path());
+
+        let mut response = generate_response(
+            &get_request("/index.html", &[]),
+            &server,
+        )
+        .unwrap();
+        server.apply_response_headers(&mut response);
+        assert_eq!(header_value(&response, "X-Frame-Options"), None);
+
+        server.enable_security_headers();
+        server.set_csp("default-src 'self'");
+        let mut response = generate_response(
+            &get_request("/index.html", &[]),
+            &server,
+        )
+        .unwrap();
+        // A handler relaxes one header; the others still apply
+        response.add_header("X-Frame-Options", "SAMEORIGIN");
+        server.apply_response_headers(&mut response);
+
+        assert_eq!(
+            header_value(&response, "X-Content-Type-Options"),
+            Some("nosniff")
+        );
+        assert_eq!(
+            header_value(&response, "X-Frame-Options"),
+            Some("SAMEORIGIN")
+        );
+        assert_eq!(
+            header_value(&response, "Referrer-Policy"),
+            Some("no-referrer")
+        );
+        assert_eq!(
+            header_value(&response, "Content-Security-Policy"),
+            Some("default-src 'self'")
+        );
+
+        let raw =
+            roundtrip(&server, b"GET /index.html HTTP/1.1\r\n\r\n");
+        let raw = String::from_utf8_lossy(&raw);
+        assert!(raw.contains("X-Frame-Options: DENY\r\n"));
+        assert!(raw.contains(
+            "Content-Security-Policy: default-src 'self'\r\n"
         ));
     }
 }
@@ -0,0 +1,213 @@
+// src/tls.rs
+
+//! TLS module for serving connections over HTTPS with rustls.
+//!
+//! Each accepted TCP connection is wrapped in a [`TlsStream`] performing the handshake
+//! lazily, on the thread that serves the connection or, over the connection limit,
+//! answers it with `503 Service Unavailable`. The accept loop therefore never waits
+//! for a handshake, which is bounded by the read timeout of the server. Requests are
+//! then served exactly as over plain TCP, except that file bodies are always copied
+//! through the TLS session rather than sent with `sendfile(2)`. Unless the
+//! configuration lists its own protocols, the server advertises `http/1.1` through
+//! ALPN.
+//!
+//! This module is only available with the `tls` feature.
+
+use crate::error::ServerError;
+use crate::server::{ClientStream, Handler, Server};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::{ServerConfig, ServerConnection, StreamOwned};
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::path::Path;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+#[cfg(target_os = "linux")]
+use std::os::unix::io::RawFd;
+
+/// The ALPN identifier of HTTP/1.1, advertised unless configured otherwise.
+pub(crate) const HTTP_1_1: &[u8] = b"http/1.1";
+
+/// Loads a server configuration from PEM files.
+///
+/// # Arguments
+///
+/// * `cert_path` - The certificate chain, leaf certificate first.
+/// * `key_path` - The private key of the leaf certificate.
+///
+/// # Returns
+///
+/// The configuration, without client authentication, or a `ServerError` if a file
+/// cannot be read or the certificate and key are rejected.
+pub(crate) fn load_config(
+    cert_path: &Path,
+    key_path: &Path,
+) -> Result<ServerConfig, ServerError> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| {
+            ServerError::Custom(format!(
+                "Failed to load certificates from {}: {}",
+                cert_path.display(),
+                e
+            ))
+        })?;
+    let key = PrivateKeyDer::from_pem_file(key_path).map_err(|e| {
+        ServerError::Custom(format!(
+            "Failed to load private key from {}: {}",
+            key_path.display(),
+            e
+        ))
+    })?;
+    ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| {
+            ServerError::Custom(format!(
+                "Invalid TLS certificate: {}",
+                e
+            ))
+        })
+}
+
+/// Refuses to serialize a TLS configuration.
+///
+/// Certificates and keys are not part of the serialized server, and a server
+/// deserialized without them would serve plain HTTP, so serialization fails instead.
+///
+/// # Arguments
+///
+/// * `_config` - The configuration of the server, always set when this is called.
+/// * `_serializer` - The serializer of the server.
+///
+/// # Returns
+///
+/// A serialization error.
+pub(crate) fn serialize_config<S: serde::Serializer>(
+    _config: &Option<Handler<ServerConfig>>,
+    _serializer: S,
+) -> Result<S::Ok, S::Error> {
+    Err(serde::ser::Error::custom(
+        "a TLS configuration cannot be serialized; load it again with \
+         Server::new_tls or Server::with_tls_config",
+    ))
+}
+
+/// A TLS connection to a client.
+///
+/// Clones share the TLS session, so the request reader and the response writer of a
+/// connection see the same stream of decrypted data.
+pub(crate) struct TlsStream {
+    /// The TLS session over the connection.
+    session: Arc<Mutex<StreamOwned<ServerConnection, TcpStream>>>,
+    /// A handle to the underlying socket, for its options.
+    socket: TcpStream,
+}
+
+impl TlsStream {
+    /// Starts a server-side TLS session over an accepted connection.
+    ///
+    /// # Arguments
+    ///
+    /// * `stream` - The accepted TCP connection.
+    /// * `config` - The configuration of the session.
+    ///
+    /// # Returns
+    ///
+    /// The stream, whose handshake runs on first use, or an I/O error.
+    pub(crate) fn new(
+        stream: TcpStream,
+        config: Arc<ServerConfig>,
+    ) -> io::Result<Self> {
+        let connection = ServerConnection::new(config)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        let socket = stream.try_clone()?;
+        Ok(TlsStream {
+            session: Arc::new(Mutex::new(StreamOwned::new(
+                connection, stream,
+            ))),
+            socket,
+        })
+    }
+
+    /// Locks the shared TLS session.
+    fn session(
+        &self,
+    ) -> MutexGuard<'_, StreamOwned<ServerConnection, TcpStream>> {
+        self.session.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Read for TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.session().read(buf)
+    }
+}
+
+impl Write for TlsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.session().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.session().flush()
+    }
+}
+
+impl ClientStream for TlsStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        Ok(TlsStream {
+            session: Arc::clone(&self.session),
+            socket: self.socket.try_clone()?,
+        })
+    }
+
+    fn set_read_timeout(
+        &self,
+        timeout: Option<Duration>,
+    ) -> io::Result<()> {
+        self.socket.set_read_timeout(timeout)
+    }
+
+    fn set_write_timeout(
+        &self,
+        timeout: Option<Duration>,
+    ) -> io::Result<()> {
+        self.socket.set_write_timeout(timeout)
+    }
+
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        if how != Shutdown::Read {
+            // Tell the client the response is complete, not truncated
+            let mut session = self.session();
+            session.conn.send_close_notify();
+            session.flush()?;
+        }
+        self.socket.shutdown(how)
+    }
+
+    fn configure(&self, server: &Server) -> io::Result<()> {
+        self.socket.configure(server)
+    }
+
+    fn remote_addr(&self) -> Option<String> {
+        self.socket.remote_addr()
+    }
+
+    #[cfg(target_os = "linux")]
+    fn raw_fd(&self) -> Option<RawFd> {
+        // Data must pass through the TLS session
+        None
+    }
+}
+
+impl fmt::Debug for TlsStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsStream")
+            .field("socket", &self.socket)
+            .finish_non_exhaustive()
+    }
+}
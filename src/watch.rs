@@ -0,0 +1,133 @@
+// src/watch.rs
+
+//! File watching module for reloading browsers when served files change.
+//!
+//! A watcher registered with the operating system reports changes under the document
+//! roots. Changes are debounced: a burst of writes, such as a site generator rewriting
+//! its output, triggers a single reload once no further change has been seen for
+//! [`DEBOUNCE`]. Events that only record an access are ignored.
+//!
+//! This module is only available with the `watch` feature.
+
+use crate::livereload::ReloadHandle;
+use log::{debug, warn};
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::fmt;
+use std::io;
+use std::path::Path;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// How long the file system must stay quiet after a change before a reload is sent.
+pub(crate) const DEBOUNCE: Duration = Duration::from_millis(100);
+
+/// A running watch over the document roots, stopped when dropped.
+pub(crate) struct FileWatcher {
+    /// The operating system watcher, delivering events to the debounce thread.
+    _watcher: RecommendedWatcher,
+}
+
+impl FileWatcher {
+    /// Starts watching directories, requesting a reload after each burst of changes.
+    ///
+    /// # Arguments
+    ///
+    /// * `roots` - The directories to watch, recursively.
+    /// * `reload` - The handle told about changes.
+    /// * `debounce` - How long to wait for further changes before reloading.
+    ///
+    /// # Returns
+    ///
+    /// The running watcher, or an I/O error if a directory cannot be watched.
+    pub(crate) fn start<'a, I>(
+        roots: I,
+        reload: ReloadHandle,
+        debounce: Duration,
+    ) -> io::Result<Self>
+    where
+        I: IntoIterator<Item = &'a Path>,
+    {
+        let (sender, changes) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(
+            move |event: notify::Result<notify::Event>| match event {
+                Ok(event)
+                    if !matches!(event.kind, EventKind::Access(_)) =>
+                {
+                    let _ = sender.send(());
+                }
+                Ok(_) => {}
+                Err(e) => warn!("File watch error: {}", e),
+            },
+        )
+        .map_err(watch_error)?;
+        for root in roots {
+            watcher
+                .watch(root, RecursiveMode::Recursive)
+                .map_err(watch_error)?;
+        }
+
+        // Ends once the watcher, and with it the sender, is dropped
+        let _ = thread::Builder::new()
+            .name("http-handle-watch".to_string())
+            .spawn(move || {
+                while changes.recv().is_ok() {
+                    while changes.recv_timeout(debounce).is_ok() {}
+                    debug!("Files changed, reloading browsers");
+                    reload.reload();
+                }
+            })?;
+        Ok(FileWatcher { _watcher: watcher })
+    }
+}
+
+impl fmt::Debug for FileWatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FileWatcher").finish_non_exhaustive()
+    }
+}
+
+/// Converts a watcher error into an I/O error.
+///
+/// # Arguments
+///
+/// * `error` - The error reported by the watcher.
+///
+/// # Returns
+///
+/// The I/O error, keeping the original one as its source.
+fn watch_error(error: notify::Error) -> io::Error {
+    match error.kind {
+        notify::ErrorKind::Io(e) => e,
+        _ => io::Error::new(io::ErrorKind::Other, error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::time::Instant;
+
+    #[test]
+    fn test_change_triggers_reload() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let page = temp_dir.path().join("index.html");
+        fs::write(&page, "<p>Before</p>").unwrap();
+        let reload = ReloadHandle::default();
+        let seen = reload.generation();
+        let _watcher = FileWatcher::start(
+            Some(temp_dir.path()),
+            reload.clone(),
+            Duration::from_millis(50),
+        )
+        .unwrap();
+
+        fs::write(&page, "<p>After</p>").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while reload.generation() == seen && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(reload.generation() > seen);
+    }
+}
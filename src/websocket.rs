@@ -0,0 +1,417 @@
+// src/websocket.rs
+
+//! WebSocket module for the opening handshake of RFC 6455.
+//!
+//! This module recognises requests asking to upgrade a connection to the WebSocket
+//! protocol, validates their `Sec-WebSocket-Key` and builds the
+//! `101 Switching Protocols` response carrying the matching `Sec-WebSocket-Accept`.
+//! Framing is not implemented: once the handshake is done, the connection is handed
+//! over as an [`UpgradedStream`] to a handler registered with
+//! [`Server::set_websocket_handler`](crate::Server::set_websocket_handler), which
+//! speaks the protocol itself.
+//!
+//! # Example
+//!
+//! ```rust
+//! use http_handle::websocket::accept_key;
+//!
+//! // The example handshake of RFC 6455, section 1.3
+//! assert_eq!(
+//!     accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+//!     "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+//! );
+//! ```
+
+use crate::error::ServerError;
+use crate::request::Request;
+use crate::response::Response;
+use crate::server::ShutdownHandle;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+/// The GUID appended to the client's key to compute the accept key (RFC 6455).
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The only WebSocket protocol version, sent in `Sec-WebSocket-Version`.
+const WEBSOCKET_VERSION: &str = "13";
+
+/// The alphabet of standard Base64 encoding.
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Computes the `Sec-WebSocket-Accept` value answering a `Sec-WebSocket-Key`.
+///
+/// The value is the Base64-encoded SHA-1 digest of the key followed by the
+/// WebSocket GUID, which proves to the client that the server understood the
+/// handshake.
+///
+/// # Arguments
+///
+/// * `key` - The `Sec-WebSocket-Key` sent by the client.
+///
+/// # Returns
+///
+/// The `Sec-WebSocket-Accept` header value.
+///
+/// # Examples
+///
+/// ```
+/// use http_handle::websocket::accept_key;
+///
+/// let accept = accept_key("dGhlIHNhbXBsZSBub25jZQ==");
+/// assert_eq!(accept, "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+/// ```
+pub fn accept_key(key: &str) -> String {
+    base64_encode(&sha1(
+        format!("{}{}", key, WEBSOCKET_GUID).as_bytes(),
+    ))
+}
+
+/// Checks whether a request asks to upgrade the connection to a WebSocket.
+///
+/// Such a request is a `GET` with `websocket` in its `Upgrade` header and `upgrade`
+/// among its `Connection` options. Its key and version are checked by
+/// [`handshake_response`].
+///
+/// # Arguments
+///
+/// * `request` - The request to check.
+///
+/// # Returns
+///
+/// `true` if the request is a WebSocket upgrade request.
+pub fn is_upgrade_request(request: &Request) -> bool {
+    request.method().eq_ignore_ascii_case("GET")
+        && request.has_connection_option("upgrade")
+        && request.header("Upgrade").map_or(false, |upgrade| {
+            upgrade.split(',').any(|protocol| {
+                protocol.trim().eq_ignore_ascii_case("websocket")
+            })
+        })
+}
+
+/// Builds the `101 Switching Protocols` response completing a WebSocket handshake.
+///
+/// # Arguments
+///
+/// * `request` - The upgrade request, as recognised by [`is_upgrade_request`].
+///
+/// # Returns
+///
+/// A `Result` containing the response, or `ServerError::InvalidRequest` if the request
+/// is not an upgrade request, does not ask for version 13 in `Sec-WebSocket-Version`,
+/// or has a `Sec-WebSocket-Key` that is not a Base64-encoded 16-byte value.
+///
+/// # Examples
+///
+/// ```
+/// use http_handle::request::Request;
+/// use http_handle::websocket::handshake_response;
+///
+/// let mut request = Request::new("GET", "/chat", "HTTP/1.1");
+/// request.add_header("Upgrade", "websocket");
+/// request.add_header("Connection", "Upgrade");
+/// request.add_header("Sec-WebSocket-Key", "dGhlIHNhbXBsZSBub25jZQ==");
+/// request.add_header("Sec-WebSocket-Version", "13");
+///
+/// let response = handshake_response(&request).unwrap();
+/// assert_eq!(response.status_code, 101);
+/// ```
+pub fn handshake_response(
+    request: &Request,
+) -> Result<Response, ServerError> {
+    if !is_upgrade_request(request) {
+        return Err(ServerError::invalid_request(
+            "Not a WebSocket upgrade request",
+        ));
+    }
+    if request.header("Sec-WebSocket-Version")
+        != Some(WEBSOCKET_VERSION)
+    {
+        return Err(ServerError::invalid_request(
+            "Unsupported WebSocket version",
+        ));
+    }
+    let key = request
+        .header("Sec-WebSocket-Key")
+        .map(str::trim)
+        .filter(|key| is_valid_key(key))
+        .ok_or_else(|| {
+            ServerError::invalid_request("Invalid Sec-WebSocket-Key")
+        })?;
+
+    let mut response =
+        Response::new(101, "Switching Protocols", Vec::new());
+    response.add_header("Upgrade", "websocket");
+    response.add_header("Connection", "Upgrade");
+    response.add_header("Sec-WebSocket-Accept", &accept_key(key));
+    Ok(response)
+}
+
+/// Checks whether a `Sec-WebSocket-Key` is a Base64-encoded 16-byte value.
+///
+/// # Arguments
+///
+/// * `key` - The key sent by the client.
+///
+/// # Returns
+///
+/// `true` if the key is 22 Base64 characters followed by `==`.
+fn is_valid_key(key: &str) -> bool {
+    key.len() == 24
+        && key.ends_with("==")
+        && key.bytes().take(22).all(|b| BASE64_ALPHABET.contains(&b))
+}
+
+/// Encodes bytes with standard, padded Base64.
+///
+/// # Arguments
+///
+/// * `data` - The bytes to encode.
+///
+/// # Returns
+///
+/// The Base64 text.
+fn base64_encode(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity((data.len() + 2) / 3 * 4);
+    for chunk in data.chunks(3) {
+        let bits =
+            chunk.iter().enumerate().fold(0u32, |bits, (i, &b)| {
+                bits | u32::from(b) << (16 - 8 * i)
+            });
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (bits >> (18 - 6 * i)) & 0x3f;
+                encoded
+                    .push(char::from(BASE64_ALPHABET[index as usize]));
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+/// Computes the SHA-1 digest of some bytes.
+///
+/// SHA-1 is only used here because the WebSocket handshake requires it; it is not
+/// collision resistant and must not be used for security.
+///
+/// # Arguments
+///
+/// * `data` - The bytes to hash.
+///
+/// # Returns
+///
+/// The 20-byte digest.
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut state: [u32; 5] = [
+        0x6745_2301,
+        0xEFCD_AB89,
+        0x98BA_DCFE,
+        0x1032_5476,
+        0xC3D2_E1F0,
+    ];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&(data.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut words = [0u32; 80];
+        for (word, bytes) in words.iter_mut().zip(block.chunks(4)) {
+            *word = u32::from_be_bytes([
+                bytes[0], bytes[1], bytes[2], bytes[3],
+            ]);
+        }
+        for i in 16..80 {
+            words[i] = (words[i - 3]
+                ^ words[i - 8]
+                ^ words[i - 14]
+                ^ words[i - 16])
+                .rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = state;
+        for (i, &word) in words.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+        for (value, add) in state.iter_mut().zip([a, b, c, d, e]) {
+            *value = value.wrapping_add(add);
+        }
+    }
+
+    let mut digest = [0; 20];
+    for (bytes, value) in digest.chunks_mut(4).zip(state.iter()) {
+        bytes.copy_from_slice(&value.to_be_bytes());
+    }
+    digest
+}
+
+/// A client connection taken over after a WebSocket handshake.
+///
+/// Reading first returns any bytes the client sent right after its handshake request,
+/// which the server had already buffered, and then reads from the connection itself.
+/// Writes go straight to the connection. The connection has no read timeout; set
+/// deadlines in the protocol if needed. It is closed when the handler returns.
+///
+/// The server waits for handlers when shutting down, so a handler keeping a connection
+/// open should check [`UpgradedStream::shutdown_handle`] regularly and return once it
+/// is stopped.
+pub struct UpgradedStream {
+    /// Bytes read past the handshake request, not yet returned.
+    buffered: io::Cursor<Vec<u8>>,
+    /// The client connection.
+    stream: Box<dyn ReadWrite>,
+    /// The handle signalling that the server is shutting down.
+    shutdown: ShutdownHandle,
+}
+
+/// A connection that can be both read and written.
+pub(crate) trait ReadWrite: Read + Write + Send {}
+
+impl<T: Read + Write + Send> ReadWrite for T {}
+
+impl UpgradedStream {
+    /// Wraps an upgraded connection.
+    ///
+    /// # Arguments
+    ///
+    /// * `buffered` - The bytes already read past the handshake request.
+    /// * `stream` - The client connection.
+    /// * `shutdown` - The handle signalling that the server is shutting down.
+    pub(crate) fn new(
+        buffered: Vec<u8>,
+        stream: Box<dyn ReadWrite>,
+        shutdown: ShutdownHandle,
+    ) -> Self {
+        UpgradedStream {
+            buffered: io::Cursor::new(buffered),
+            stream,
+            shutdown,
+        }
+    }
+
+    /// Returns the handle of the server that accepted the connection.
+    ///
+    /// # Returns
+    ///
+    /// The handle, stopped once the server is shutting down.
+    pub fn shutdown_handle(&self) -> &ShutdownHandle {
+        &self.shutdown
+    }
+}
+
+impl Read for UpgradedStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.buffered.read(buf)? {
+            0 => self.stream.read(buf),
+            read => Ok(read),
+        }
+    }
+}
+
+impl Write for UpgradedStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stream.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl fmt::Debug for UpgradedStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pending = self.buffered.get_ref().len() as u64
+            - self.buffered.position();
+        f.debug_struct("UpgradedStream")
+            .field("pending", &pending)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn upgrade_request(key: &str) -> Request {
+        let mut request = Request::new("GET", "/chat", "HTTP/1.1");
+        request.add_header("Host", "server.example.com");
+        request.add_header("Upgrade", "websocket");
+        request.add_header("Connection", "keep-alive, Upgrade");
+        request.add_header("Sec-WebSocket-Key", key);
+        request.add_header("Sec-WebSocket-Version", "13");
+        request
+    }
+
+    #[test]
+    fn test_accept_key() {
+        // RFC 6455, section 1.3
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg==");
+        assert_eq!(base64_encode(b"fo"), "Zm8=");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(
+            sha1(b"abc"),
+            [
+                0xa9, 0x99, 0x3e, 0x36, 0x47, 0x06, 0x81, 0x6a, 0xba,
+                0x3e, 0x25, 0x71, 0x78, 0x50, 0xc2, 0x6c, 0x9c, 0xd0,
+                0xd8, 0x9d
+            ]
+        );
+    }
+
+    #[test]
+    fn test_handshake_response() {
+        let response = handshake_response(&upgrade_request(
+            "dGhlIHNhbXBsZSBub25jZQ==",
+        ))
+        .unwrap();
+        assert_eq!(response.status_code, 101);
+        assert!(response.headers.contains(&(
+            "Sec-WebSocket-Accept".to_string(),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=".to_string()
+        )));
+
+        for key in [
+            "",
+            "short==",
+            "dGhlIHNhbXBsZSBub25jZQ",
+            "dGhlIHNhbXBsZSBub25j!Q==",
+        ] {
+            assert!(handshake_response(&upgrade_request(key)).is_err());
+        }
+        let mut request = upgrade_request("dGhlIHNhbXBsZSBub25jZQ==");
+        request
+            .headers
+            .retain(|(name, _)| name != "Sec-WebSocket-Version");
+        assert!(handshake_response(&request).is_err());
+        assert!(!is_upgrade_request(&Request::new(
+            "GET", "/chat", "HTTP/1.1"
+        )));
+    }
+}